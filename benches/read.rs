@@ -0,0 +1,89 @@
+#![feature(test)]
+
+extern crate test;
+
+use actix::{Addr, System, SystemRunner};
+use futures::future;
+use tempfile::{tempdir, TempDir};
+use test::Bencher;
+
+use weid::config::Config;
+use weid::db::{self, ArticleContent, Db, Query};
+
+const ARTICLES: usize = 1000;
+const REQUESTS: usize = 64;
+
+fn open(workers: usize) -> (TempDir, SystemRunner, Addr<Db>) {
+  let dir = tempdir().unwrap();
+  let config = Config {
+    db_path: dir.path().to_owned(),
+    map_size: 1 << 28,
+    workers,
+    ..Default::default()
+  };
+  let mut system = System::new("bench");
+  let db = Db::open(&config).unwrap();
+  for i in 0..ARTICLES {
+    let title = format!("On the Art of Computer Programming, Part {}", i);
+    let content = ArticleContent::new(&title, ["Donald E. Knuth"]).unwrap();
+    system
+      .block_on(db.send(db::Add::new(content, None)))
+      .unwrap()
+      .unwrap();
+  }
+  (dir, system, db)
+}
+
+fn search(b: &mut Bencher, workers: usize) {
+  let (_dir, mut system, db) = open(workers);
+  let query = Query::parse("author:knuth programming").unwrap();
+  b.iter(|| {
+    let requests = future::lazy(|| {
+      future::join_all((0..REQUESTS).map(|_| db.send(db::Search::new(query.clone()))))
+    });
+    for found in system.block_on(requests).unwrap() {
+      assert_eq!(found.unwrap().len(), ARTICLES);
+    }
+  });
+}
+
+fn get(b: &mut Bencher, workers: usize) {
+  let (_dir, mut system, db) = open(workers);
+  b.iter(|| {
+    let requests =
+      future::lazy(|| future::join_all((0..REQUESTS as u64).map(|id| db.send(db::Get::new(id)))));
+    for article in system.block_on(requests).unwrap() {
+      article.unwrap();
+    }
+  });
+}
+
+#[bench]
+fn bench_search_1_worker(b: &mut Bencher) {
+  search(b, 1);
+}
+
+#[bench]
+fn bench_search_2_workers(b: &mut Bencher) {
+  search(b, 2);
+}
+
+#[bench]
+fn bench_search_4_workers(b: &mut Bencher) {
+  search(b, 4);
+}
+
+#[bench]
+fn bench_search_8_workers(b: &mut Bencher) {
+  search(b, 8);
+}
+
+#[bench]
+fn bench_get_1_worker(b: &mut Bencher) {
+  get(b, 1);
+}
+
+#[bench]
+fn bench_get_4_workers(b: &mut Bencher) {
+  get(b, 4);
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::latex::escape;
+use crate::db::{family_name, fold, Article, ArticleContent};
+
+const STOPWORDS: &[&str] = &[
+  "a", "an", "and", "at", "for", "from", "in", "of", "on", "the", "to", "with",
+];
+
+fn escape_title(title: &str) -> String {
+  title
+    .split(' ')
+    .map(|word| {
+      let escaped = escape(word);
+      if word.chars().skip(1).any(char::is_uppercase) {
+        format!("{{{}}}", escaped)
+      } else {
+        escaped
+      }
+    })
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// Folds a word into the ASCII letters and digits that a key may contain.
+fn fold_ascii(word: &str) -> String {
+  fold(word).chars().filter(char::is_ascii).collect()
+}
+
+pub fn key(content: &ArticleContent) -> String {
+  let author = content
+    .authors
+    .first()
+    .map(|a| fold_ascii(family_name(a)))
+    .filter(|a| !a.is_empty())
+    .unwrap_or_else(|| "anon".to_owned());
+  let year = content.year.map_or(String::new(), |y| y.to_string());
+  let word = content
+    .title
+    .split(|c: char| c.is_whitespace() || c == '-')
+    .map(fold_ascii)
+    .find(|w| !w.is_empty() && !STOPWORDS.contains(&w.as_str()))
+    .unwrap_or_default();
+  format!("{}{}{}", author, year, word)
+}
+
+fn field(entry: &mut String, name: &str, val: &str) {
+  let _ = writeln!(entry, "  {} = {{{}}},", name, val);
+}
+
+pub fn entry(key: &str, content: &ArticleContent) -> String {
+  let kind = if content.venue.is_some() {
+    "article"
+  } else {
+    "misc"
+  };
+  let mut entry = format!("@{}{{{},\n", kind, key);
+  field(&mut entry, "title", &escape_title(&content.title));
+  if !content.authors.is_empty() {
+    let authors: Vec<_> = content.authors.iter().map(|a| escape(a)).collect();
+    field(&mut entry, "author", &authors.join(" and "));
+  }
+  if let Some(year) = content.year {
+    field(&mut entry, "year", &year.to_string());
+  }
+  if let Some(venue) = &content.venue {
+    field(&mut entry, "journal", &escape(venue));
+  }
+  if let Some(volume) = &content.volume {
+    field(&mut entry, "volume", &escape(volume));
+  }
+  if let Some(pages) = &content.pages {
+    let pages = pages.replace("--", "-").replace('-', "--");
+    field(&mut entry, "pages", &escape(&pages));
+  }
+  if let Some(doi) = &content.doi {
+    field(&mut entry, "doi", &escape(doi));
+  }
+  if let Some(arxiv) = &content.arxiv {
+    field(&mut entry, "eprint", &escape(arxiv));
+    field(&mut entry, "archiveprefix", "arXiv");
+  }
+  if let Some(abstract_) = &content.abstract_ {
+    field(&mut entry, "abstract", &escape(abstract_));
+  }
+  if !content.keywords.is_empty() {
+    let keywords: Vec<_> = content.keywords.iter().map(|k| escape(k)).collect();
+    field(&mut entry, "keywords", &keywords.join(", "));
+  }
+  if let Some(note) = &content.note {
+    field(&mut entry, "note", &escape(note));
+  }
+  entry.push_str("}\n");
+  entry
+}
+
+fn suffix(mut n: usize) -> String {
+  let mut letters = Vec::new();
+  while n > 0 {
+    n -= 1;
+    letters.push((b'a' + (n % 26) as u8) as char);
+    n /= 26;
+  }
+  letters.into_iter().rev().collect()
+}
+
+pub fn render(articles: &[Article]) -> String {
+  let mut ids: Vec<_> = articles
+    .iter()
+    .map(|a| (a.id(), key(a.content())))
+    .collect();
+  ids.sort();
+  let mut count = HashMap::new();
+  for (_, key) in ids.iter() {
+    *count.entry(key.clone()).or_insert(0) += 1;
+  }
+  let mut seen = HashMap::new();
+  let mut keys = HashMap::new();
+  for (id, key) in ids {
+    let key = if count[&key] > 1 {
+      let n = seen.entry(key.clone()).or_insert(0);
+      *n += 1;
+      format!("{}{}", key, suffix(*n))
+    } else {
+      key
+    };
+    keys.insert(id, key);
+  }
+  articles
+    .iter()
+    .map(|a| entry(&keys[&a.id()], a.content()))
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::path::PathBuf;
+
+  #[test]
+  fn test_key() {
+    let content = ArticleContent {
+      year: Some(1984),
+      ..ArticleContent::new("The Literate Programming", ["Donald E. Knuth"]).unwrap()
+    };
+    assert_eq!(key(&content), "knuth1984literate");
+    let content = ArticleContent::new("Über Gödel's Theorem", ["Gödel, Kurt"]).unwrap();
+    assert_eq!(key(&content), "godeluber");
+    let content = ArticleContent::new("A", &[] as &[&str]).unwrap();
+    assert_eq!(key(&content), "anon");
+  }
+
+  #[test]
+  fn test_entry() {
+    let content = ArticleContent {
+      year: Some(1984),
+      venue: Some("The Computer Journal".to_owned()),
+      pages: Some("97-111".to_owned()),
+      doi: Some("10.1093/comjnl/27.2.97".to_owned()),
+      ..ArticleContent::new(
+        "Literate Programming & TeX_50%",
+        ["Donald E. Knuth", "Erdős, Paul"],
+      )
+      .unwrap()
+    };
+    assert_eq!(
+      entry("knuth1984literate", &content),
+      "@article{knuth1984literate,
+  title = {Literate Programming \\& {TeX\\_50\\%}},
+  author = {Donald E. Knuth and Erd{\\H o}s, Paul},
+  year = {1984},
+  journal = {The Computer Journal},
+  pages = {97--111},
+  doi = {10.1093/comjnl/27.2.97},
+}
+"
+    );
+    let content = ArticleContent {
+      doi: Some("10.1000/a_b%c".to_owned()),
+      ..ArticleContent::new("Title", ["Knuth"]).unwrap()
+    };
+    assert!(entry("knuth", &content).contains("  doi = {10.1000/a\\_b\\%c},\n"));
+  }
+
+  #[test]
+  fn test_suffix() {
+    assert_eq!(suffix(1), "a");
+    assert_eq!(suffix(26), "z");
+    assert_eq!(suffix(27), "aa");
+  }
+
+  #[test]
+  fn test_render() {
+    let article = |id, title: &str| {
+      Article::new(
+        PathBuf::new(),
+        id,
+        ArticleContent::new(title, ["Knuth"]).unwrap(),
+      )
+    };
+    let rendered = render(&[
+      article(3, "Literate Programming"),
+      article(1, "Literate Programming"),
+      article(2, "The Art"),
+    ]);
+    let keys: Vec<_> = rendered.lines().filter(|l| l.starts_with('@')).collect();
+    assert_eq!(
+      keys,
+      vec![
+        "@misc{knuthliterateb,",
+        "@misc{knuthliteratea,",
+        "@misc{knuthart,"
+      ]
+    );
+  }
+}
@@ -0,0 +1,217 @@
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::str::Chars;
+
+const ACCENTED: &str = "áéíóúýÁÉÍÓÚÝćńśźàèìòùÀÈÌÒÙâêîôûÂÊÎÔÛäëïöüÿÄËÏÖÜãñõÃÑÕçÇşŞåÅčěňřšžČŘŠŽőűŐŰ";
+const LIGATURES: &str = "ßæÆœŒøØłŁı";
+
+fn accent(c: char) -> Option<(char, char)> {
+  Some(match c {
+    'á' | 'é' | 'í' | 'ó' | 'ú' | 'ý' | 'Á' | 'É' | 'Í' | 'Ó' | 'Ú' | 'Ý' | 'ć' | 'ń' | 'ś'
+    | 'ź' => ('\'', base(c)),
+    'à' | 'è' | 'ì' | 'ò' | 'ù' | 'À' | 'È' | 'Ì' | 'Ò' | 'Ù' => ('`', base(c)),
+    'â' | 'ê' | 'î' | 'ô' | 'û' | 'Â' | 'Ê' | 'Î' | 'Ô' | 'Û' => ('^', base(c)),
+    'ä' | 'ë' | 'ï' | 'ö' | 'ü' | 'ÿ' | 'Ä' | 'Ë' | 'Ï' | 'Ö' | 'Ü' => ('"', base(c)),
+    'ã' | 'ñ' | 'õ' | 'Ã' | 'Ñ' | 'Õ' => ('~', base(c)),
+    'ç' | 'Ç' | 'ş' | 'Ş' => ('c', base(c)),
+    'å' | 'Å' => ('r', base(c)),
+    'č' | 'ě' | 'ň' | 'ř' | 'š' | 'ž' | 'Č' | 'Ř' | 'Š' | 'Ž' => ('v', base(c)),
+    'ő' | 'ű' | 'Ő' | 'Ű' => ('H', base(c)),
+    _ => return None,
+  })
+}
+
+pub fn base(c: char) -> char {
+  match c {
+    'á' | 'à' | 'â' | 'ä' | 'ã' | 'å' => 'a',
+    'Á' | 'À' | 'Â' | 'Ä' | 'Ã' | 'Å' => 'A',
+    'é' | 'è' | 'ê' | 'ë' | 'ě' => 'e',
+    'É' | 'È' | 'Ê' | 'Ë' => 'E',
+    'í' | 'ì' | 'î' | 'ï' => 'i',
+    'Í' | 'Ì' | 'Î' | 'Ï' => 'I',
+    'ó' | 'ò' | 'ô' | 'ö' | 'õ' | 'ő' => 'o',
+    'Ó' | 'Ò' | 'Ô' | 'Ö' | 'Õ' | 'Ő' => 'O',
+    'ú' | 'ù' | 'û' | 'ü' | 'ű' => 'u',
+    'Ú' | 'Ù' | 'Û' | 'Ü' | 'Ű' => 'U',
+    'ý' | 'ÿ' => 'y',
+    'Ý' => 'Y',
+    'ñ' | 'ń' | 'ň' => 'n',
+    'Ñ' => 'N',
+    'ç' | 'ć' | 'č' => 'c',
+    'Ç' | 'Č' => 'C',
+    'ş' | 'ś' | 'š' => 's',
+    'Ş' | 'Š' => 'S',
+    'ź' | 'ž' => 'z',
+    'Ž' => 'Z',
+    'ř' => 'r',
+    'Ř' => 'R',
+    c => c,
+  }
+}
+
+pub fn ligature(c: char) -> Option<&'static str> {
+  Some(match c {
+    'ß' => "ss",
+    'æ' => "ae",
+    'Æ' => "AE",
+    'œ' => "oe",
+    'Œ' => "OE",
+    'ø' => "o",
+    'Ø' => "O",
+    'ł' => "l",
+    'Ł' => "L",
+    'ı' => "i",
+    _ => return None,
+  })
+}
+
+pub fn escape(val: &str) -> String {
+  let mut escaped = String::with_capacity(val.len());
+  for c in val.chars() {
+    match c {
+      '\\' => escaped.push_str("\\textbackslash{}"),
+      '~' => escaped.push_str("\\textasciitilde{}"),
+      '^' => escaped.push_str("\\textasciicircum{}"),
+      '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+        escaped.push('\\');
+        escaped.push(c);
+      }
+      c => match (accent(c), ligature(c)) {
+        (Some((cmd, base)), _) if cmd.is_alphabetic() => {
+          let _ = write!(escaped, "{{\\{} {}}}", cmd, base);
+        }
+        (Some((cmd, base)), _) => {
+          let _ = write!(escaped, "{{\\{}{}}}", cmd, base);
+        }
+        (None, Some(cmd)) => {
+          let _ = write!(escaped, "{{\\{}}}", cmd);
+        }
+        (None, None) => escaped.push(c),
+      },
+    }
+  }
+  escaped
+}
+
+fn command(chars: &mut Peekable<Chars>) -> String {
+  let mut name = String::new();
+  match chars.next() {
+    Some(c) if c.is_ascii_alphabetic() => {
+      name.push(c);
+      while let Some(&c) = chars.peek() {
+        if !c.is_ascii_alphabetic() {
+          break;
+        }
+        name.push(c);
+        chars.next();
+      }
+      if chars.peek() == Some(&' ') {
+        chars.next();
+      }
+    }
+    Some(c) => name.push(c),
+    None => {}
+  }
+  name
+}
+
+fn argument(chars: &mut Peekable<Chars>) -> Option<char> {
+  while let Some(&c) = chars.peek() {
+    match c {
+      '{' | ' ' => {
+        chars.next();
+      }
+      '\\' => {
+        chars.next();
+        return match command(chars).as_str() {
+          "i" => Some('i'),
+          "j" => Some('j'),
+          name => LIGATURES.chars().find(|&l| ligature(l) == Some(name)),
+        };
+      }
+      _ => return chars.next(),
+    }
+  }
+  None
+}
+
+pub fn decode(raw: &str) -> String {
+  let mut decoded = String::with_capacity(raw.len());
+  let mut chars = raw.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '{' | '}' | '$' => {}
+      '~' => decoded.push(' '),
+      '-' if chars.peek() == Some(&'-') => {
+        chars.next();
+        if chars.peek() == Some(&'-') {
+          chars.next();
+          decoded.push('—');
+        } else {
+          decoded.push('–');
+        }
+      }
+      '\\' => {
+        let name = command(&mut chars);
+        let mut cmd = name.chars();
+        match (cmd.next(), cmd.next()) {
+          (Some(c), None) if "&%$#_{} ".contains(c) => decoded.push(c),
+          (Some(c), None) if "'`^\"~crvH".contains(c) => {
+            if let Some(base) = argument(&mut chars) {
+              decoded.push(
+                ACCENTED
+                  .chars()
+                  .find(|&a| accent(a) == Some((c, base)))
+                  .unwrap_or(base),
+              );
+            }
+            if chars.peek() == Some(&'}') {
+              chars.next();
+            }
+          }
+          _ => match name.as_str() {
+            "textbackslash" => decoded.push('\\'),
+            "textasciitilde" => decoded.push('~'),
+            "textasciicircum" => decoded.push('^'),
+            name => decoded.extend(LIGATURES.chars().find(|&l| ligature(l) == Some(name))),
+          },
+        }
+      }
+      c if c.is_whitespace() => {
+        if !decoded.ends_with(' ') {
+          decoded.push(' ');
+        }
+      }
+      c => decoded.push(c),
+    }
+  }
+  decoded.trim().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_decode() {
+    for val in &[
+      "Erdős & Gödel: 50% of C_1 {x}",
+      "Łukasiewicz ~ 2^n \\ ß",
+      "Čech, Ångström, Ñandú",
+    ] {
+      assert_eq!(decode(&escape(val)), *val);
+    }
+    assert_eq!(
+      decode(r#"G\"{o}del and \'Eva {\v{C}}ech"#),
+      "Gödel and Éva Čech"
+    );
+    assert_eq!(
+      decode("{The {TeX}book}\n  pages 1--10"),
+      "The TeXbook pages 1–10"
+    );
+    assert_eq!(
+      decode(r#"\emph{Na\"\i ve} $\alpha$ hyphen\-ation"#),
+      "Naïve hyphenation"
+    );
+  }
+}
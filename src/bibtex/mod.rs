@@ -0,0 +1,5 @@
+mod export;
+mod latex;
+
+pub use self::export::*;
+pub use self::latex::{base, decode, ligature};
@@ -0,0 +1,437 @@
+use std::path::PathBuf;
+
+use failure::Error;
+use tempfile::NamedTempFile;
+
+use weid::config::Config;
+use weid::db::{self, Store};
+use weid::{bibtex, import, pdf};
+
+pub const USAGE: &str = "Usage: weid [COMMAND]
+
+Commands:
+  serve                      Start the HTTP server (default)
+  add FILE [OPTIONS]         Add an article with FILE attached
+      --title TITLE  --author AUTHOR...  --year YEAR  --venue VENUE
+      --volume VOLUME  --pages PAGES  --doi DOI  --arxiv ID
+      --abstract TEXT  --keyword KEYWORD...  --note NOTE
+  search [--json] QUERY      Search articles
+  rm ID...                   Remove articles
+  export [QUERY]             Print BibTeX of the matched or all articles
+  import FILE                Import a BibTeX or RIS file
+  fsck [--repair]            Check and repair the database
+  reindex                    Regenerate the indices from the database
+  help                       Print this message";
+
+#[derive(Debug, Fail, PartialEq)]
+pub enum CliError {
+  #[fail(display = "Unknown command '{}'.", _0)]
+  UnknownCommand(String),
+  #[fail(display = "Unknown option '{}'.", _0)]
+  UnknownOption(String),
+  #[fail(display = "The option '{}' needs a value.", _0)]
+  MissingValue(String),
+  #[fail(display = "The value '{}' of '{}' is invalid.", _1, _0)]
+  InvalidValue(String, String),
+  #[fail(display = "{} is required.", _0)]
+  MissingArgument(&'static str),
+  #[fail(display = "Unexpected argument '{}'.", _0)]
+  UnexpectedArgument(String),
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct AddArgs {
+  file: PathBuf,
+  title: Option<String>,
+  authors: Vec<String>,
+  year: Option<u16>,
+  venue: Option<String>,
+  volume: Option<String>,
+  pages: Option<String>,
+  doi: Option<String>,
+  arxiv: Option<String>,
+  abstract_: Option<String>,
+  keywords: Vec<String>,
+  note: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+  Serve,
+  Add(Box<AddArgs>),
+  Search { query: String, json: bool },
+  Remove(Vec<u64>),
+  Export(Option<String>),
+  Import(PathBuf),
+  Fsck { repair: bool },
+  Reindex,
+  Help,
+}
+
+struct Args {
+  args: std::vec::IntoIter<String>,
+  pending: Option<String>,
+}
+
+impl Args {
+  fn next(&mut self) -> Option<String> {
+    self.pending.take().or_else(|| self.args.next())
+  }
+
+  fn option(&mut self) -> Option<Result<String, String>> {
+    let arg = self.next()?;
+    if !arg.starts_with("--") {
+      return Some(Err(arg));
+    }
+    Some(Ok(match arg.find('=') {
+      Some(i) => {
+        self.pending = Some(arg[i + 1..].to_owned());
+        arg[..i].to_owned()
+      }
+      None => arg,
+    }))
+  }
+
+  fn value(&mut self, name: &str) -> Result<String, CliError> {
+    self
+      .next()
+      .ok_or_else(|| CliError::MissingValue(name.to_owned()))
+  }
+
+  fn positionals(&mut self, flags: &[&str]) -> Result<(Vec<String>, Vec<String>), CliError> {
+    let (mut found, mut set) = (Vec::new(), Vec::new());
+    while let Some(arg) = self.option() {
+      match arg {
+        Ok(ref name) if flags.contains(&name.as_str()) => set.push(name.clone()),
+        Ok(name) => return Err(CliError::UnknownOption(name)),
+        Err(arg) => found.push(arg),
+      }
+    }
+    Ok((found, set))
+  }
+}
+
+fn parse_value<T: std::str::FromStr>(name: &str, val: String) -> Result<T, CliError> {
+  val
+    .parse()
+    .map_err(|_| CliError::InvalidValue(name.to_owned(), val))
+}
+
+fn parse_add(args: &mut Args) -> Result<AddArgs, CliError> {
+  let mut parsed = AddArgs::default();
+  let mut file = None;
+  while let Some(arg) = args.option() {
+    let name = match arg {
+      Ok(name) => name,
+      Err(arg) => match file {
+        None => {
+          file = Some(PathBuf::from(arg));
+          continue;
+        }
+        Some(_) => return Err(CliError::UnexpectedArgument(arg)),
+      },
+    };
+    let val = args.value(&name)?;
+    match name.as_str() {
+      "--title" => parsed.title = Some(val),
+      "--author" => parsed.authors.push(val),
+      "--year" => parsed.year = Some(parse_value(&name, val)?),
+      "--venue" => parsed.venue = Some(val),
+      "--volume" => parsed.volume = Some(val),
+      "--pages" => parsed.pages = Some(val),
+      "--doi" => parsed.doi = Some(val),
+      "--arxiv" => parsed.arxiv = Some(val),
+      "--abstract" => parsed.abstract_ = Some(val),
+      "--keyword" => parsed.keywords.push(val),
+      "--note" => parsed.note = Some(val),
+      _ => return Err(CliError::UnknownOption(name)),
+    }
+  }
+  parsed.file = file.ok_or(CliError::MissingArgument("FILE"))?;
+  Ok(parsed)
+}
+
+impl Command {
+  pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, CliError> {
+    let mut args = Args {
+      args: args.into_iter().collect::<Vec<_>>().into_iter(),
+      pending: None,
+    };
+    let command = match args.next() {
+      None => return Ok(Command::Serve),
+      Some(command) => command,
+    };
+    let command = match command.as_str() {
+      "serve" => Command::Serve,
+      "add" => return Ok(Command::Add(Box::new(parse_add(&mut args)?))),
+      "search" => {
+        let (words, flags) = args.positionals(&["--json"])?;
+        if words.is_empty() {
+          return Err(CliError::MissingArgument("QUERY"));
+        }
+        return Ok(Command::Search {
+          query: words.join(" "),
+          json: !flags.is_empty(),
+        });
+      }
+      "rm" => {
+        let (ids, _) = args.positionals(&[])?;
+        if ids.is_empty() {
+          return Err(CliError::MissingArgument("ID"));
+        }
+        return Ok(Command::Remove(
+          ids
+            .into_iter()
+            .map(|id| parse_value("ID", id))
+            .collect::<Result<_, _>>()?,
+        ));
+      }
+      "export" => {
+        let (words, _) = args.positionals(&[])?;
+        return Ok(Command::Export(
+          Some(words.join(" ")).filter(|q| !q.is_empty()),
+        ));
+      }
+      "import" => {
+        let (mut files, _) = args.positionals(&[])?;
+        if files.len() > 1 {
+          return Err(CliError::UnexpectedArgument(files.remove(1)));
+        }
+        let file = files.pop().ok_or(CliError::MissingArgument("FILE"))?;
+        return Ok(Command::Import(PathBuf::from(file)));
+      }
+      "fsck" => {
+        let (_, flags) = args.positionals(&["--repair"])?;
+        return Ok(Command::Fsck {
+          repair: !flags.is_empty(),
+        });
+      }
+      "reindex" => Command::Reindex,
+      "help" | "--help" | "-h" => Command::Help,
+      _ => return Err(CliError::UnknownCommand(command)),
+    };
+    match args.next() {
+      Some(arg) => Err(CliError::UnexpectedArgument(arg)),
+      None => Ok(command),
+    }
+  }
+}
+
+fn add(args: AddArgs, store: &mut Store) -> Result<(), Error> {
+  let file = NamedTempFile::new()?;
+  std::fs::copy(&args.file, file.path())?;
+  let filename = args
+    .file
+    .file_name()
+    .map(|name| name.to_string_lossy().into_owned());
+  let upload = db::Upload::new(file, filename)?;
+  let param = pdf::Fields {
+    title: args.title,
+    authors: Some(args.authors).filter(|a| !a.is_empty()),
+    year: args.year,
+    venue: args.venue,
+    volume: args.volume,
+    pages: args.pages,
+    doi: args.doi,
+    arxiv: args.arxiv,
+    abstract_: args.abstract_,
+    keywords: Some(args.keywords).filter(|k| !k.is_empty()),
+    note: args.note,
+  };
+  let meta = if upload.media_type() == db::MediaType::Pdf && param.inferable() {
+    pdf::extract_metadata(upload.file().path()).unwrap_or_else(|e| {
+      warn!("Failed to read the metadata of the PDF: {}", e);
+      Default::default()
+    })
+  } else {
+    Default::default()
+  };
+  let (content, _) = pdf::infer(param, meta)?;
+  let article = store.add(content, Some(upload))?;
+  store.index_text(&article)?;
+  println!("{}", serde_json::to_string(&article)?);
+  Ok(())
+}
+
+fn print_articles(articles: &[db::Article]) {
+  for article in articles {
+    let content = article.content();
+    println!(
+      "{}\t{}\t{}\t{}",
+      article.id(),
+      content.year.map_or(String::new(), |y| y.to_string()),
+      content.title,
+      content.authors.join(", ")
+    );
+  }
+}
+
+fn fsck(config: &Config, repair: bool) -> Result<(), Error> {
+  let problems = Store::fsck(config, repair)?;
+  for problem in problems.iter() {
+    let status = match (repair, problem.repairable()) {
+      (_, false) => " [unrepairable]",
+      (true, true) => " [repaired]",
+      (false, true) => "",
+    };
+    println!("{}{}", problem, status);
+  }
+  let remaining = problems
+    .iter()
+    .filter(|p| !repair || !p.repairable())
+    .count();
+  println!(
+    "{} problems found, {} remaining.",
+    problems.len(),
+    remaining
+  );
+  if remaining > 0 {
+    std::process::exit(1);
+  }
+  Ok(())
+}
+
+fn reindex(config: &Config) -> Result<(), Error> {
+  let reindexed = Store::reindex(config, |p| {
+    eprint!("\r{}: {}/{}", p.stage, p.done, p.total);
+    if p.done == p.total {
+      eprintln!();
+    }
+  })?;
+  println!("{}", serde_json::to_string(&reindexed)?);
+  Ok(())
+}
+
+pub fn run(config: &Config, command: Command) -> Result<(), Error> {
+  match command {
+    Command::Serve => unreachable!(),
+    Command::Help => {
+      println!("{}", USAGE);
+      return Ok(());
+    }
+    Command::Fsck { repair } => return fsck(config, repair),
+    Command::Reindex => return reindex(config),
+    _ => {}
+  }
+  let mut store = Store::open(config)?;
+  match command {
+    Command::Add(args) => add(*args, &mut store)?,
+    Command::Search { query, json } => {
+      let articles = store.search(&db::Query::parse(query)?)?;
+      if json {
+        println!("{}", serde_json::to_string(&articles)?);
+      } else {
+        print_articles(&articles);
+      }
+    }
+    Command::Remove(ids) => {
+      for id in ids {
+        print_articles(&[store.remove(id)?]);
+      }
+    }
+    Command::Export(query) => {
+      let articles = match query {
+        Some(query) => store.search(&db::Query::parse(query)?)?,
+        None => store
+          .list(0, usize::MAX, db::Sort::Id, db::Order::Asc)?
+          .into_articles(),
+      };
+      print!("{}", bibtex::render(&articles));
+    }
+    Command::Import(path) => {
+      let filename = path.file_name().and_then(|name| name.to_str());
+      let entries = import::read(filename, &path)?;
+      let imported = store.import(import::contents(&entries))?;
+      let report = import::report(entries, imported)?;
+      println!("{}", serde_json::to_string(&report)?);
+    }
+    Command::Serve | Command::Help | Command::Fsck { .. } | Command::Reindex => {}
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(args: &[&str]) -> Result<Command, CliError> {
+    Command::parse(args.iter().map(|&a| a.to_owned()))
+  }
+
+  #[test]
+  fn test_parse() {
+    assert_eq!(parse(&[]), Ok(Command::Serve));
+    assert_eq!(parse(&["serve"]), Ok(Command::Serve));
+    assert_eq!(
+      parse(&[
+        "add",
+        "paper.pdf",
+        "--title",
+        "Literate Programming",
+        "--author=Knuth",
+        "--author",
+        "Lamport",
+        "--year",
+        "1984",
+      ]),
+      Ok(Command::Add(Box::new(AddArgs {
+        file: PathBuf::from("paper.pdf"),
+        title: Some("Literate Programming".to_owned()),
+        authors: vec!["Knuth".to_owned(), "Lamport".to_owned()],
+        year: Some(1984),
+        ..Default::default()
+      })))
+    );
+    assert_eq!(
+      parse(&["search", "--json", "title:literate", "knuth"]),
+      Ok(Command::Search {
+        query: "title:literate knuth".to_owned(),
+        json: true,
+      })
+    );
+    assert_eq!(parse(&["rm", "3", "5"]), Ok(Command::Remove(vec![3, 5])));
+    assert_eq!(parse(&["export"]), Ok(Command::Export(None)));
+    assert_eq!(
+      parse(&["import", "refs.bib"]),
+      Ok(Command::Import(PathBuf::from("refs.bib")))
+    );
+    assert_eq!(
+      parse(&["fsck", "--repair"]),
+      Ok(Command::Fsck { repair: true })
+    );
+    assert_eq!(parse(&["reindex"]), Ok(Command::Reindex));
+
+    assert_eq!(
+      parse(&["nope"]),
+      Err(CliError::UnknownCommand("nope".to_owned()))
+    );
+    assert_eq!(parse(&["add"]), Err(CliError::MissingArgument("FILE")));
+    assert_eq!(
+      parse(&["add", "a.pdf", "--title"]),
+      Err(CliError::MissingValue("--title".to_owned()))
+    );
+    assert_eq!(
+      parse(&["add", "a.pdf", "--year", "soon"]),
+      Err(CliError::InvalidValue(
+        "--year".to_owned(),
+        "soon".to_owned()
+      ))
+    );
+    assert_eq!(
+      parse(&["add", "a.pdf", "b.pdf"]),
+      Err(CliError::UnexpectedArgument("b.pdf".to_owned()))
+    );
+    assert_eq!(
+      parse(&["rm", "x"]),
+      Err(CliError::InvalidValue("ID".to_owned(), "x".to_owned()))
+    );
+    assert_eq!(
+      parse(&["search", "--limit", "3"]),
+      Err(CliError::UnknownOption("--limit".to_owned()))
+    );
+    assert_eq!(
+      parse(&["reindex", "now"]),
+      Err(CliError::UnexpectedArgument("now".to_owned()))
+    );
+  }
+}
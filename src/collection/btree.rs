@@ -0,0 +1,831 @@
+use super::{Mem, Violation};
+use failure::Error;
+use memmap::MmapMut;
+use std::cmp::Ord;
+use std::fs::{File, OpenOptions};
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+
+/// The maximum number of keys in a page.
+const MAX: usize = 63;
+/// The minimum number of keys in a page other than the root.
+const MIN: usize = MAX / 2;
+/// The root is always the first page, so that the header only has to track
+/// the free pages.
+const ROOT: u64 = 0;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Page<K, V> {
+  leaf: bool,
+  len: u32,
+  // The next leaf in key order, or the next free page.
+  next: Option<u64>,
+  keys: [K; MAX],
+  // Only used in leaves.
+  vals: [V; MAX],
+  // Only used in internal pages.
+  children: [u64; MAX + 1],
+}
+
+impl<K: Default + Ord + Copy, V: Default + Copy> Page<K, V> {
+  fn empty(leaf: bool) -> Self {
+    Self {
+      leaf,
+      len: 0,
+      next: None,
+      keys: [K::default(); MAX],
+      vals: [V::default(); MAX],
+      children: [0; MAX + 1],
+    }
+  }
+
+  #[inline]
+  fn len(&self) -> usize {
+    self.len as usize
+  }
+
+  #[inline]
+  fn is_full(&self) -> bool {
+    self.len() == MAX
+  }
+
+  #[inline]
+  fn keys(&self) -> &[K] {
+    &self.keys[..self.len()]
+  }
+
+  /// The index of the child whose subtree may contain `key`.
+  fn child(&self, key: &K) -> usize {
+    match self.keys().binary_search(key) {
+      Ok(i) => i + 1,
+      Err(i) => i,
+    }
+  }
+
+  fn insert(&mut self, i: usize, key: K, val: V) {
+    let len = self.len();
+    self.keys.copy_within(i..len, i + 1);
+    self.vals.copy_within(i..len, i + 1);
+    self.keys[i] = key;
+    self.vals[i] = val;
+    self.len += 1;
+  }
+
+  fn remove(&mut self, i: usize) -> (K, V) {
+    let len = self.len();
+    let entry = (self.keys[i], self.vals[i]);
+    self.keys.copy_within(i + 1..len, i);
+    self.vals.copy_within(i + 1..len, i);
+    self.len -= 1;
+    entry
+  }
+
+  /// Inserts `key` at `i` with `child` to its right.
+  fn insert_child(&mut self, i: usize, key: K, child: u64) {
+    let len = self.len();
+    self.keys.copy_within(i..len, i + 1);
+    self.children.copy_within(i + 1..=len, i + 2);
+    self.keys[i] = key;
+    self.children[i + 1] = child;
+    self.len += 1;
+  }
+
+  /// Removes the key at `i` and the child to its right.
+  fn remove_child(&mut self, i: usize) {
+    let len = self.len();
+    self.keys.copy_within(i + 1..len, i);
+    self.children.copy_within(i + 2..=len, i + 1);
+    self.len -= 1;
+  }
+}
+
+#[repr(C)]
+struct BTreeMeta {
+  len: u64,
+  // The first free page, or `ROOT` if none is.
+  free: u64,
+}
+
+/// A B+tree map from `K` to `V` stored in pages of a [`Mem`], persisted in a
+/// file. Leaves are linked in key order for range scans.
+pub struct BTree<K, V = ()> {
+  file: File,
+  mem: Mem<Page<K, V>, BTreeMeta>,
+  capacity: u64,
+}
+
+impl<K: Default + Ord + Copy, V: Default + Copy> BTree<K, V> {
+  const LEAST_CAPACITY: u64 = 4096;
+
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+    let file = OpenOptions::new()
+      .create(true)
+      .truncate(false)
+      .write(true)
+      .read(true)
+      .open(path)?;
+    Self::create(file)
+  }
+
+  pub fn create(file: File) -> Result<Self, Error> {
+    debug_assert!(mem::size_of::<BTreeMeta>() <= mem::size_of::<Option<u64>>());
+    let len = file.metadata()?.len();
+    let capacity = if len < Self::LEAST_CAPACITY {
+      file.set_len(Self::LEAST_CAPACITY)?;
+      Self::LEAST_CAPACITY
+    } else {
+      len
+    };
+    let mem = Mem::new(unsafe { MmapMut::map_mut(&file)? });
+    let mut tree = Self {
+      file,
+      mem,
+      capacity,
+    };
+    if tree.mem.is_empty() {
+      tree.init()?;
+    }
+    Ok(tree)
+  }
+
+  fn init(&mut self) -> Result<(), Error> {
+    *self.mem.meta_mut() = BTreeMeta { len: 0, free: ROOT };
+    let root = self.new_page(true)?;
+    debug_assert_eq!(root, ROOT);
+    Ok(())
+  }
+
+  fn extend(&mut self) -> Result<(), Error> {
+    self.capacity *= 2;
+    self.file.set_len(self.capacity)?;
+    self.mem = Mem::new(unsafe { MmapMut::map_mut(&self.file)? });
+    Ok(())
+  }
+
+  pub fn clear(&mut self) -> Result<(), Error> {
+    self.mem.clear();
+    self.capacity = Self::LEAST_CAPACITY;
+    self.file.set_len(self.capacity)?;
+    self.mem = Mem::new(unsafe { MmapMut::map_mut(&self.file)? });
+    self.init()
+  }
+
+  pub fn flush(&self) -> Result<(), Error> {
+    self.mem.flush()?;
+    Ok(())
+  }
+
+  fn new_page(&mut self, leaf: bool) -> Result<u64, Error> {
+    let free = self.mem.meta().free;
+    let page = if free != ROOT {
+      self.mem.meta_mut().free = self.mem[free].next.unwrap_or(ROOT);
+      free
+    } else {
+      while self.mem.occupy() + mem::size_of::<Page<K, V>>() > self.capacity as usize {
+        self.extend()?;
+      }
+      self.mem.push()
+    };
+    self.mem[page] = Page::empty(leaf);
+    Ok(page)
+  }
+
+  fn free_page(&mut self, page: u64) {
+    let free = self.mem.meta().free;
+    self.mem[page].len = 0;
+    self.mem[page].next = Some(free).filter(|&f| f != ROOT);
+    self.mem.meta_mut().free = page;
+  }
+
+  #[inline]
+  pub fn len(&self) -> u64 {
+    self.mem.meta().len
+  }
+
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Checks the pages reachable from the root and the free list. A free page
+  /// counts as reachable, so [`Violation::Count`] reports the leaked pages.
+  pub fn violations(&self) -> Vec<Violation> {
+    let len = self.mem.len();
+    let mut found = vec![];
+    let mut visited = vec![false; len as usize];
+    let mut reachable = 0;
+    let mut entries = 0;
+    let mut depth = None;
+    let mut leaves = vec![];
+    let mut stack: Vec<(u64, Option<K>, Option<K>, usize)> = vec![(ROOT, None, None, 0)];
+    while let Some((x, lo, hi, d)) = stack.pop() {
+      if x >= len {
+        found.push(Violation::Dangling(x));
+        continue;
+      }
+      if visited[x as usize] {
+        found.push(Violation::Cycle(x));
+        continue;
+      }
+      visited[x as usize] = true;
+      reachable += 1;
+      let page = &self.mem[x];
+      if page.len() > MAX || (x != ROOT && page.len() < MIN) || (!page.leaf && page.len() == 0) {
+        found.push(Violation::Occupancy(x));
+        if page.len() > MAX {
+          continue;
+        }
+      }
+      let keys = page.keys();
+      if keys.windows(2).any(|w| w[0] >= w[1])
+        || keys.iter().any(|k| lo.is_some_and(|lo| *k < lo) || hi.is_some_and(|hi| *k >= hi))
+      {
+        found.push(Violation::Order(x));
+      }
+      if page.leaf {
+        if *depth.get_or_insert(d) != d {
+          found.push(Violation::Depth(x));
+        }
+        entries += page.len() as u64;
+        leaves.push(x);
+        continue;
+      }
+      for i in (0..=page.len()).rev() {
+        let lo = if i > 0 { Some(keys[i - 1]) } else { lo };
+        let hi = if i < page.len() { Some(keys[i]) } else { hi };
+        stack.push((page.children[i], lo, hi, d + 1));
+      }
+    }
+    for (i, &x) in leaves.iter().enumerate() {
+      if self.mem[x].next != leaves.get(i + 1).cloned() {
+        found.push(Violation::Link(x));
+      }
+    }
+    let mut free = Some(self.mem.meta().free).filter(|&f| f != ROOT);
+    while let Some(x) = free {
+      if x >= len {
+        found.push(Violation::Dangling(x));
+        break;
+      }
+      if visited[x as usize] {
+        found.push(Violation::Cycle(x));
+        break;
+      }
+      visited[x as usize] = true;
+      reachable += 1;
+      free = self.mem[x].next;
+    }
+    if reachable != len {
+      found.push(Violation::Count { len, reachable });
+    }
+    if entries != self.len() {
+      found.push(Violation::Entries {
+        len: self.len(),
+        found: entries,
+      });
+    }
+    found
+  }
+
+  fn leaf(&self, key: &K) -> u64 {
+    let mut x = ROOT;
+    while !self.mem[x].leaf {
+      x = self.mem[x].children[self.mem[x].child(key)];
+    }
+    x
+  }
+
+  pub fn get(&self, key: &K) -> Option<V> {
+    let page = &self.mem[self.leaf(key)];
+    page.keys().binary_search(key).ok().map(|i| page.vals[i])
+  }
+
+  pub fn contains(&self, key: &K) -> bool {
+    self.get(key).is_some()
+  }
+
+  pub fn iter(&self) -> Range<'_, K, V> {
+    self.range(..)
+  }
+
+  /// Iterates over the entries whose keys are within `range` in key order.
+  pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V> {
+    let (page, pos) = match range.start_bound() {
+      Bound::Unbounded => {
+        let mut x = ROOT;
+        while !self.mem[x].leaf {
+          x = self.mem[x].children[0];
+        }
+        (x, 0)
+      }
+      Bound::Included(key) => {
+        let x = self.leaf(key);
+        (
+          x,
+          self.mem[x].keys().binary_search(key).unwrap_or_else(|i| i),
+        )
+      }
+      Bound::Excluded(key) => {
+        let x = self.leaf(key);
+        (
+          x,
+          self.mem[x]
+            .keys()
+            .binary_search(key)
+            .map_or_else(|i| i, |i| i + 1),
+        )
+      }
+    };
+    let end = match range.end_bound() {
+      Bound::Unbounded => Bound::Unbounded,
+      Bound::Included(key) => Bound::Included(*key),
+      Bound::Excluded(key) => Bound::Excluded(*key),
+    };
+    Range {
+      tree: self,
+      page: Some(page),
+      pos,
+      end,
+    }
+  }
+
+  /// Inserts `val` at `key` and returns the value replaced, if any.
+  pub fn insert(&mut self, key: K, val: V) -> Result<Option<V>, Error> {
+    if self.mem[ROOT].is_full() {
+      let page = self.new_page(true)?;
+      self.mem[page] = self.mem[ROOT];
+      let mut root = Page::empty(false);
+      root.children[0] = page;
+      self.mem[ROOT] = root;
+      self.split(ROOT, 0)?;
+    }
+    let mut x = ROOT;
+    while !self.mem[x].leaf {
+      let mut i = self.mem[x].child(&key);
+      if self.mem[self.mem[x].children[i]].is_full() {
+        self.split(x, i)?;
+        if key >= self.mem[x].keys[i] {
+          i += 1;
+        }
+      }
+      x = self.mem[x].children[i];
+    }
+    let page = &mut self.mem[x];
+    match page.keys().binary_search(&key) {
+      Ok(i) => Ok(Some(mem::replace(&mut page.vals[i], val))),
+      Err(i) => {
+        page.insert(i, key, val);
+        self.mem.meta_mut().len += 1;
+        Ok(None)
+      }
+    }
+  }
+
+  /// Splits the full `i`-th child of `x`, which must not be full itself.
+  fn split(&mut self, x: u64, i: usize) -> Result<(), Error> {
+    let c = self.mem[x].children[i];
+    let leaf = self.mem[c].leaf;
+    let right = self.new_page(leaf)?;
+    let mut left = self.mem[c];
+    let mut page = Page::empty(leaf);
+    let key = if leaf {
+      page.keys[..MAX - MIN].copy_from_slice(&left.keys[MIN..]);
+      page.vals[..MAX - MIN].copy_from_slice(&left.vals[MIN..]);
+      page.len = (MAX - MIN) as u32;
+      page.next = left.next;
+      left.next = Some(right);
+      page.keys[0]
+    } else {
+      page.keys[..MAX - MIN - 1].copy_from_slice(&left.keys[MIN + 1..]);
+      page.children[..MAX - MIN].copy_from_slice(&left.children[MIN + 1..]);
+      page.len = (MAX - MIN - 1) as u32;
+      left.keys[MIN]
+    };
+    left.len = MIN as u32;
+    self.mem[c] = left;
+    self.mem[right] = page;
+    self.mem[x].insert_child(i, key, right);
+    Ok(())
+  }
+
+  /// Removes `key` and returns its value, if any.
+  pub fn remove(&mut self, key: &K) -> Result<Option<V>, Error> {
+    let mut x = ROOT;
+    while !self.mem[x].leaf {
+      let i = self.mem[x].child(key);
+      let c = self.mem[x].children[i];
+      x = if self.mem[c].len() > MIN {
+        c
+      } else {
+        self.fill(x, i)
+      };
+    }
+    let page = &mut self.mem[x];
+    match page.keys().binary_search(key) {
+      Ok(i) => {
+        let (_, val) = page.remove(i);
+        self.mem.meta_mut().len -= 1;
+        Ok(Some(val))
+      }
+      Err(_) => Ok(None),
+    }
+  }
+
+  /// Gives the `i`-th child of `x` more than the minimum number of keys by
+  /// borrowing from or merging with a sibling, and returns the page to
+  /// descend into.
+  fn fill(&mut self, x: u64, i: usize) -> u64 {
+    let parent = self.mem[x];
+    if i > 0 && self.mem[parent.children[i - 1]].len() > MIN {
+      self.borrow_left(x, i);
+      return parent.children[i];
+    }
+    if i < parent.len() && self.mem[parent.children[i + 1]].len() > MIN {
+      self.borrow_right(x, i);
+      return parent.children[i];
+    }
+    let i = if i < parent.len() { i } else { i - 1 };
+    self.merge(x, i);
+    let merged = parent.children[i];
+    if x == ROOT && self.mem[ROOT].len == 0 {
+      self.mem[ROOT] = self.mem[merged];
+      self.free_page(merged);
+      ROOT
+    } else {
+      merged
+    }
+  }
+
+  fn borrow_left(&mut self, x: u64, i: usize) {
+    let (l, c) = (self.mem[x].children[i - 1], self.mem[x].children[i]);
+    let mut left = self.mem[l];
+    let mut page = self.mem[c];
+    let n = left.len();
+    if page.leaf {
+      let (key, val) = left.remove(n - 1);
+      page.insert(0, key, val);
+      self.mem[x].keys[i - 1] = key;
+    } else {
+      let len = page.len();
+      page.keys.copy_within(..len, 1);
+      page.children.copy_within(..=len, 1);
+      page.keys[0] = self.mem[x].keys[i - 1];
+      page.children[0] = left.children[n];
+      page.len += 1;
+      left.len -= 1;
+      self.mem[x].keys[i - 1] = left.keys[n - 1];
+    }
+    self.mem[l] = left;
+    self.mem[c] = page;
+  }
+
+  fn borrow_right(&mut self, x: u64, i: usize) {
+    let (c, r) = (self.mem[x].children[i], self.mem[x].children[i + 1]);
+    let mut page = self.mem[c];
+    let mut right = self.mem[r];
+    let len = page.len();
+    if page.leaf {
+      let (key, val) = right.remove(0);
+      page.insert(len, key, val);
+      self.mem[x].keys[i] = right.keys[0];
+    } else {
+      let n = right.len();
+      page.keys[len] = self.mem[x].keys[i];
+      page.children[len + 1] = right.children[0];
+      page.len += 1;
+      self.mem[x].keys[i] = right.keys[0];
+      right.keys.copy_within(1..n, 0);
+      right.children.copy_within(1..=n, 0);
+      right.len -= 1;
+    }
+    self.mem[c] = page;
+    self.mem[r] = right;
+  }
+
+  /// Merges the `i`-th and the `i + 1`-th children of `x`.
+  fn merge(&mut self, x: u64, i: usize) {
+    let (l, r) = (self.mem[x].children[i], self.mem[x].children[i + 1]);
+    let mut left = self.mem[l];
+    let right = self.mem[r];
+    let (len, n) = (left.len(), right.len());
+    if left.leaf {
+      left.keys[len..len + n].copy_from_slice(right.keys());
+      left.vals[len..len + n].copy_from_slice(&right.vals[..n]);
+      left.len += n as u32;
+      left.next = right.next;
+    } else {
+      left.keys[len] = self.mem[x].keys[i];
+      left.keys[len + 1..len + 1 + n].copy_from_slice(right.keys());
+      left.children[len + 1..len + 2 + n].copy_from_slice(&right.children[..=n]);
+      left.len += n as u32 + 1;
+    }
+    self.mem[l] = left;
+    self.mem[x].remove_child(i);
+    self.free_page(r);
+  }
+
+  /// Replaces the contents with `entries`, which must be in strictly
+  /// ascending order of keys, filling the leaves as they are read.
+  pub fn load<I: IntoIterator<Item = (K, V)>>(&mut self, entries: I) -> Result<(), Error> {
+    self.clear()?;
+    let mut level: Vec<(K, u64)> = vec![];
+    let mut leaf = Page::empty(true);
+    let mut len = 0;
+    let mut last = None;
+    for (key, val) in entries {
+      if let Some(last) = last {
+        if last >= key {
+          self.clear()?;
+          bail!("The entries to load are not in ascending order.");
+        }
+      }
+      last = Some(key);
+      if leaf.is_full() {
+        self.push_leaf(&mut level, &leaf)?;
+        leaf = Page::empty(true);
+      }
+      leaf.insert(leaf.len(), key, val);
+      len += 1;
+    }
+    self.mem.meta_mut().len = len;
+    if level.is_empty() {
+      self.mem[ROOT] = leaf;
+      return Ok(());
+    }
+    if leaf.len() < MIN {
+      let prev = level[level.len() - 1].1;
+      let (len, moved) = (leaf.len(), MIN - leaf.len());
+      let from = self.mem[prev].len() - moved;
+      leaf.keys.copy_within(..len, moved);
+      leaf.vals.copy_within(..len, moved);
+      leaf.keys[..moved].copy_from_slice(&self.mem[prev].keys[from..from + moved]);
+      leaf.vals[..moved].copy_from_slice(&self.mem[prev].vals[from..from + moved]);
+      leaf.len += moved as u32;
+      self.mem[prev].len -= moved as u32;
+    }
+    self.push_leaf(&mut level, &leaf)?;
+    while level.len() > MAX + 1 {
+      let groups = (level.len() + MAX) / (MAX + 1);
+      let mut upper = Vec::with_capacity(groups);
+      let mut rest = &level[..];
+      for g in 0..groups {
+        let (group, tail) = rest.split_at(rest.len() / (groups - g));
+        let page = self.new_page(false)?;
+        self.mem[page] = Self::internal(group);
+        upper.push((group[0].0, page));
+        rest = tail;
+      }
+      level = upper;
+    }
+    self.mem[ROOT] = Self::internal(&level);
+    Ok(())
+  }
+
+  fn push_leaf(&mut self, level: &mut Vec<(K, u64)>, leaf: &Page<K, V>) -> Result<(), Error> {
+    let page = self.new_page(true)?;
+    self.mem[page] = *leaf;
+    if let Some(&(_, prev)) = level.last() {
+      self.mem[prev].next = Some(page);
+    }
+    level.push((leaf.keys[0], page));
+    Ok(())
+  }
+
+  fn internal(children: &[(K, u64)]) -> Page<K, V> {
+    let mut page = Page::empty(false);
+    for (i, &(key, child)) in children.iter().enumerate() {
+      if i > 0 {
+        page.keys[i - 1] = key;
+      }
+      page.children[i] = child;
+    }
+    page.len = children.len() as u32 - 1;
+    page
+  }
+}
+
+/// An iterator over a range of the entries of a [`BTree`].
+pub struct Range<'a, K, V> {
+  tree: &'a BTree<K, V>,
+  page: Option<u64>,
+  pos: usize,
+  end: Bound<K>,
+}
+
+impl<'a, K: Default + Ord + Copy, V: Default + Copy> Iterator for Range<'a, K, V> {
+  type Item = (K, V);
+
+  fn next(&mut self) -> Option<(K, V)> {
+    loop {
+      let page = &self.tree.mem[self.page?];
+      if self.pos < page.len() {
+        let (key, val) = (page.keys[self.pos], page.vals[self.pos]);
+        let within = match self.end {
+          Bound::Unbounded => true,
+          Bound::Included(end) => key <= end,
+          Bound::Excluded(end) => key < end,
+        };
+        if !within {
+          self.page = None;
+          return None;
+        }
+        self.pos += 1;
+        return Some((key, val));
+      }
+      self.page = page.next;
+      self.pos = 0;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::Rng;
+  use std::collections::BTreeMap;
+  use tempfile::tempfile;
+
+  impl<K: Default + Ord + Copy, V: Default + Copy> BTree<K, V> {
+    /// Checks the invariants and returns the keys in the order of the leaves.
+    fn check(&self) -> Vec<K> {
+      assert!(self.violations().is_empty(), "{:?}", self.violations());
+      self.iter().map(|(k, _)| k).collect()
+    }
+
+    fn free_pages(&self) -> u64 {
+      let mut count = 0;
+      let mut x = Some(self.mem.meta().free).filter(|&f| f != ROOT);
+      while let Some(f) = x {
+        count += 1;
+        x = self.mem[f].next;
+      }
+      count
+    }
+  }
+
+  #[test]
+  fn test_create() -> Result<(), Error> {
+    let tree: BTree<u64, u64> = BTree::create(tempfile()?)?;
+    assert!(tree.is_empty());
+    assert_eq!(tree.mem.len(), 1);
+    assert_eq!(tree.iter().next(), None);
+    assert_eq!(tree.get(&1), None);
+    Ok(())
+  }
+
+  #[test]
+  fn test_random() -> Result<(), Error> {
+    let mut rng = rand::thread_rng();
+    let mut tree: BTree<u64, u64> = BTree::create(tempfile()?)?;
+    let mut expected = BTreeMap::new();
+    for round in 0..20000 {
+      let key = rng.gen_range(0, 3000);
+      if rng.gen_bool(0.6) {
+        assert_eq!(tree.insert(key, round)?, expected.insert(key, round));
+      } else {
+        assert_eq!(tree.remove(&key)?, expected.remove(&key));
+      }
+      if round % 1000 == 0 {
+        tree.check();
+      }
+    }
+    assert_eq!(tree.check(), expected.keys().cloned().collect::<Vec<_>>());
+    assert_eq!(
+      tree.iter().collect::<Vec<_>>(),
+      expected.into_iter().collect::<Vec<_>>()
+    );
+    for key in 0..3000 {
+      tree.remove(&key)?;
+    }
+    tree.check();
+    assert!(tree.is_empty());
+    assert_eq!(tree.free_pages(), tree.mem.len() - 1);
+    Ok(())
+  }
+
+  #[test]
+  fn test_range() -> Result<(), Error> {
+    let mut tree: BTree<u64> = BTree::create(tempfile()?)?;
+    for key in (0..1000).map(|k| k * 2) {
+      tree.insert(key, ())?;
+    }
+    let keys = |range: Range<'_, u64, ()>| range.map(|(k, _)| k).collect::<Vec<_>>();
+    assert_eq!(keys(tree.range(10..=16)), vec![10, 12, 14, 16]);
+    assert_eq!(keys(tree.range(9..16)), vec![10, 12, 14]);
+    assert_eq!(
+      keys(tree.range((Bound::Excluded(10), Bound::Included(14)))),
+      vec![12, 14]
+    );
+    assert_eq!(keys(tree.range(..4)), vec![0, 2]);
+    assert_eq!(keys(tree.range(1994..)), vec![1994, 1996, 1998]);
+    assert!(keys(tree.range(2000..)).is_empty());
+    assert_eq!(tree.range(100..1100).count(), 500);
+    assert!(tree.contains(&500));
+    assert!(!tree.contains(&501));
+    Ok(())
+  }
+
+  #[test]
+  fn test_load() -> Result<(), Error> {
+    for &n in [0, 1, MAX, MAX + 1, MAX * 64 + 1, 20000].iter() {
+      let mut tree: BTree<u64, u64> = BTree::create(tempfile()?)?;
+      tree.insert(12345, 0)?;
+      tree.load((0..n as u64).map(|k| (k * 3, k)))?;
+      assert_eq!(
+        tree.check(),
+        (0..n as u64).map(|k| k * 3).collect::<Vec<_>>()
+      );
+      if n > 0 {
+        assert_eq!(tree.get(&(n as u64 / 2 * 3)), Some(n as u64 / 2));
+      }
+      for k in 0..n as u64 {
+        tree.insert(k * 3 + 1, k)?;
+        if k % 2 == 0 {
+          tree.remove(&(k * 3))?;
+        }
+      }
+      assert_eq!(tree.check().len(), n + n / 2);
+    }
+    let mut tree: BTree<u64> = BTree::create(tempfile()?)?;
+    assert!(tree.load(vec![(1, ()), (3, ()), (2, ())]).is_err());
+    assert!(tree.is_empty());
+    Ok(())
+  }
+
+  #[test]
+  fn test_violations() -> Result<(), Error> {
+    let mut tree: BTree<u64, u64> = BTree::create(tempfile()?)?;
+    for key in 0..5000 {
+      tree.insert(key, key)?;
+    }
+    for key in 0..1000 {
+      tree.remove(&key)?;
+    }
+    assert_eq!(tree.violations(), vec![]);
+
+    let first = tree.mem[ROOT].children[0];
+    let leaf = (0..tree.mem.len())
+      .find(|&x| tree.mem[x].leaf && tree.mem[x].len() > 0 && tree.mem[x].next.is_some())
+      .unwrap();
+    let key = tree.mem[leaf].keys[0];
+    tree.mem[leaf].keys[0] = u64::MAX;
+    assert!(tree.violations().contains(&Violation::Order(leaf)));
+    tree.mem[leaf].keys[0] = key;
+
+    let next = tree.mem[leaf].next;
+    tree.mem[leaf].next = None;
+    assert_eq!(tree.violations(), vec![Violation::Link(leaf)]);
+    tree.mem[leaf].next = next;
+
+    tree.mem[ROOT].children[0] = tree.mem.len();
+    assert!(tree.violations().contains(&Violation::Dangling(tree.mem.len())));
+    tree.mem[ROOT].children[0] = first;
+
+    let len = tree.mem[leaf].len;
+    tree.mem[leaf].len = 1;
+    assert!(tree.violations().contains(&Violation::Occupancy(leaf)));
+    tree.mem[leaf].len = len;
+
+    tree.mem.meta_mut().len += 1;
+    assert_eq!(
+      tree.violations(),
+      vec![Violation::Entries {
+        len: 4001,
+        found: 4000
+      }]
+    );
+    tree.mem.meta_mut().len -= 1;
+
+    let free = tree.mem.meta().free;
+    tree.mem.meta_mut().free = ROOT;
+    assert!(tree
+      .violations()
+      .iter()
+      .any(|v| matches!(v, Violation::Count { .. })));
+    tree.mem.meta_mut().free = free;
+    assert_eq!(tree.violations(), vec![]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_open() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("btree");
+    {
+      let mut tree: BTree<u64, u64> = BTree::open(&path)?;
+      for key in 0..5000 {
+        tree.insert(key, key * key)?;
+      }
+      tree.remove(&42)?;
+      tree.flush()?;
+    }
+    let tree: BTree<u64, u64> = BTree::open(&path)?;
+    assert_eq!(tree.len(), 4999);
+    assert_eq!(tree.get(&70), Some(4900));
+    assert_eq!(tree.get(&42), None);
+    tree.check();
+    Ok(())
+  }
+}
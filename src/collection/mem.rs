@@ -6,6 +6,7 @@ use std::mem;
 use std::slice::SliceIndex;
 use std::ops::{Range, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive};
 
+/// A growable array of `T` in a memory-mapped file, preceded by a header `M`.
 pub struct Mem<T, M = ()> {
   mmap: MmapMut,
   pt: PhantomData<M>,
@@ -25,6 +26,11 @@ impl<T, M> Mem<T, M> {
   pub fn len(&self) -> u64 {
     unsafe { *(self.mmap.as_ptr() as *const u64) }
   }
+
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
   #[inline]
   fn len_mut(&mut self) -> &mut u64 {
     unsafe { &mut *(self.mmap.as_mut_ptr() as *mut u64) }
@@ -45,6 +51,15 @@ impl<T, M> Mem<T, M> {
       + mem::size_of::<T>() * self.len() as usize
   }
 
+  pub fn flush(&self) -> std::io::Result<()> {
+    self.mmap.flush()
+  }
+
+  #[inline]
+  pub fn clear(&mut self) {
+    *self.len_mut() = 0;
+  }
+
   #[inline]
   pub fn push(&mut self) -> u64 {
     let len = self.len();
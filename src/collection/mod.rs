@@ -1,5 +1,7 @@
+mod btree;
 mod mem;
 mod rbtree;
 
+pub use self::btree::*;
 pub use self::mem::*;
 pub use self::rbtree::*;
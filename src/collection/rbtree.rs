@@ -1,8 +1,9 @@
 use failure::Error;
 use memmap::MmapMut;
 use std::cmp::{Ord, Ordering};
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::mem;
+use std::path::Path;
 use super::Mem;
 
 #[repr(C)]
@@ -40,20 +41,66 @@ impl<T> Node<T> {
     !self.color
   }
   #[inline]
-  fn to_red(&mut self) {
+  fn set_red(&mut self) {
     self.color = true;
   }
   #[inline]
-  fn to_black(&mut self) {
+  fn set_black(&mut self) {
     self.color = false;
   }
 }
 
+/// A broken invariant reported by [`RBTree::violations`] or
+/// [`BTree::violations`](super::BTree::violations).
+#[derive(Debug, PartialEq)]
+pub enum Violation {
+  RedRoot,
+  Dangling(u64),
+  Cycle(u64),
+  Parent(u64),
+  RedRed(u64),
+  BlackHeight(u64),
+  Order(u64),
+  Count { len: u64, reachable: u64 },
+  Occupancy(u64),
+  Depth(u64),
+  Link(u64),
+  Entries { len: u64, found: u64 },
+}
+
+impl std::fmt::Display for Violation {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Violation::RedRoot => write!(f, "the root is red"),
+      Violation::Dangling(x) => write!(f, "a link points outside the tree ({})", x),
+      Violation::Cycle(x) => write!(f, "the node {} is reachable twice", x),
+      Violation::Parent(x) => write!(f, "the node {} has a wrong parent link", x),
+      Violation::RedRed(x) => write!(f, "the red node {} has a red parent", x),
+      Violation::BlackHeight(x) => write!(f, "the paths below the node {} differ in black height", x),
+      Violation::Order(x) => write!(f, "the node {} is out of order", x),
+      Violation::Count { len, reachable } => {
+        write!(f, "{} of {} nodes are reachable from the root", reachable, len)
+      }
+      Violation::Occupancy(x) => write!(f, "the page {} holds too few or too many keys", x),
+      Violation::Depth(x) => write!(f, "the leaf {} differs in depth from the others", x),
+      Violation::Link(x) => write!(f, "the leaf {} does not link to the next leaf", x),
+      Violation::Entries { len, found } => {
+        write!(f, "{} entries are found but {} are counted", found, len)
+      }
+    }
+  }
+}
+
+/// A node to visit in [`RBTree::violations`]: its index, its parent, the
+/// black nodes above it and the bounds of its value.
+type Visit<T> = (u64, Option<u64>, usize, Option<T>, Option<T>);
+
 #[repr(C)]
 struct RBTreeMeta {
   root: Option<u64>,
 }
 
+/// A red-black tree set of `T` stored in a [`Mem`], persisted in a file.
 pub struct RBTree<T> {
   file: File,
   mem: Mem<Node<T>, RBTreeMeta>,
@@ -66,11 +113,7 @@ impl<T: std::fmt::Display> RBTree<T> {
     let right = self.mem[node].right;
     if left.is_some() || right.is_some() {
       if let Some(l) = left {
-        write!(
-          f,
-          "  {} -> {};\n",
-          self.mem[node].val, self.mem[l].val
-        )?;
+        writeln!(f, "  {} -> {};", self.mem[node].val, self.mem[l].val)?;
         self.fmt_inner(f, l)?;
       } else {
         write!(
@@ -80,11 +123,7 @@ impl<T: std::fmt::Display> RBTree<T> {
         )?;
       }
       if let Some(r) = right {
-        write!(
-          f,
-          "  {} -> {};\n",
-          self.mem[node].val, self.mem[r].val
-        )?;
+        writeln!(f, "  {} -> {};", self.mem[node].val, self.mem[r].val)?;
         self.fmt_inner(f, r)?;
       } else {
         write!(
@@ -99,16 +138,16 @@ impl<T: std::fmt::Display> RBTree<T> {
 }
 impl<T: std::fmt::Display> std::fmt::Display for RBTree<T> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    write!(f, "digraph G {{\n  graph [ordering=\"out\"];\n")?;
+    writeln!(f, "digraph G {{\n  graph [ordering=\"out\"];")?;
     for i in 0..self.mem.len() {
       let color = if self.mem[i].is_black() {
         "black"
       } else {
         "red"
       };
-      write!(
+      writeln!(
         f,
-        "  {} [label=\"{},{0}\", color=\"{}\"];\n",
+        "  {} [label=\"{},{0}\", color=\"{}\"];",
         self.mem[i].val, i, color
       )?;
     }
@@ -123,6 +162,16 @@ impl<T: std::fmt::Display> std::fmt::Display for RBTree<T> {
 impl<T: Default + Ord + Copy> RBTree<T> {
   const LEAST_CAPACITY: u64 = 4096;
 
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+    let file = OpenOptions::new()
+      .create(true)
+      .truncate(false)
+      .write(true)
+      .read(true)
+      .open(path)?;
+    Self::create(file)
+  }
+
   pub fn create(file: File) -> Result<Self, Error> {
     let len = file.metadata()?.len();
     let capacity = if len < Self::LEAST_CAPACITY {
@@ -146,6 +195,75 @@ impl<T: Default + Ord + Copy> RBTree<T> {
     Ok(())
   }
 
+  pub fn clear(&mut self) -> Result<(), Error> {
+    self.mem.clear();
+    self.mem.meta_mut().root = None;
+    self.capacity = Self::LEAST_CAPACITY;
+    self.file.set_len(self.capacity)?;
+    self.mem = Mem::new(unsafe { MmapMut::map_mut(&self.file)? });
+    Ok(())
+  }
+
+  pub fn flush(&self) -> Result<(), Error> {
+    self.mem.flush()?;
+    Ok(())
+  }
+
+  pub fn violations(&self) -> Vec<Violation> {
+    let len = self.mem.len();
+    let mut found = vec![];
+    let mut visited = vec![false; len as usize];
+    let mut reachable = 0;
+    let mut height = None;
+    let root = self.mem.meta().root;
+    if self.is_red(root.filter(|&r| r < len)) {
+      found.push(Violation::RedRoot);
+    }
+    let mut stack: Vec<Visit<T>> = vec![];
+    if let Some(r) = root {
+      stack.push((r, None, 0, None, None));
+    }
+    while let Some((x, parent, blacks, lo, hi)) = stack.pop() {
+      if x >= len {
+        found.push(Violation::Dangling(x));
+        continue;
+      }
+      if visited[x as usize] {
+        found.push(Violation::Cycle(x));
+        continue;
+      }
+      visited[x as usize] = true;
+      reachable += 1;
+      let node = &self.mem[x];
+      if node.parent != parent {
+        found.push(Violation::Parent(x));
+      }
+      if node.is_red() && self.is_red(parent) {
+        found.push(Violation::RedRed(x));
+      }
+      if lo.is_some_and(|lo| node.val <= lo) || hi.is_some_and(|hi| node.val >= hi) {
+        found.push(Violation::Order(x));
+      }
+      let blacks = if node.is_black() { blacks + 1 } else { blacks };
+      for &(child, lo, hi) in [(node.left, lo, Some(node.val)), (node.right, Some(node.val), hi)].iter() {
+        match child {
+          Some(c) => stack.push((c, Some(x), blacks, lo, hi)),
+          None => match height {
+            None => height = Some(blacks + 1),
+            Some(h) if h != blacks + 1 && found.last() != Some(&Violation::BlackHeight(x)) => {
+              found.push(Violation::BlackHeight(x))
+            }
+            _ => {}
+          },
+        }
+      }
+    }
+    if reachable != len {
+      found.push(Violation::Count { len, reachable });
+    }
+    found
+  }
+
   fn shrink(&mut self) -> Result<(), Error> {
     self.capacity /= 2;
     self.file.set_len(self.capacity)?;
@@ -213,10 +331,13 @@ impl<T: Default + Ord + Copy> RBTree<T> {
 
   fn del_node(&mut self, x: u64) -> Result<(), Error> {
     let last = self.mem.len() - 1;
-    self.assign_tree(last, x);
-    self.assign_parent_left(last, x);
-    self.assign_parent_right(last, x);
-    self.mem[x].val = self.mem[last].val;
+    if x != last {
+      self.assign_tree(last, x);
+      self.assign_parent_left(last, x);
+      self.assign_parent_right(last, x);
+      self.mem[x].val = self.mem[last].val;
+      self.mem[x].color = self.mem[last].color;
+    }
     self.mem.pop();
     if self.mem.occupy() < (self.capacity) as usize / 2 {
       self.shrink()?;
@@ -226,8 +347,6 @@ impl<T: Default + Ord + Copy> RBTree<T> {
 
   #[inline]
   fn swap_color(&mut self, x: u64, y: u64) {
-    let x = x;
-    let y = y;
     let c = self.mem[x].color;
     self.mem[x].color = self.mem[y].color;
     self.mem[y].color = c;
@@ -268,7 +387,7 @@ impl<T: Default + Ord + Copy> RBTree<T> {
     let node = self.new_node()?;
     self.mem[node].val = val;
     self.mem[node].parent = p;
-    self.mem[node].to_red();
+    self.mem[node].set_red();
     match ord {
       Ordering::Less => self.mem[p.unwrap()].left = Some(node),
       Ordering::Greater => self.mem[p.unwrap()].right = Some(node),
@@ -278,10 +397,7 @@ impl<T: Default + Ord + Copy> RBTree<T> {
   }
 
   fn del_bst(&mut self, x: Option<u64>, val: T) -> Option<u64> {
-    let x = match x {
-      None => return None,
-      Some(x) => x,
-    };
+    let x = x?;
     match val.cmp(&self.mem[x].val) {
       Ordering::Less => self.del_bst(self.mem[x].left, val),
       Ordering::Greater => self.del_bst(self.mem[x].right, val),
@@ -312,8 +428,95 @@ impl<T: Default + Ord + Copy> RBTree<T> {
     x.is_none() || self.mem[x.unwrap()].is_black()
   }
 
-  pub fn new(&mut self) -> Result<T, Error> {
-    unimplemented!();
+  fn minimum(&self, mut x: u64) -> u64 {
+    while let Some(l) = self.mem[x].left {
+      x = l;
+    }
+    x
+  }
+
+  fn maximum(&self, mut x: u64) -> u64 {
+    while let Some(r) = self.mem[x].right {
+      x = r;
+    }
+    x
+  }
+
+  fn predecessor(&self, mut x: u64) -> Option<u64> {
+    if let Some(l) = self.mem[x].left {
+      return Some(self.maximum(l));
+    }
+    while let Some(p) = self.mem[x].parent {
+      if Some(x) == self.mem[p].right {
+        return Some(p);
+      }
+      x = p;
+    }
+    None
+  }
+
+  fn successor(&self, mut x: u64) -> Option<u64> {
+    if let Some(r) = self.mem[x].right {
+      return Some(self.minimum(r));
+    }
+    while let Some(p) = self.mem[x].parent {
+      if Some(x) == self.mem[p].left {
+        return Some(p);
+      }
+      x = p;
+    }
+    None
+  }
+
+  fn lower_bound(&self, val: &T) -> Option<u64> {
+    let mut x = self.mem.meta().root;
+    let mut bound = None;
+    while let Some(i) = x {
+      if *val <= self.mem[i].val {
+        bound = x;
+        x = self.mem[i].left;
+      } else {
+        x = self.mem[i].right;
+      }
+    }
+    bound
+  }
+
+  #[inline]
+  pub fn len(&self) -> u64 {
+    self.mem.len()
+  }
+
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn contains(&self, val: &T) -> bool {
+    self
+      .lower_bound(val)
+      .is_some_and(|x| self.mem[x].val == *val)
+  }
+
+  pub fn iter(&self) -> Iter<'_, T> {
+    Iter {
+      tree: self,
+      next: self.mem.meta().root.map(|r| self.minimum(r)),
+    }
+  }
+
+  pub fn iter_rev(&self) -> RevIter<'_, T> {
+    RevIter {
+      tree: self,
+      next: self.mem.meta().root.map(|r| self.maximum(r)),
+    }
+  }
+
+  pub fn range_from(&self, val: T) -> Iter<'_, T> {
+    Iter {
+      tree: self,
+      next: self.lower_bound(&val),
+    }
   }
 
   pub fn add(&mut self, val: T) -> Result<(), Error> {
@@ -325,9 +528,9 @@ impl<T: Default + Ord + Copy> RBTree<T> {
         let u = self.mem[g].right;
         if self.is_red(u) {
           let u = u.unwrap();
-          self.mem[u].to_black();
-          self.mem[p].to_black();
-          self.mem[g].to_red();
+          self.mem[u].set_black();
+          self.mem[p].set_black();
+          self.mem[g].set_red();
           x = g;
         } else {
           if Some(x) == self.mem[p].right {
@@ -342,9 +545,9 @@ impl<T: Default + Ord + Copy> RBTree<T> {
         let u = self.mem[g].left;
         if self.is_red(u) {
           let u = u.unwrap();
-          self.mem[u].to_black();
-          self.mem[p].to_black();
-          self.mem[g].to_red();
+          self.mem[u].set_black();
+          self.mem[p].set_black();
+          self.mem[g].set_red();
           x = g;
         } else {
           if Some(x) == self.mem[p].left {
@@ -358,7 +561,7 @@ impl<T: Default + Ord + Copy> RBTree<T> {
       }
     }
     let r = self.mem.meta().root.unwrap();
-    self.mem[r].to_black();
+    self.mem[r].set_black();
     Ok(())
   }
 
@@ -374,16 +577,16 @@ impl<T: Default + Ord + Copy> RBTree<T> {
       };
       if self.mem[s].is_red() {
         self.rotate_left(p);
-        self.mem[s].to_black();
-        self.mem[p].to_red();
+        self.mem[s].set_black();
+        self.mem[p].set_red();
         self.del_dblack(Some(p), x);
       } else {
         let l = self.mem[s].left;
         let r = self.mem[s].right;
         if self.is_black(l) && self.is_black(r) {
-          self.mem[s].to_red();
+          self.mem[s].set_red();
           if self.mem[p].is_red() {
-            self.mem[p].to_black();
+            self.mem[p].set_black();
           } else {
             self.del_dblack(self.mem[p].parent, Some(p));
           }
@@ -395,6 +598,7 @@ impl<T: Default + Ord + Copy> RBTree<T> {
             self.mem[r.unwrap()].color = self.mem[s].color;
             self.mem[s].color = self.mem[p].color;
           }
+          self.mem[p].set_black();
           self.rotate_left(p);
         }
       }
@@ -405,16 +609,16 @@ impl<T: Default + Ord + Copy> RBTree<T> {
       };
       if self.mem[s].is_red() {
         self.rotate_right(p);
-        self.mem[s].to_black();
-        self.mem[p].to_red();
+        self.mem[s].set_black();
+        self.mem[p].set_red();
         self.del_dblack(Some(p), x);
       } else {
         let l = self.mem[s].left;
         let r = self.mem[s].right;
         if self.is_black(l) && self.is_black(r) {
-          self.mem[s].to_red();
+          self.mem[s].set_red();
           if self.mem[p].is_red() {
-            self.mem[p].to_black();
+            self.mem[p].set_black();
           } else {
             self.del_dblack(self.mem[p].parent, Some(p));
           }
@@ -426,6 +630,7 @@ impl<T: Default + Ord + Copy> RBTree<T> {
             self.mem[l.unwrap()].color = self.mem[s].color;
             self.mem[s].color = self.mem[p].color;
           }
+          self.mem[p].set_black();
           self.rotate_right(p);
         }
       }
@@ -433,50 +638,85 @@ impl<T: Default + Ord + Copy> RBTree<T> {
   }
 
   pub fn del(&mut self, val: T) -> Result<(), Error> {
-    let x = self.del_bst(self.mem.meta().root, val);
-    let x = match x {
+    let x = match self.del_bst(self.mem.meta().root, val) {
       None => return Ok(()),
-      Some(x) if Some(x) == self.mem.meta().root => {
-        self.mem.meta_mut().root = None;
-        return Ok(());
-      },
       Some(x) => x,
     };
-    let c = self.mem[x].left.and(self.mem[x].right);
-    if self.mem[x].is_red() || (c.is_some() && self.mem[c.unwrap()].is_red()) {
-      let p = self.mem[x].parent.unwrap();
+    let c = self.mem[x].left.or(self.mem[x].right);
+    let p = self.mem[x].parent;
+    if let Some(p) = p {
       if Some(x) == self.mem[p].left {
-        self.mem[p].left = c;
+        self.mem[p].left = c
       } else {
-        self.mem[p].right = c;
-      }
-      if let Some(c) = c {
-        self.mem[c].to_black();
-        self.mem[c].parent = Some(p);
+        self.mem[p].right = c
       }
     } else {
-      let p = self.mem[x].parent;
-      if let Some(p) = p {
-        if Some(x) == self.mem[p].left {
-          self.mem[p].left = c
-        } else {
-          self.mem[p].right = c
-        }
-      } else {
-        self.mem.meta_mut().root = c;
-      }
+      self.mem.meta_mut().root = c;
+    }
+    if let Some(c) = c {
+      self.mem[c].parent = p;
+    }
+    if self.mem[x].is_red() || self.is_red(c) {
       if let Some(c) = c {
-        self.mem[c].parent = p;
+        self.mem[c].set_black();
       }
+    } else {
       self.del_dblack(p, c);
     }
     self.del_node(x)?;
-    let r = self.mem.meta().root.unwrap();
-    self.mem[r].to_black();
+    if let Some(r) = self.mem.meta().root {
+      self.mem[r].set_black();
+    }
     Ok(())
   }
 }
 
+impl RBTree<u64> {
+  /// Returns the least value not less than `floor` that is not in the tree.
+  pub fn first_free(&self, floor: u64) -> Result<u64, Error> {
+    let mut val = floor;
+    for v in self.range_from(floor) {
+      if v != val {
+        break;
+      }
+      val = val
+        .checked_add(1)
+        .ok_or_else(|| format_err!("No value is left to allocate."))?;
+    }
+    Ok(val)
+  }
+}
+
+pub struct Iter<'a, T> {
+  tree: &'a RBTree<T>,
+  next: Option<u64>,
+}
+
+impl<'a, T: Default + Ord + Copy> Iterator for Iter<'a, T> {
+  type Item = T;
+
+  fn next(&mut self) -> Option<T> {
+    let x = self.next?;
+    self.next = self.tree.successor(x);
+    Some(self.tree.mem[x].val)
+  }
+}
+
+pub struct RevIter<'a, T> {
+  tree: &'a RBTree<T>,
+  next: Option<u64>,
+}
+
+impl<'a, T: Default + Ord + Copy> Iterator for RevIter<'a, T> {
+  type Item = T;
+
+  fn next(&mut self) -> Option<T> {
+    let x = self.next?;
+    self.next = self.tree.predecessor(x);
+    Some(self.tree.mem[x].val)
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -681,39 +921,57 @@ mod tests {
   }
 
 
-  struct BlackHeightAsserter<'a, T> {
-    tree: &'a RBTree<T>,
-    height: Option<usize>,
-  }
   impl<T: Default + Ord + Copy> RBTree<T> {
     fn assert_constraint(&self) {
-      assert!(self.is_black(self.mem.meta().root));
-      self.assert_black_height();
-    }
-
-    fn assert_black_height(&self) {
-      BlackHeightAsserter { tree: self, height: None }.assert();
+      assert_eq!(self.violations(), vec![]);
     }
   }
-  impl<'a, T> BlackHeightAsserter<'a, T> {
-    fn assert_recur(&mut self, x: Option<u64>, h: usize) {
-      if let Some(x) = x {
-        let h = if self.tree.mem[x].is_black() { h + 1 } else { h };
-        self.assert_recur(self.tree.mem[x].left, h);
-        self.assert_recur(self.tree.mem[x].right, h);
-      } else {
-        let h = h + 1;
-        if let Some(e) = self.height {
-          assert_eq!(h, e);
-        } else {
-          self.height = h.into();
-        }
-      }
-    }
 
-    fn assert(&mut self) {
-      self.assert_recur(self.tree.mem.meta().root, 0);
+  #[test]
+  fn test_violations() -> Result<(), Error> {
+    let file = tempfile()?;
+    let mut tree: RBTree<u64> = RBTree::create(file)?;
+    for v in vals() {
+      tree.add(v)?;
     }
+    assert_eq!(tree.violations(), vec![]);
+
+    let root = tree.mem.meta().root.unwrap();
+    tree.mem[root].set_red();
+    assert!(tree.violations().contains(&Violation::RedRoot));
+    tree.mem[root].set_black();
+
+    let leaf = tree.minimum(root);
+    tree.mem[leaf].val = u64::MAX;
+    assert_eq!(tree.violations(), vec![Violation::Order(leaf)]);
+    tree.mem[leaf].val = 0;
+    assert_eq!(tree.violations(), vec![]);
+
+    let x = tree.mem[leaf].parent.unwrap();
+    tree.mem[leaf].parent = None;
+    assert_eq!(tree.violations(), vec![Violation::Parent(leaf)]);
+    tree.mem[leaf].parent = Some(x);
+
+    tree.mem[leaf].left = Some(tree.mem.len());
+    assert_eq!(tree.violations(), vec![Violation::Dangling(tree.mem.len())]);
+    tree.mem[leaf].left = Some(root);
+    assert!(tree.violations().contains(&Violation::Cycle(root)));
+    tree.mem[leaf].left = None;
+
+    let black = (0..tree.mem.len()).find(|&i| i != root && tree.mem[i].is_black()).unwrap();
+    tree.mem[black].set_red();
+    assert!(tree
+      .violations()
+      .iter()
+      .any(|v| matches!(v, Violation::BlackHeight(_))));
+    tree.mem[black].set_black();
+
+    tree.mem.meta_mut().root = tree.mem[root].left;
+    assert!(tree
+      .violations()
+      .iter()
+      .any(|v| matches!(v, Violation::Count { .. })));
+    Ok(())
   }
 
   #[test]
@@ -730,6 +988,9 @@ mod tests {
       tree.add(v)?;
     }
     tree.assert_constraint();
+    let mut rev = vals_sorted();
+    rev.reverse();
+    assert_eq!(tree.iter_rev().collect::<Vec<_>>(), rev);
 
     Ok(())
   }
@@ -768,4 +1029,66 @@ mod tests {
 
     Ok(())
   }
+
+  #[test]
+  fn test_del_all() -> Result<(), Error> {
+    let file = tempfile()?;
+    let mut tree: RBTree<u64> = RBTree::create(file)?;
+    let mut rng = rand::thread_rng();
+    let mut vs: Vec<u64> = (0..2000).map(|_| rng.gen()).collect();
+    vs.sort();
+    vs.dedup();
+    for &v in vs.iter() {
+      tree.add(v)?;
+    }
+    for (i, &v) in vs.iter().enumerate() {
+      tree.del(v)?;
+      if i % 100 == 0 {
+        tree.assert_constraint();
+        assert_eq!(tree.dfs(), &vs[(i + 1)..]);
+      }
+    }
+    assert_eq!(tree.len(), 0);
+    assert_eq!(tree.mem.meta().root, None);
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_iter() -> Result<(), Error> {
+    let file = tempfile()?;
+    let mut tree: RBTree<u64> = RBTree::create(file)?;
+    for v in vals() {
+      tree.add(v)?;
+    }
+    assert_eq!(tree.iter().collect::<Vec<_>>(), vals_sorted());
+    let vs: Vec<u64> = vals_sorted().into_iter().filter(|&v| v >= 5000).collect();
+    assert_eq!(tree.range_from(5000).collect::<Vec<_>>(), vs);
+    assert!(tree.contains(&6531));
+    assert!(!tree.contains(&6532));
+    assert_eq!(tree.range_from(9888).next(), None);
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_first_free() -> Result<(), Error> {
+    let file = tempfile()?;
+    let mut tree: RBTree<u64> = RBTree::create(file)?;
+    for v in &[0, 1, 2, 4, 5, 8] {
+      tree.add(*v)?;
+    }
+    assert_eq!(tree.first_free(0)?, 3);
+    assert_eq!(tree.first_free(3)?, 3);
+    assert_eq!(tree.first_free(4)?, 6);
+    assert_eq!(tree.first_free(8)?, 9);
+    assert_eq!(tree.first_free(20)?, 20);
+    tree.assert_constraint();
+
+    tree.clear()?;
+    assert_eq!(tree.len(), 0);
+    assert_eq!(tree.first_free(0)?, 0);
+
+    Ok(())
+  }
 }
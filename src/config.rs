@@ -1,3 +1,4 @@
+use std::convert::TryFrom;
 use std::path::PathBuf;
 
 use serde::{Deserialize, Deserializer};
@@ -17,6 +18,18 @@ pub struct Config {
   pub upload_limit: usize,
   #[serde(default = "Config::default_db_path")]
   pub db_path: PathBuf,
+  #[serde(default = "Config::default_map_size")]
+  pub map_size: usize,
+  #[serde(default)]
+  pub id_allocation: IdAllocation,
+}
+
+#[derive(Deserialize, Debug, Default, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IdAllocation {
+  #[default]
+  Monotonic,
+  FillGaps,
 }
 impl Config {
   fn default_log() -> simplelog::LevelFilter {
@@ -37,6 +50,10 @@ impl Config {
   fn default_db_path() -> PathBuf {
     PathBuf::from("db/")
   }
+  /// 64 GiB, or 1 GiB where the address space cannot hold that much.
+  fn default_map_size() -> usize {
+    usize::try_from(64 * 1024 * 1024 * 1024u64).unwrap_or(1024 * 1024 * 1024)
+  }
 
   fn deserialize_log_level<'de, D>(
     deserializer: D,
@@ -66,6 +83,8 @@ impl Default for Config {
       workers: Self::default_workers(),
       upload_limit: Self::default_upload_limit(),
       db_path: Self::default_db_path(),
+      map_size: Self::default_map_size(),
+      id_allocation: Default::default(),
     }
   }
 }
@@ -0,0 +1,155 @@
+use super::super::hash::Hash;
+
+#[derive(Debug, Fail)]
+pub enum AttachmentError {
+  #[fail(display = "The article id={} has no attachment {}.", _0, _1)]
+  NotFound(u64, u32),
+  #[fail(display = "The same file is already stored in the article id={}.", _0)]
+  Duplicate(u64),
+}
+
+const EPUB_MIMETYPE: &[u8] = b"mimetypeapplication/epub+zip";
+
+#[derive(Serialize, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaType {
+  Other = 0,
+  Pdf = 1,
+  Epub = 2,
+  PostScript = 3,
+  Djvu = 4,
+  Html = 5,
+  Zip = 6,
+}
+
+fn is_html(head: &[u8]) -> bool {
+  let head = if head.starts_with(b"\xef\xbb\xbf") {
+    &head[3..]
+  } else {
+    head
+  };
+  let start = head
+    .iter()
+    .position(|b| !b.is_ascii_whitespace())
+    .unwrap_or(head.len());
+  let head = head[start..].to_ascii_lowercase();
+  head.starts_with(b"<!doctype html") || head.starts_with(b"<html") || head.starts_with(b"<head")
+}
+
+impl MediaType {
+  pub const ALL: [MediaType; 7] = [
+    MediaType::Other,
+    MediaType::Pdf,
+    MediaType::Epub,
+    MediaType::PostScript,
+    MediaType::Djvu,
+    MediaType::Html,
+    MediaType::Zip,
+  ];
+
+  pub fn detect(head: &[u8]) -> Self {
+    if head.starts_with(b"%PDF-") {
+      MediaType::Pdf
+    } else if head.starts_with(b"PK\x03\x04") {
+      if head.len() >= 30 + EPUB_MIMETYPE.len()
+        && &head[30..30 + EPUB_MIMETYPE.len()] == EPUB_MIMETYPE
+      {
+        MediaType::Epub
+      } else {
+        MediaType::Zip
+      }
+    } else if head.starts_with(b"PK\x05\x06") {
+      MediaType::Zip
+    } else if head.starts_with(b"%!PS") {
+      MediaType::PostScript
+    } else if head.starts_with(b"AT&TFORM") {
+      MediaType::Djvu
+    } else if is_html(head) {
+      MediaType::Html
+    } else {
+      MediaType::Other
+    }
+  }
+
+  pub fn from_u8(val: u8) -> Option<Self> {
+    Self::ALL.iter().cloned().find(|&t| t as u8 == val)
+  }
+
+  pub fn from_extension(ext: &str) -> Option<Self> {
+    Self::ALL.iter().cloned().find(|t| t.extension() == ext)
+  }
+
+  pub fn extension(self) -> &'static str {
+    match self {
+      MediaType::Other => "bin",
+      MediaType::Pdf => "pdf",
+      MediaType::Epub => "epub",
+      MediaType::PostScript => "ps",
+      MediaType::Djvu => "djvu",
+      MediaType::Html => "html",
+      MediaType::Zip => "zip",
+    }
+  }
+
+  pub fn mime(self) -> &'static str {
+    match self {
+      MediaType::Other => "application/octet-stream",
+      MediaType::Pdf => "application/pdf",
+      MediaType::Epub => "application/epub+zip",
+      MediaType::PostScript => "application/postscript",
+      MediaType::Djvu => "image/vnd.djvu",
+      MediaType::Html => "text/html; charset=utf-8",
+      MediaType::Zip => "application/zip",
+    }
+  }
+
+  pub fn inline(self) -> bool {
+    self == MediaType::Pdf
+  }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Attachment {
+  pub id: u32,
+  pub media_type: MediaType,
+  pub hash: Hash,
+  pub name: Option<String>,
+  pub size: u64,
+}
+
+impl Attachment {
+  pub fn filename(&self) -> String {
+    format!("{}.{}", self.hash, self.media_type.extension())
+  }
+
+  pub fn default_name(&self, article: u64) -> String {
+    match self.id {
+      0 => format!("{}.{}", article, self.media_type.extension()),
+      id => format!("{}.{}.{}", article, id, self.media_type.extension()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_detect() {
+    assert_eq!(MediaType::detect(b"%PDF-1.4\n"), MediaType::Pdf);
+    let mut epub = b"PK\x03\x04".to_vec();
+    epub.resize(30, 0);
+    epub.extend_from_slice(EPUB_MIMETYPE);
+    assert_eq!(MediaType::detect(&epub), MediaType::Epub);
+    epub[30] = b'M';
+    assert_eq!(MediaType::detect(&epub), MediaType::Zip);
+    assert_eq!(MediaType::detect(b"%!PS-Adobe-3.0"), MediaType::PostScript);
+    assert_eq!(MediaType::detect(b"AT&TFORM\x00\x00"), MediaType::Djvu);
+    assert_eq!(
+      MediaType::detect(b"\xef\xbb\xbf\n  <!DOCTYPE html><html>"),
+      MediaType::Html
+    );
+    assert_eq!(MediaType::detect(b"plain text"), MediaType::Other);
+    assert_eq!(MediaType::detect(b""), MediaType::Other);
+  }
+}
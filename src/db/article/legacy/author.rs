@@ -0,0 +1,60 @@
+use std::borrow::Cow;
+use std::ops::Index;
+
+const CAP: usize = 128;
+const TEXT_CAP: usize = 1024;
+
+#[derive(Copy, Clone)]
+pub struct Author {
+  len: u16,
+  name: [u8; TEXT_CAP],
+}
+
+#[derive(Copy, Clone)]
+pub struct Authors {
+  len: u8,
+  arr: [Author; CAP],
+}
+
+impl Author {
+  #[cfg(test)]
+  fn set<S: AsRef<str>>(&mut self, val: S) {
+    let bytes: &[u8] = val.as_ref().as_ref();
+    let len = bytes.len();
+    self.len = len as u16;
+    self.name[..len].copy_from_slice(bytes);
+  }
+
+  pub fn as_str_lossy(&self) -> Cow<'_, str> {
+    String::from_utf8_lossy(&self.name[..(self.len as usize).min(TEXT_CAP)])
+  }
+}
+
+impl Authors {
+  #[cfg(test)]
+  pub fn new<S: AsRef<str>>(val: &[S]) -> Self {
+    let mut new = Authors {
+      len: 0,
+      arr: [Author {
+        len: 0,
+        name: [0; TEXT_CAP],
+      }; CAP],
+    };
+    for author in val {
+      new.arr[new.len as usize].set(author);
+      new.len += 1;
+    }
+    new
+  }
+
+  pub fn len(&self) -> usize {
+    (self.len as usize).min(CAP)
+  }
+}
+
+impl Index<usize> for Authors {
+  type Output = Author;
+  fn index(&self, index: usize) -> &Self::Output {
+    &self.arr[index]
+  }
+}
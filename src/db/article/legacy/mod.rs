@@ -0,0 +1,37 @@
+mod author;
+mod title;
+
+use crate::lmdb::traits::LmdbRaw;
+
+use self::author::*;
+use self::title::*;
+
+#[derive(Copy, Clone)]
+pub struct ArticleContent {
+  title: Title,
+  authors: Authors,
+}
+
+unsafe impl LmdbRaw for ArticleContent {}
+
+impl ArticleContent {
+  #[cfg(test)]
+  pub fn new<S: AsRef<str>>(title: S, authors: &[S]) -> Self {
+    ArticleContent {
+      title: Title::new(title),
+      authors: Authors::new(authors),
+    }
+  }
+}
+
+impl<'a> From<&'a ArticleContent> for super::ArticleContent {
+  fn from(legacy: &'a ArticleContent) -> Self {
+    super::ArticleContent {
+      title: legacy.title.as_str_lossy().into_owned(),
+      authors: (0..legacy.authors.len())
+        .map(|i| legacy.authors[i].as_str_lossy().into_owned())
+        .collect(),
+      ..Default::default()
+    }
+  }
+}
@@ -0,0 +1,28 @@
+use std::borrow::Cow;
+
+const CAP: usize = 65536;
+
+#[derive(Copy, Clone)]
+pub struct Title {
+  len: u16,
+  text: [u8; CAP],
+}
+
+impl Title {
+  #[cfg(test)]
+  pub fn new<S: AsRef<str>>(val: S) -> Self {
+    let mut new = Title {
+      len: 0,
+      text: [0; CAP],
+    };
+    let bytes: &[u8] = val.as_ref().as_ref();
+    let len = bytes.len();
+    new.len = len as u16;
+    new.text[..len].copy_from_slice(bytes);
+    new
+  }
+
+  pub fn as_str_lossy(&self) -> Cow<'_, str> {
+    String::from_utf8_lossy(&self.text[..(self.len as usize).min(CAP)])
+  }
+}
@@ -1,63 +1,375 @@
-mod author;
-mod title;
+mod attachment;
+mod legacy;
+mod name;
+mod patch;
+mod record;
 
 use std::path::{Path, PathBuf};
 
-use crate::lmdb::traits::LmdbRaw;
-use serde::{ser::SerializeStruct, Serialize, Serializer};
+pub use self::attachment::*;
+pub use self::name::{family_name, fold};
+pub use self::patch::*;
 
-pub use self::author::*;
-pub use self::title::*;
+const MAX_TITLE_LEN: usize = 4096;
+const MAX_AUTHORS: usize = 4096;
+const MAX_AUTHOR_LEN: usize = 1024;
+const MAX_FIELD_LEN: usize = 1024;
+const MAX_TEXT_LEN: usize = 65536;
+const MAX_KEYWORDS: usize = 256;
 
-#[derive(Serialize, Copy, Clone)]
+#[derive(Debug, Fail)]
+pub enum ArticleError {
+  #[fail(display = "The title is empty.")]
+  EmptyTitle,
+  #[fail(display = "The title is {} bytes, longer than {} bytes.", _0, _1)]
+  TitleTooLong(usize, usize),
+  #[fail(display = "{} authors are given, more than {}.", _0, _1)]
+  TooManyAuthors(usize, usize),
+  #[fail(display = "An author name is empty.")]
+  EmptyAuthor,
+  #[fail(display = "An author name is {} bytes, longer than {} bytes.", _0, _1)]
+  AuthorTooLong(usize, usize),
+  #[fail(display = "The {} is {} bytes, longer than {} bytes.", _0, _1, _2)]
+  FieldTooLong(&'static str, usize, usize),
+  #[fail(display = "{} keywords are given, more than {}.", _0, _1)]
+  TooManyKeywords(usize, usize),
+  #[fail(display = "The year {} is out of range.", _0)]
+  InvalidYear(u16),
+  #[fail(display = "'{}' is not a DOI.", _0)]
+  InvalidDoi(String),
+  #[fail(display = "'{}' is not an arXiv identifier.", _0)]
+  InvalidArxiv(String),
+}
+
+/// The metadata and attachments of an article as stored in a record.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
 pub struct ArticleContent {
-  pub title: Title,
-  pub authors: Authors,
+  pub title: String,
+  pub authors: Vec<String>,
+  pub year: Option<u16>,
+  pub venue: Option<String>,
+  pub volume: Option<String>,
+  pub pages: Option<String>,
+  pub doi: Option<String>,
+  pub arxiv: Option<String>,
+  #[serde(rename = "abstract")]
+  pub abstract_: Option<String>,
+  pub keywords: Vec<String>,
+  pub note: Option<String>,
+  pub attachments: Vec<Attachment>,
+  /// The id of the next attachment. Ids are not reused, so a link to a
+  /// removed attachment never reaches one added later.
+  #[serde(skip)]
+  pub next_attachment: u32,
+}
+
+fn trim(val: String) -> String {
+  if val.trim().len() == val.len() {
+    val
+  } else {
+    val.trim().to_owned()
+  }
 }
 
-unsafe impl LmdbRaw for ArticleContent {}
+fn trim_opt(val: Option<String>) -> Option<String> {
+  val.map(trim).filter(|v| !v.is_empty())
+}
+
+fn strip_prefixes(val: String, prefixes: &[&str]) -> String {
+  let lower = val.to_lowercase();
+  match prefixes.iter().find(|p| lower.starts_with(*p)) {
+    Some(p) => val[p.len()..].trim_start().to_owned(),
+    None => val,
+  }
+}
+
+fn is_doi(val: &str) -> bool {
+  match val.find('/') {
+    Some(i) => {
+      let prefix = &val[..i];
+      prefix.starts_with("10.")
+        && prefix.len() > 3
+        && prefix[3..].bytes().all(|b| b.is_ascii_digit() || b == b'.')
+        && i + 1 < val.len()
+        && !val.contains(char::is_whitespace)
+    }
+    None => false,
+  }
+}
+
+fn is_arxiv(val: &str) -> bool {
+  let val = match val.rfind('v') {
+    Some(i) if i + 1 < val.len() && val[i + 1..].bytes().all(|b| b.is_ascii_digit()) => &val[..i],
+    _ => val,
+  };
+  let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+  match val.find('/') {
+    Some(i) => {
+      let archive = &val[..i];
+      !archive.is_empty()
+        && archive
+          .bytes()
+          .all(|b| b.is_ascii_lowercase() || b == b'-' || b == b'.')
+        && val[i + 1..].len() == 7
+        && digits(&val[i + 1..])
+    }
+    None => match val.find('.') {
+      Some(i) => {
+        i == 4
+          && digits(&val[..i])
+          && (4..=5).contains(&val[i + 1..].len())
+          && digits(&val[i + 1..])
+      }
+      None => false,
+    },
+  }
+}
+
+fn check_len(name: &'static str, val: &Option<String>, max: usize) -> Result<(), ArticleError> {
+  match val {
+    Some(val) if val.len() > max => Err(ArticleError::FieldTooLong(name, val.len(), max)),
+    _ => Ok(()),
+  }
+}
 
 impl ArticleContent {
-  pub fn new<T: AsRef<str>, I: AsRef<str>, A: AsRef<[I]>>(title: T, authors: A) -> Self {
+  pub fn new<T: AsRef<str>, I: AsRef<str>, A: AsRef<[I]>>(
+    title: T,
+    authors: A,
+  ) -> Result<Self, ArticleError> {
     ArticleContent {
-      title: Title::new(title),
-      authors: Authors::new(authors),
+      title: title.as_ref().to_owned(),
+      authors: authors
+        .as_ref()
+        .iter()
+        .map(|a| a.as_ref().to_owned())
+        .collect(),
+      ..Default::default()
     }
+    .normalized()
+  }
+
+  pub fn normalized(self) -> Result<Self, ArticleError> {
+    let content = ArticleContent {
+      title: trim(self.title),
+      authors: self.authors.into_iter().map(trim).collect(),
+      year: self.year,
+      venue: trim_opt(self.venue),
+      volume: trim_opt(self.volume),
+      pages: trim_opt(self.pages),
+      doi: trim_opt(self.doi).map(|d| {
+        strip_prefixes(
+          d,
+          &[
+            "https://doi.org/",
+            "http://doi.org/",
+            "https://dx.doi.org/",
+            "http://dx.doi.org/",
+            "doi:",
+          ],
+        )
+      }),
+      arxiv: trim_opt(self.arxiv).map(|a| {
+        strip_prefixes(
+          a,
+          &["https://arxiv.org/abs/", "http://arxiv.org/abs/", "arxiv:"],
+        )
+      }),
+      abstract_: trim_opt(self.abstract_),
+      keywords: self
+        .keywords
+        .into_iter()
+        .map(trim)
+        .filter(|k| !k.is_empty())
+        .collect(),
+      note: trim_opt(self.note),
+      attachments: self.attachments,
+      next_attachment: self.next_attachment,
+    };
+    content.validate()?;
+    Ok(content)
+  }
+
+  pub fn primary(&self) -> Option<&Attachment> {
+    self.attachments.first()
+  }
+
+  pub fn attachment(&self, id: u32) -> Option<&Attachment> {
+    self.attachments.iter().find(|a| a.id == id)
+  }
+
+  /// Allocates the id of a new attachment.
+  pub fn new_attachment_id(&mut self) -> u32 {
+    let id = self
+      .attachments
+      .iter()
+      .map(|a| a.id + 1)
+      .max()
+      .unwrap_or(0)
+      .max(self.next_attachment);
+    self.next_attachment = id + 1;
+    id
+  }
+
+  pub fn validate(&self) -> Result<(), ArticleError> {
+    if self.title.is_empty() {
+      return Err(ArticleError::EmptyTitle);
+    }
+    if self.title.len() > MAX_TITLE_LEN {
+      return Err(ArticleError::TitleTooLong(self.title.len(), MAX_TITLE_LEN));
+    }
+    if self.authors.len() > MAX_AUTHORS {
+      return Err(ArticleError::TooManyAuthors(
+        self.authors.len(),
+        MAX_AUTHORS,
+      ));
+    }
+    for author in self.authors.iter() {
+      if author.is_empty() {
+        return Err(ArticleError::EmptyAuthor);
+      }
+      if author.len() > MAX_AUTHOR_LEN {
+        return Err(ArticleError::AuthorTooLong(author.len(), MAX_AUTHOR_LEN));
+      }
+    }
+    if let Some(year) = self.year {
+      if year == 0 || year > 9999 {
+        return Err(ArticleError::InvalidYear(year));
+      }
+    }
+    check_len("venue", &self.venue, MAX_FIELD_LEN)?;
+    check_len("volume", &self.volume, MAX_FIELD_LEN)?;
+    check_len("pages", &self.pages, MAX_FIELD_LEN)?;
+    check_len("abstract", &self.abstract_, MAX_TEXT_LEN)?;
+    check_len("note", &self.note, MAX_TEXT_LEN)?;
+    if let Some(doi) = &self.doi {
+      if !is_doi(doi) {
+        return Err(ArticleError::InvalidDoi(doi.to_owned()));
+      }
+    }
+    if let Some(arxiv) = &self.arxiv {
+      if !is_arxiv(arxiv) {
+        return Err(ArticleError::InvalidArxiv(arxiv.to_owned()));
+      }
+    }
+    if self.keywords.len() > MAX_KEYWORDS {
+      return Err(ArticleError::TooManyKeywords(
+        self.keywords.len(),
+        MAX_KEYWORDS,
+      ));
+    }
+    for keyword in self.keywords.iter() {
+      if keyword.len() > MAX_FIELD_LEN {
+        return Err(ArticleError::FieldTooLong(
+          "keyword",
+          keyword.len(),
+          MAX_FIELD_LEN,
+        ));
+      }
+    }
+    Ok(())
   }
 }
 
+/// An article as returned by the store: its id, content and the directory
+/// holding its files.
+#[derive(Serialize)]
 pub struct Article {
-  path: PathBuf,
+  #[serde(skip)]
+  dir: PathBuf,
   id: u64,
+  #[serde(flatten)]
   content: ArticleContent,
 }
 
-impl Serialize for Article {
-  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-  where
-    S: Serializer,
-  {
-    let mut s = serializer.serialize_struct("Article", 3)?;
-    s.serialize_field("id", &self.id)?;
-    s.serialize_field("title", &self.content.title)?;
-    s.serialize_field("authors", &self.content.authors)?;
-    s.end()
+impl Article {
+  pub fn new(dir: PathBuf, id: u64, content: ArticleContent) -> Self {
+    Self { dir, id, content }
   }
-}
 
-impl Article {
-  pub fn new(path: PathBuf, id: u64, content: ArticleContent) -> Self {
-    Self { path, id, content }
+  pub fn id(&self) -> u64 {
+    self.id
+  }
+
+  pub fn content(&self) -> &ArticleContent {
+    &self.content
+  }
+
+  pub fn attachment_path(&self, attachment: &Attachment) -> PathBuf {
+    self.dir.join(attachment.filename())
   }
 
-  pub fn path(&self) -> &Path {
-    self.path.as_path()
+  pub fn revision_dir(&self) -> PathBuf {
+    Self::revision_dir_of(&self.dir, self.id)
   }
 
-  pub fn filename(&self) -> String {
+  pub fn revision_dir_of(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{}.revisions", id))
+  }
+
+  pub fn revision_path(&self, revision: u32, media_type: MediaType) -> PathBuf {
     self
-      .path
-      .file_name()
-      .map_or(String::new(), |s| s.to_string_lossy().to_string())
+      .revision_dir()
+      .join(format!("{}.{}", revision, media_type.extension()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_validate() {
+    assert!(ArticleContent::new("Literate Programming", ["Knuth"]).is_ok());
+    assert!(ArticleContent::new("Anonymous", &[] as &[&str]).is_ok());
+    match ArticleContent::new("  ", ["Knuth"]) {
+      Err(ArticleError::EmptyTitle) => {}
+      _ => panic!("an empty title is accepted"),
+    }
+    match ArticleContent::new("a".repeat(MAX_TITLE_LEN + 1), ["Knuth"]) {
+      Err(ArticleError::TitleTooLong(..)) => {}
+      _ => panic!("a long title is accepted"),
+    }
+    match ArticleContent::new("Title", vec!["Knuth"; MAX_AUTHORS + 1]) {
+      Err(ArticleError::TooManyAuthors(..)) => {}
+      _ => panic!("too many authors are accepted"),
+    }
+    match ArticleContent::new("Title", ["Knuth", ""]) {
+      Err(ArticleError::EmptyAuthor) => {}
+      _ => panic!("an empty author is accepted"),
+    }
+    let content = ArticleContent {
+      title: "Go To Statement Considered Harmful".to_owned(),
+      doi: Some(" https://doi.org/10.1145/362929.362947".to_owned()),
+      arxiv: Some("arXiv:1501.00001v2".to_owned()),
+      venue: Some("  ".to_owned()),
+      ..Default::default()
+    }
+    .normalized()
+    .unwrap();
+    assert_eq!(content.doi, Some("10.1145/362929.362947".to_owned()));
+    assert_eq!(content.arxiv, Some("1501.00001v2".to_owned()));
+    assert_eq!(content.venue, None);
+    let invalid = |content: ArticleContent| content.normalized().is_err();
+    let base = || ArticleContent {
+      title: "Title".to_owned(),
+      ..Default::default()
+    };
+    assert!(invalid(ArticleContent {
+      year: Some(0),
+      ..base()
+    }));
+    assert!(invalid(ArticleContent {
+      doi: Some("11.1145/362929".to_owned()),
+      ..base()
+    }));
+    assert!(invalid(ArticleContent {
+      arxiv: Some("15010.00001".to_owned()),
+      ..base()
+    }));
+    assert!(!invalid(ArticleContent {
+      arxiv: Some("hep-th/9901001".to_owned()),
+      ..base()
+    }));
   }
 }
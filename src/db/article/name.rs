@@ -0,0 +1,37 @@
+use crate::bibtex::{base, ligature};
+
+/// Lowercases a word and drops its accents and punctuation, so that spellings
+/// such as "Gödel" and "Godel" fold to the same word.
+pub fn fold(word: &str) -> String {
+  word
+    .chars()
+    .filter_map(|c| match ligature(c) {
+      Some(s) => Some(s.to_lowercase()),
+      None => Some(base(c))
+        .filter(|c| c.is_alphanumeric())
+        .map(|c| c.to_lowercase().to_string()),
+    })
+    .collect()
+}
+
+/// The family name of an author written either as "Given Family" or as
+/// "Family, Given".
+pub fn family_name(author: &str) -> &str {
+  match author.find(',') {
+    Some(i) => &author[..i],
+    None => author.split_whitespace().last().unwrap_or(author),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_family_name() {
+    assert_eq!(fold(family_name("Donald E. Knuth")), "knuth");
+    assert_eq!(fold(family_name("Gödel, Kurt")), "godel");
+    assert_eq!(fold(family_name("Łukasz Kaiser")), "kaiser");
+    assert_eq!(fold("Straße"), "strasse");
+  }
+}
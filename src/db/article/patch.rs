@@ -0,0 +1,78 @@
+use serde::{Deserialize, Deserializer};
+
+use super::ArticleContent;
+
+fn some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+  T: Deserialize<'de>,
+  D: Deserializer<'de>,
+{
+  T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ArticlePatch {
+  pub title: Option<String>,
+  pub authors: Option<Vec<String>>,
+  #[serde(default, deserialize_with = "some")]
+  pub year: Option<Option<u16>>,
+  #[serde(default, deserialize_with = "some")]
+  pub venue: Option<Option<String>>,
+  #[serde(default, deserialize_with = "some")]
+  pub volume: Option<Option<String>>,
+  #[serde(default, deserialize_with = "some")]
+  pub pages: Option<Option<String>>,
+  #[serde(default, deserialize_with = "some")]
+  pub doi: Option<Option<String>>,
+  #[serde(default, deserialize_with = "some")]
+  pub arxiv: Option<Option<String>>,
+  #[serde(default, deserialize_with = "some", rename = "abstract")]
+  pub abstract_: Option<Option<String>>,
+  pub keywords: Option<Vec<String>>,
+  #[serde(default, deserialize_with = "some")]
+  pub note: Option<Option<String>>,
+}
+
+impl ArticlePatch {
+  pub fn apply(self, content: ArticleContent) -> ArticleContent {
+    ArticleContent {
+      title: self.title.unwrap_or(content.title),
+      authors: self.authors.unwrap_or(content.authors),
+      year: self.year.unwrap_or(content.year),
+      venue: self.venue.unwrap_or(content.venue),
+      volume: self.volume.unwrap_or(content.volume),
+      pages: self.pages.unwrap_or(content.pages),
+      doi: self.doi.unwrap_or(content.doi),
+      arxiv: self.arxiv.unwrap_or(content.arxiv),
+      abstract_: self.abstract_.unwrap_or(content.abstract_),
+      keywords: self.keywords.unwrap_or(content.keywords),
+      note: self.note.unwrap_or(content.note),
+      attachments: content.attachments,
+      next_attachment: content.next_attachment,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_apply() {
+    let content = ArticleContent {
+      year: Some(1986),
+      venue: Some("The Computer Journal".to_owned()),
+      ..ArticleContent::new("Literate Programing", ["Knuth"]).unwrap()
+    };
+    let patch: ArticlePatch =
+      serde_json::from_str(r#"{"title": "Literate Programming", "venue": null}"#).unwrap();
+    let content = patch.apply(content);
+    assert_eq!(content.title, "Literate Programming");
+    assert_eq!(content.authors, vec!["Knuth"]);
+    assert_eq!(content.year, Some(1986));
+    assert_eq!(content.venue, None);
+
+    assert!(serde_json::from_str::<ArticlePatch>(r#"{"titel": "Typo"}"#).is_err());
+  }
+}
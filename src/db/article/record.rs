@@ -0,0 +1,288 @@
+use std::mem;
+
+use failure::Error;
+use lmdb::traits::FromLmdbBytes;
+
+use super::super::hash::Hash;
+use super::legacy;
+use super::{ArticleContent, Attachment, MediaType};
+
+const MAGIC: &[u8; 4] = b"WEID";
+const VERSION: u8 = 1;
+
+#[derive(Debug, Fail)]
+pub enum RecordError {
+  #[fail(display = "The record is truncated.")]
+  Truncated,
+  #[fail(display = "The record has {} trailing bytes.", _0)]
+  TrailingBytes(usize),
+  #[fail(display = "The record has an unknown version {}.", _0)]
+  UnknownVersion(u8),
+  #[fail(display = "The record is not in a known format ({} bytes).", _0)]
+  UnknownFormat(usize),
+  #[fail(display = "The record contains an invalid UTF-8 string.")]
+  InvalidUtf8,
+  #[fail(display = "The record has an unknown media type {}.", _0)]
+  UnknownMediaType(u8),
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+  fn take(&mut self, len: usize) -> Result<&'a [u8], RecordError> {
+    if self.0.len() < len {
+      return Err(RecordError::Truncated);
+    }
+    let (head, tail) = self.0.split_at(len);
+    self.0 = tail;
+    Ok(head)
+  }
+
+  fn u32(&mut self) -> Result<u32, RecordError> {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(self.take(4)?);
+    Ok(u32::from_le_bytes(buf))
+  }
+
+  fn u64(&mut self) -> Result<u64, RecordError> {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(self.take(8)?);
+    Ok(u64::from_le_bytes(buf))
+  }
+
+  fn u16(&mut self) -> Result<u16, RecordError> {
+    let mut buf = [0; 2];
+    buf.copy_from_slice(self.take(2)?);
+    Ok(u16::from_le_bytes(buf))
+  }
+
+  fn string(&mut self) -> Result<String, RecordError> {
+    let len = self.u32()? as usize;
+    let bytes = self.take(len)?;
+    String::from_utf8(bytes.to_vec()).map_err(|_| RecordError::InvalidUtf8)
+  }
+
+  fn opt_string(&mut self) -> Result<Option<String>, RecordError> {
+    match self.take(1)?[0] {
+      0 => Ok(None),
+      _ => self.string().map(Some),
+    }
+  }
+
+  fn strings(&mut self) -> Result<Vec<String>, RecordError> {
+    let len = self.u32()? as usize;
+    let mut vals = Vec::with_capacity(len.min(self.0.len() / 4));
+    for _ in 0..len {
+      vals.push(self.string()?);
+    }
+    Ok(vals)
+  }
+
+  fn hash(&mut self) -> Result<Hash, RecordError> {
+    let mut hash = Hash::default();
+    hash.0.copy_from_slice(self.take(32)?);
+    Ok(hash)
+  }
+
+  fn attachments(&mut self) -> Result<Vec<Attachment>, RecordError> {
+    let len = self.u32()? as usize;
+    let mut attachments = Vec::with_capacity(len.min(self.0.len() / 14));
+    for _ in 0..len {
+      let id = self.u32()?;
+      let media_type = self.take(1)?[0];
+      attachments.push(Attachment {
+        id,
+        media_type: MediaType::from_u8(media_type)
+          .ok_or(RecordError::UnknownMediaType(media_type))?,
+        hash: self.hash()?,
+        size: self.u64()?,
+        name: self.opt_string()?,
+      });
+    }
+    Ok(attachments)
+  }
+}
+
+fn put_string(buf: &mut Vec<u8>, val: &str) {
+  buf.extend_from_slice(&(val.len() as u32).to_le_bytes());
+  buf.extend_from_slice(val.as_bytes());
+}
+
+fn put_opt_string(buf: &mut Vec<u8>, val: &Option<String>) {
+  match val {
+    Some(val) => {
+      buf.push(1);
+      put_string(buf, val);
+    }
+    None => buf.push(0),
+  }
+}
+
+fn put_strings(buf: &mut Vec<u8>, vals: &[String]) {
+  buf.extend_from_slice(&(vals.len() as u32).to_le_bytes());
+  for val in vals {
+    put_string(buf, val);
+  }
+}
+
+fn put_attachments(buf: &mut Vec<u8>, attachments: &[Attachment]) {
+  buf.extend_from_slice(&(attachments.len() as u32).to_le_bytes());
+  for attachment in attachments {
+    buf.extend_from_slice(&attachment.id.to_le_bytes());
+    buf.push(attachment.media_type as u8);
+    buf.extend_from_slice(&attachment.hash.0);
+    buf.extend_from_slice(&attachment.size.to_le_bytes());
+    put_opt_string(buf, &attachment.name);
+  }
+}
+
+impl ArticleContent {
+  pub fn encode(&self) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(MAGIC);
+    buf.push(VERSION);
+    put_string(&mut buf, &self.title);
+    put_strings(&mut buf, &self.authors);
+    buf.extend_from_slice(&self.year.unwrap_or(0).to_le_bytes());
+    put_opt_string(&mut buf, &self.venue);
+    put_opt_string(&mut buf, &self.volume);
+    put_opt_string(&mut buf, &self.pages);
+    put_opt_string(&mut buf, &self.doi);
+    put_opt_string(&mut buf, &self.arxiv);
+    put_opt_string(&mut buf, &self.abstract_);
+    put_strings(&mut buf, &self.keywords);
+    put_opt_string(&mut buf, &self.note);
+    put_attachments(&mut buf, &self.attachments);
+    buf.extend_from_slice(&self.next_attachment.to_le_bytes());
+    buf
+  }
+
+  pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+    if !bytes.starts_with(MAGIC) {
+      if Self::is_legacy(bytes) {
+        let legacy =
+          legacy::ArticleContent::from_lmdb_bytes(bytes).map_err(|e| format_err!("{}", e))?;
+        return Ok(legacy.into());
+      }
+      return Err(RecordError::UnknownFormat(bytes.len()).into());
+    }
+    let mut reader = Reader(&bytes[MAGIC.len()..]);
+    let version = reader.take(1)?[0];
+    if version != VERSION {
+      return Err(RecordError::UnknownVersion(version).into());
+    }
+    let content = ArticleContent {
+      title: reader.string()?,
+      authors: reader.strings()?,
+      year: Some(reader.u16()?).filter(|&y| y != 0),
+      venue: reader.opt_string()?,
+      volume: reader.opt_string()?,
+      pages: reader.opt_string()?,
+      doi: reader.opt_string()?,
+      arxiv: reader.opt_string()?,
+      abstract_: reader.opt_string()?,
+      keywords: reader.strings()?,
+      note: reader.opt_string()?,
+      attachments: reader.attachments()?,
+      next_attachment: reader.u32()?,
+    };
+    if !reader.0.is_empty() {
+      return Err(RecordError::TrailingBytes(reader.0.len()).into());
+    }
+    Ok(content)
+  }
+
+  /// Decodes only the title and the authors, which lead the record.
+  pub fn decode_names(bytes: &[u8]) -> Result<(String, Vec<String>), Error> {
+    if !bytes.starts_with(MAGIC) {
+      let content = Self::decode(bytes)?;
+      return Ok((content.title, content.authors));
+    }
+    let mut reader = Reader(&bytes[MAGIC.len()..]);
+    match reader.take(1)?[0] {
+      VERSION => Ok((reader.string()?, reader.strings()?)),
+      version => Err(RecordError::UnknownVersion(version).into()),
+    }
+  }
+
+  pub fn is_current(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC) && bytes.get(MAGIC.len()) == Some(&VERSION)
+  }
+
+  pub fn is_legacy(bytes: &[u8]) -> bool {
+    !bytes.starts_with(MAGIC) && bytes.len() == mem::size_of::<legacy::ArticleContent>()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lmdb::traits::AsLmdbBytes;
+
+  #[test]
+  fn test_encode_decode() -> Result<(), Error> {
+    let content = ArticleContent {
+      year: Some(1985),
+      venue: Some("MIT Press".to_owned()),
+      abstract_: Some("".to_owned()),
+      keywords: vec!["lisp".to_owned(), "scheme".to_owned()],
+      attachments: vec![
+        Attachment {
+          id: 0,
+          media_type: MediaType::Pdf,
+          hash: Hash::default(),
+          name: None,
+          size: 1024,
+        },
+        Attachment {
+          id: 2,
+          media_type: MediaType::Epub,
+          hash: Hash([7; 32]),
+          name: Some("sicp.epub".to_owned()),
+          size: 4096,
+        },
+      ],
+      next_attachment: 5,
+      ..ArticleContent::new(
+        "計算機プログラムの構造と解釈",
+        ["Harold Abelson", "Gerald Jay Sussman"],
+      )?
+    };
+    let bytes = content.encode();
+    assert_eq!(ArticleContent::decode(&bytes)?, content);
+    assert!(!ArticleContent::is_legacy(&bytes));
+    assert!(ArticleContent::is_current(&bytes));
+    assert_eq!(
+      ArticleContent::decode_names(&bytes)?,
+      (content.title.clone(), content.authors.clone())
+    );
+
+    for len in 0..bytes.len() {
+      assert!(ArticleContent::decode(&bytes[..len]).is_err());
+    }
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(ArticleContent::decode(&trailing).is_err());
+    let mut future = bytes.clone();
+    future[MAGIC.len()] = VERSION + 1;
+    assert!(ArticleContent::decode(&future).is_err());
+    assert!(ArticleContent::decode_names(&future).is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn test_decode_legacy() -> Result<(), Error> {
+    let legacy = Box::new(legacy::ArticleContent::new(
+      "Literate Programming",
+      &["Donald E. Knuth", "Silvio Levy"],
+    ));
+    let bytes = legacy.as_lmdb_bytes().to_vec();
+    assert!(ArticleContent::is_legacy(&bytes));
+    assert!(!ArticleContent::is_current(&bytes));
+    assert_eq!(
+      ArticleContent::decode(&bytes)?,
+      ArticleContent::new("Literate Programming", ["Donald E. Knuth", "Silvio Levy"])?
+    );
+    Ok(())
+  }
+}
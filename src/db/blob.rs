@@ -0,0 +1,140 @@
+use failure::Error;
+use lmdb::{put, ConstAccessor, ConstTransaction, Database, LmdbResultExt, WriteAccessor};
+
+use super::hash::Hash;
+
+const KEY_LEN: usize = 44;
+
+fn key(hash: &Hash, article: u64, attachment: u32) -> [u8; KEY_LEN] {
+  let mut key = [0; KEY_LEN];
+  key[..32].copy_from_slice(&hash.0);
+  key[32..40].copy_from_slice(&article.to_be_bytes());
+  key[40..].copy_from_slice(&attachment.to_be_bytes());
+  key
+}
+
+pub struct Blobs<'a, 'env> {
+  pub txn: &'a ConstTransaction<'env>,
+  pub files: &'a Database<'static>,
+}
+
+impl<'a, 'env> Blobs<'a, 'env> {
+  pub fn refs(&self, access: &ConstAccessor, hash: &Hash) -> Result<Vec<(u64, u32)>, Error> {
+    let mut cursor = self.txn.cursor(self.files)?;
+    let mut found = Vec::new();
+    let mut item = cursor
+      .seek_range_k::<[u8], [u8]>(access, &hash.0[..])
+      .to_opt()?;
+    while let Some((key, _)) = item {
+      if key.len() != KEY_LEN || key[..32] != hash.0 {
+        break;
+      }
+      let mut article = [0; 8];
+      article.copy_from_slice(&key[32..40]);
+      let mut attachment = [0; 4];
+      attachment.copy_from_slice(&key[40..]);
+      found.push((u64::from_be_bytes(article), u32::from_be_bytes(attachment)));
+      item = cursor.next::<[u8], [u8]>(access).to_opt()?;
+    }
+    Ok(found)
+  }
+
+  pub fn all(&self, access: &ConstAccessor) -> Result<Vec<(Hash, u64, u32)>, Error> {
+    let mut cursor = self.txn.cursor(self.files)?;
+    let mut found = Vec::new();
+    let mut item = cursor.first::<[u8], [u8]>(access).to_opt()?;
+    while let Some((key, _)) = item {
+      if key.len() == KEY_LEN {
+        let mut hash = Hash::default();
+        hash.0.copy_from_slice(&key[..32]);
+        let mut article = [0; 8];
+        article.copy_from_slice(&key[32..40]);
+        let mut attachment = [0; 4];
+        attachment.copy_from_slice(&key[40..]);
+        found.push((
+          hash,
+          u64::from_be_bytes(article),
+          u32::from_be_bytes(attachment),
+        ));
+      }
+      item = cursor.next::<[u8], [u8]>(access).to_opt()?;
+    }
+    Ok(found)
+  }
+
+  pub fn add(
+    &self,
+    access: &mut WriteAccessor,
+    hash: &Hash,
+    article: u64,
+    attachment: u32,
+  ) -> Result<(), Error> {
+    let empty: &[u8] = &[];
+    access.put(
+      self.files,
+      &key(hash, article, attachment)[..],
+      empty,
+      put::Flags::empty(),
+    )?;
+    Ok(())
+  }
+
+  pub fn del(
+    &self,
+    access: &mut WriteAccessor,
+    hash: &Hash,
+    article: u64,
+    attachment: u32,
+  ) -> Result<(), Error> {
+    access
+      .del_key(self.files, &key(hash, article, attachment)[..])
+      .to_opt()?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lmdb::{db, open, DatabaseOptions, EnvBuilder, WriteTransaction};
+  use std::sync::Arc;
+  use tempfile::tempdir;
+
+  #[test]
+  fn test_blobs() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let env = Arc::new(unsafe {
+      let mut builder = EnvBuilder::new()?;
+      builder.set_maxdbs(1)?;
+      builder.open(
+        dir.path().to_string_lossy().as_ref(),
+        open::Flags::empty(),
+        0o600,
+      )?
+    });
+    let files = Database::open(env.clone(), Some("f"), &DatabaseOptions::new(db::CREATE))?;
+    let txn = WriteTransaction::new(env.clone())?;
+    let db = Blobs {
+      txn: &txn,
+      files: &files,
+    };
+    let mut access = txn.access();
+    let (a, b) = (Hash([1; 32]), Hash([2; 32]));
+
+    db.add(&mut access, &a, 3, 0)?;
+    db.add(&mut access, &a, 1, 2)?;
+    db.add(&mut access, &b, 3, 1)?;
+    assert_eq!(db.refs(&access, &a)?, vec![(1, 2), (3, 0)]);
+    assert_eq!(db.refs(&access, &b)?, vec![(3, 1)]);
+    assert_eq!(db.refs(&access, &Hash([0; 32]))?, vec![]);
+    assert_eq!(db.all(&access)?, vec![(a, 1, 2), (a, 3, 0), (b, 3, 1)]);
+
+    db.del(&mut access, &a, 3, 0)?;
+    assert_eq!(db.refs(&access, &a)?, vec![(1, 2)]);
+    db.del(&mut access, &a, 1, 2)?;
+    db.del(&mut access, &a, 1, 2)?;
+    assert_eq!(db.refs(&access, &a)?, vec![]);
+    assert_eq!(db.refs(&access, &b)?, vec![(3, 1)]);
+    Ok(())
+  }
+}
@@ -0,0 +1,453 @@
+use std::collections::HashSet;
+
+use failure::Error;
+use lmdb::{put, ConstAccessor, ConstTransaction, Database, LmdbResultExt, WriteAccessor};
+
+use super::Store;
+
+const MAX_NAME_LEN: usize = 256;
+const NO_PARENT: u64 = u64::MAX;
+/// The key under which the collections database keeps the next id.
+const NEXT_ID: u64 = u64::MAX;
+
+#[derive(Debug, Fail)]
+pub enum CollectionError {
+  #[fail(display = "The collection id={} is not found.", _0)]
+  NotFound(u64),
+  #[fail(display = "The name is empty.")]
+  EmptyName,
+  #[fail(display = "The name is {} bytes, longer than {} bytes.", _0, _1)]
+  NameTooLong(usize, usize),
+  #[fail(display = "A collection name cannot contain '/'.")]
+  SlashInName,
+  #[fail(display = "'{}' already exists here.", _0)]
+  Duplicate(String),
+  #[fail(display = "A tag cannot be nested.")]
+  NestedTag,
+  #[fail(display = "The parent id={} is a tag, not a collection.", _0)]
+  TagParent(u64),
+}
+
+#[derive(Serialize, Deserialize, Default, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+  #[default]
+  Collection = 1,
+  Tag = 2,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Collection {
+  pub id: u64,
+  pub kind: Kind,
+  pub name: String,
+  pub parent: Option<u64>,
+}
+
+impl Collection {
+  fn encode(&self) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(9 + self.name.len());
+    buf.push(self.kind as u8);
+    buf.extend_from_slice(&self.parent.unwrap_or(NO_PARENT).to_le_bytes());
+    buf.extend_from_slice(self.name.as_bytes());
+    buf
+  }
+
+  fn decode(id: u64, bytes: &[u8]) -> Result<Self, Error> {
+    if bytes.len() < 9 {
+      bail!("The collection record id={} is truncated.", id);
+    }
+    let kind = match bytes[0] {
+      1 => Kind::Collection,
+      2 => Kind::Tag,
+      k => bail!("The collection record id={} has an unknown kind {}.", id, k),
+    };
+    let mut parent = [0; 8];
+    parent.copy_from_slice(&bytes[1..9]);
+    let parent = u64::from_le_bytes(parent);
+    Ok(Self {
+      id,
+      kind,
+      name: String::from_utf8(bytes[9..].to_vec())?,
+      parent: Some(parent).filter(|&p| p != NO_PARENT),
+    })
+  }
+}
+
+pub fn validate_name<S: AsRef<str>>(name: S, kind: Kind) -> Result<String, CollectionError> {
+  let name = name.as_ref().trim();
+  if name.is_empty() {
+    return Err(CollectionError::EmptyName);
+  }
+  if name.len() > MAX_NAME_LEN {
+    return Err(CollectionError::NameTooLong(name.len(), MAX_NAME_LEN));
+  }
+  if kind == Kind::Collection && name.contains('/') {
+    return Err(CollectionError::SlashInName);
+  }
+  Ok(name.to_owned())
+}
+
+fn pair(a: u64, b: u64) -> [u8; 16] {
+  let mut key = [0; 16];
+  key[..8].copy_from_slice(&a.to_be_bytes());
+  key[8..].copy_from_slice(&b.to_be_bytes());
+  key
+}
+
+pub struct Collections<'a, 'env> {
+  pub txn: &'a ConstTransaction<'env>,
+  pub collections: &'a Database<'static>,
+  pub members: &'a Database<'static>,
+  pub memberships: &'a Database<'static>,
+}
+
+impl<'a, 'env> Collections<'a, 'env> {
+  fn scan(&self, access: &ConstAccessor, db: &Database, prefix: u64) -> Result<Vec<u64>, Error> {
+    let prefix = prefix.to_be_bytes();
+    let mut cursor = self.txn.cursor(db)?;
+    let mut found = Vec::new();
+    let mut item = cursor
+      .seek_range_k::<[u8], [u8]>(access, &prefix[..])
+      .to_opt()?;
+    while let Some((key, _)) = item {
+      if key.len() != 16 || key[..8] != prefix {
+        break;
+      }
+      let mut id = [0; 8];
+      id.copy_from_slice(&key[8..]);
+      found.push(u64::from_be_bytes(id));
+      item = cursor.next::<[u8], [u8]>(access).to_opt()?;
+    }
+    Ok(found)
+  }
+
+  pub fn all(&self, access: &ConstAccessor) -> Result<Vec<Collection>, Error> {
+    let mut cursor = self.txn.cursor(self.collections)?;
+    let mut collections = Vec::new();
+    let mut item = cursor.first::<[u8], [u8]>(access).to_opt()?;
+    while let Some((key, bytes)) = item {
+      if let Some(id) = Store::decode_key(key).filter(|&id| id != NEXT_ID) {
+        collections.push(Collection::decode(id, bytes)?);
+      }
+      item = cursor.next::<[u8], [u8]>(access).to_opt()?;
+    }
+    Ok(collections)
+  }
+
+  pub fn get(&self, access: &ConstAccessor, id: u64) -> Result<Collection, Error> {
+    match access.get::<u64, [u8]>(self.collections, &id).to_opt()? {
+      Some(bytes) if id != NEXT_ID => Collection::decode(id, bytes),
+      _ => Err(CollectionError::NotFound(id).into()),
+    }
+  }
+
+  fn next_id(&self, access: &ConstAccessor) -> Result<u64, Error> {
+    match access
+      .get::<u64, [u8]>(self.collections, &NEXT_ID)
+      .to_opt()?
+    {
+      Some(bytes) if bytes.len() == 8 => {
+        let mut next = [0; 8];
+        next.copy_from_slice(bytes);
+        Ok(u64::from_le_bytes(next))
+      }
+      Some(_) => bail!("The next collection id is corrupted."),
+      // Collections created before the counter was kept.
+      None => Ok(
+        self
+          .all(access)?
+          .iter()
+          .map(|c| c.id + 1)
+          .max()
+          .unwrap_or(0),
+      ),
+    }
+  }
+
+  fn check(&self, access: &ConstAccessor, collection: &Collection) -> Result<(), Error> {
+    if let Some(parent) = collection.parent {
+      if collection.kind == Kind::Tag {
+        return Err(CollectionError::NestedTag.into());
+      }
+      if self.get(access, parent)?.kind == Kind::Tag {
+        return Err(CollectionError::TagParent(parent).into());
+      }
+    }
+    let duplicate = self.all(access)?.into_iter().any(|c| {
+      c.id != collection.id
+        && c.kind == collection.kind
+        && c.parent == collection.parent
+        && c.name.to_lowercase() == collection.name.to_lowercase()
+    });
+    if duplicate {
+      return Err(CollectionError::Duplicate(collection.name.clone()).into());
+    }
+    Ok(())
+  }
+
+  pub fn create(
+    &self,
+    access: &mut WriteAccessor,
+    kind: Kind,
+    name: String,
+    parent: Option<u64>,
+  ) -> Result<Collection, Error> {
+    let id = self.next_id(access)?;
+    let collection = Collection {
+      id,
+      kind,
+      name: validate_name(name, kind)?,
+      parent,
+    };
+    self.check(access, &collection)?;
+    access.put(
+      self.collections,
+      &id,
+      &collection.encode()[..],
+      put::NOOVERWRITE,
+    )?;
+    access.put(
+      self.collections,
+      &NEXT_ID,
+      &(id + 1).to_le_bytes()[..],
+      put::Flags::empty(),
+    )?;
+    Ok(collection)
+  }
+
+  pub fn rename(
+    &self,
+    access: &mut WriteAccessor,
+    id: u64,
+    name: String,
+  ) -> Result<Collection, Error> {
+    let mut collection = self.get(access, id)?;
+    collection.name = validate_name(name, collection.kind)?;
+    self.check(access, &collection)?;
+    access.put(
+      self.collections,
+      &id,
+      &collection.encode()[..],
+      put::Flags::empty(),
+    )?;
+    Ok(collection)
+  }
+
+  pub fn descendants(&self, access: &ConstAccessor, id: u64) -> Result<Vec<u64>, Error> {
+    let all = self.all(access)?;
+    let mut found = vec![id];
+    let mut i = 0;
+    while i < found.len() {
+      let parent = found[i];
+      found.extend(
+        all
+          .iter()
+          .filter(|c| c.parent == Some(parent))
+          .map(|c| c.id),
+      );
+      i += 1;
+    }
+    Ok(found)
+  }
+
+  pub fn delete(&self, access: &mut WriteAccessor, id: u64) -> Result<Collection, Error> {
+    let collection = self.get(access, id)?;
+    for id in self.descendants(access, id)? {
+      for article in self.scan(access, self.members, id)? {
+        access.del_key(self.members, &pair(id, article)[..])?;
+        access.del_key(self.memberships, &pair(article, id)[..])?;
+      }
+      access.del_key(self.collections, &id)?;
+    }
+    Ok(collection)
+  }
+
+  pub fn assign(
+    &self,
+    access: &mut WriteAccessor,
+    id: u64,
+    article: u64,
+    member: bool,
+  ) -> Result<Collection, Error> {
+    let collection = self.get(access, id)?;
+    if member {
+      let empty: &[u8] = &[];
+      access.put(
+        self.members,
+        &pair(id, article)[..],
+        empty,
+        put::Flags::empty(),
+      )?;
+      access.put(
+        self.memberships,
+        &pair(article, id)[..],
+        empty,
+        put::Flags::empty(),
+      )?;
+    } else {
+      access
+        .del_key(self.members, &pair(id, article)[..])
+        .to_opt()?;
+      access
+        .del_key(self.memberships, &pair(article, id)[..])
+        .to_opt()?;
+    }
+    Ok(collection)
+  }
+
+  pub fn memberships(
+    &self,
+    access: &ConstAccessor,
+    article: u64,
+  ) -> Result<Vec<Collection>, Error> {
+    self
+      .scan(access, self.memberships, article)?
+      .into_iter()
+      .map(|id| self.get(access, id))
+      .collect()
+  }
+
+  pub fn forget(&self, access: &mut WriteAccessor, article: u64) -> Result<(), Error> {
+    for id in self.scan(access, self.memberships, article)? {
+      access.del_key(self.members, &pair(id, article)[..])?;
+      access.del_key(self.memberships, &pair(article, id)[..])?;
+    }
+    Ok(())
+  }
+
+  pub fn resolve(&self, access: &ConstAccessor, kind: Kind, name: &str) -> Result<Vec<u64>, Error> {
+    let all = self.all(access)?;
+    let name = name.trim().to_lowercase();
+    let found = match kind {
+      Kind::Tag => all
+        .iter()
+        .find(|c| c.kind == Kind::Tag && c.name.to_lowercase() == name)
+        .map(|c| c.id),
+      Kind::Collection => {
+        let mut parent = None;
+        let mut found = None;
+        for part in name.split('/').map(str::trim).filter(|p| !p.is_empty()) {
+          found = all
+            .iter()
+            .find(|c| {
+              c.kind == Kind::Collection && c.parent == parent && c.name.to_lowercase() == part
+            })
+            .map(|c| c.id);
+          match found {
+            Some(id) => parent = Some(id),
+            None => break,
+          }
+        }
+        found.or_else(|| {
+          name.parse().ok().filter(|id| {
+            all
+              .iter()
+              .any(|c| c.kind == Kind::Collection && c.id == *id)
+          })
+        })
+      }
+    };
+    let ids = match found {
+      Some(id) => self.descendants(access, id)?,
+      None => return Ok(Vec::new()),
+    };
+    let mut articles = HashSet::new();
+    for id in ids {
+      articles.extend(self.scan(access, self.members, id)?);
+    }
+    Ok(articles.into_iter().collect())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lmdb::{db, open, DatabaseOptions, EnvBuilder, WriteTransaction};
+  use std::sync::Arc;
+  use tempfile::tempdir;
+
+  #[test]
+  fn test_collections() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let env = Arc::new(unsafe {
+      let mut builder = EnvBuilder::new()?;
+      builder.set_maxdbs(3)?;
+      builder.open(
+        dir.path().to_string_lossy().as_ref(),
+        open::Flags::empty(),
+        0o600,
+      )?
+    });
+    let named =
+      |name: &str| Database::open(env.clone(), Some(name), &DatabaseOptions::new(db::CREATE));
+    let (collections, members, memberships) = (named("c")?, named("m")?, named("s")?);
+    let txn = WriteTransaction::new(env.clone())?;
+    let db = Collections {
+      txn: &txn,
+      collections: &collections,
+      members: &members,
+      memberships: &memberships,
+    };
+    let mut access = txn.access();
+
+    let thesis = db.create(&mut access, Kind::Collection, " Thesis ".to_owned(), None)?;
+    assert_eq!(thesis.name, "Thesis");
+    let related = db.create(
+      &mut access,
+      Kind::Collection,
+      "Related".to_owned(),
+      Some(thesis.id),
+    )?;
+    let tag = db.create(&mut access, Kind::Tag, "toread".to_owned(), None)?;
+    assert!(db
+      .create(
+        &mut access,
+        Kind::Collection,
+        "related".to_owned(),
+        Some(thesis.id)
+      )
+      .is_err());
+    assert!(db
+      .create(&mut access, Kind::Collection, "a/b".to_owned(), None)
+      .is_err());
+    assert!(db
+      .create(
+        &mut access,
+        Kind::Collection,
+        "Nested".to_owned(),
+        Some(tag.id)
+      )
+      .is_err());
+
+    db.assign(&mut access, related.id, 7, true)?;
+    db.assign(&mut access, tag.id, 7, true)?;
+    db.assign(&mut access, tag.id, 8, true)?;
+    assert_eq!(
+      db.memberships(&access, 7)?,
+      vec![related.clone(), tag.clone()]
+    );
+    assert_eq!(db.resolve(&access, Kind::Collection, "thesis")?, vec![7]);
+    assert_eq!(
+      db.resolve(&access, Kind::Collection, "Thesis/Related")?,
+      vec![7]
+    );
+    assert_eq!(
+      db.resolve(&access, Kind::Collection, "toread")?,
+      Vec::<u64>::new()
+    );
+    let mut tagged = db.resolve(&access, Kind::Tag, "TOREAD")?;
+    tagged.sort();
+    assert_eq!(tagged, vec![7, 8]);
+
+    db.forget(&mut access, 8)?;
+    assert_eq!(db.resolve(&access, Kind::Tag, "toread")?, vec![7]);
+    db.delete(&mut access, thesis.id)?;
+    assert_eq!(db.all(&access)?, vec![tag.clone()]);
+    assert_eq!(db.memberships(&access, 7)?, vec![tag.clone()]);
+    db.delete(&mut access, tag.id)?;
+    let next = db.create(&mut access, Kind::Tag, "toread".to_owned(), None)?;
+    assert_eq!(next.id, tag.id + 1);
+    Ok(())
+  }
+}
@@ -0,0 +1,415 @@
+use std::collections::{HashMap, HashSet};
+
+use super::article::{family_name, fold, ArticleContent, Attachment};
+use super::search::tokenize;
+
+const TITLE_WEIGHT: f64 = 0.7;
+const DIFFERENT_DOI_PENALTY: f64 = 0.5;
+const ARXIV_DOI_PREFIX: &str = "10.48550/arxiv.";
+
+#[derive(Debug, Fail)]
+pub enum MergeError {
+  #[fail(display = "No article is given to merge.")]
+  Empty,
+  #[fail(display = "The article id={} cannot be merged into itself.", _0)]
+  SameArticle(u64),
+  #[fail(display = "The article id={} is not found.", _0)]
+  NotFound(u64),
+}
+
+fn jaccard(a: &[String], b: &[String]) -> f64 {
+  if a.is_empty() && b.is_empty() {
+    return 0.0;
+  }
+  let (mut i, mut j, mut common) = (0, 0, 0);
+  while i < a.len() && j < b.len() {
+    if a[i] == b[j] {
+      common += 1;
+      i += 1;
+      j += 1;
+    } else if a[i] < b[j] {
+      i += 1;
+    } else {
+      j += 1;
+    }
+  }
+  common as f64 / (a.len() + b.len() - common) as f64
+}
+
+fn sorted(mut words: Vec<String>) -> Vec<String> {
+  words.sort();
+  words.dedup();
+  words
+}
+
+#[derive(Debug)]
+pub struct Signature {
+  title: Vec<String>,
+  authors: Vec<String>,
+  doi: Option<String>,
+  arxiv: Option<String>,
+}
+
+impl Signature {
+  pub fn new(content: &ArticleContent) -> Self {
+    let mut doi = content.doi.as_ref().map(|d| d.trim().to_lowercase());
+    let mut arxiv = content.arxiv.as_ref().map(|a| a.trim().to_lowercase());
+    if let Some(id) = doi
+      .as_ref()
+      .filter(|d| d.starts_with(ARXIV_DOI_PREFIX))
+      .map(|d| d[ARXIV_DOI_PREFIX.len()..].to_owned())
+    {
+      arxiv = arxiv.or(Some(id));
+      doi = None;
+    }
+    Self {
+      title: sorted(tokenize(&content.title).iter().map(|w| fold(w)).collect()),
+      authors: sorted(
+        content
+          .authors
+          .iter()
+          .map(|a| fold(family_name(a)))
+          .filter(|a| !a.is_empty())
+          .collect(),
+      ),
+      doi: doi.filter(|d| !d.is_empty()),
+      arxiv: arxiv.filter(|a| !a.is_empty()),
+    }
+  }
+
+  pub fn similarity(&self, other: &Self) -> f64 {
+    let same = |a: &Option<String>, b: &Option<String>| a.is_some() && a == b;
+    if same(&self.doi, &other.doi) || same(&self.arxiv, &other.arxiv) {
+      return 1.0;
+    }
+    let title = jaccard(&self.title, &other.title);
+    let score = if self.authors.is_empty() || other.authors.is_empty() {
+      title
+    } else {
+      TITLE_WEIGHT * title + (1.0 - TITLE_WEIGHT) * jaccard(&self.authors, &other.authors)
+    };
+    if self.doi.is_some() && other.doi.is_some() {
+      score * DIFFERENT_DOI_PENALTY
+    } else {
+      score
+    }
+  }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Pair {
+  pub a: u64,
+  pub b: u64,
+  pub score: f64,
+}
+
+fn candidates(docs: &[(u64, Signature)], threshold: f64) -> HashSet<(usize, usize)> {
+  let mut found = HashSet::new();
+  let mut add = |i: usize, j: usize| {
+    if i != j {
+      found.insert((i.min(j), i.max(j)));
+    }
+  };
+  let mut ids: HashMap<&str, Vec<usize>> = HashMap::new();
+  for (i, (_, sig)) in docs.iter().enumerate() {
+    for id in sig.doi.iter().chain(sig.arxiv.iter()) {
+      ids.entry(id.as_str()).or_default().push(i);
+    }
+  }
+  for group in ids.values() {
+    for &i in group.iter() {
+      for &j in group.iter() {
+        add(i, j);
+      }
+    }
+  }
+
+  let min_title = (threshold - (1.0 - TITLE_WEIGHT)) / TITLE_WEIGHT;
+  if min_title <= 0.0 {
+    for i in 0..docs.len() {
+      for j in i + 1..docs.len() {
+        add(i, j);
+      }
+    }
+    return found;
+  }
+  let mut freqs: HashMap<&str, usize> = HashMap::new();
+  for (_, sig) in docs.iter() {
+    for word in sig.title.iter() {
+      *freqs.entry(word.as_str()).or_insert(0) += 1;
+    }
+  }
+  let mut index: HashMap<&str, Vec<usize>> = HashMap::new();
+  for (i, (_, sig)) in docs.iter().enumerate() {
+    let mut words: Vec<&str> = sig.title.iter().map(String::as_str).collect();
+    words.sort_by_key(|w| (freqs[w], *w));
+    let prefix = words.len() - (min_title.min(1.0) * words.len() as f64).ceil() as usize + 1;
+    for word in words.into_iter().take(prefix) {
+      let postings = index.entry(word).or_default();
+      for &j in postings.iter() {
+        add(i, j);
+      }
+      postings.push(i);
+    }
+  }
+  found
+}
+
+pub fn pairs(docs: &[(u64, Signature)], threshold: f64) -> Vec<Pair> {
+  let mut pairs: Vec<Pair> = candidates(docs, threshold)
+    .into_iter()
+    .filter_map(|(i, j)| {
+      let score = docs[i].1.similarity(&docs[j].1);
+      (score >= threshold).then(|| Pair {
+        a: docs[i].0.min(docs[j].0),
+        b: docs[i].0.max(docs[j].0),
+        score,
+      })
+    })
+    .collect();
+  pairs.sort_by_key(|p| (p.a, p.b));
+  pairs
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Cluster {
+  pub ids: Vec<u64>,
+  pub score: f64,
+  pub pairs: Vec<Pair>,
+}
+
+fn root(parents: &mut HashMap<u64, u64>, id: u64) -> u64 {
+  let parent = *parents.entry(id).or_insert(id);
+  if parent == id {
+    return id;
+  }
+  let r = root(parents, parent);
+  parents.insert(id, r);
+  r
+}
+
+pub fn clusters(pairs: Vec<Pair>) -> Vec<Cluster> {
+  let mut parents = HashMap::new();
+  for pair in pairs.iter() {
+    let (a, b) = (root(&mut parents, pair.a), root(&mut parents, pair.b));
+    parents.insert(a.max(b), a.min(b));
+  }
+  let mut clusters: HashMap<u64, Cluster> = HashMap::new();
+  for pair in pairs {
+    let cluster = clusters
+      .entry(root(&mut parents, pair.a))
+      .or_insert_with(|| Cluster {
+        ids: Vec::new(),
+        score: 0.0,
+        pairs: Vec::new(),
+      });
+    cluster.ids.push(pair.a);
+    cluster.ids.push(pair.b);
+    cluster.score = cluster.score.max(pair.score);
+    cluster.pairs.push(pair);
+  }
+  let mut clusters: Vec<Cluster> = clusters
+    .into_values()
+    .map(|mut c| {
+      c.ids.sort();
+      c.ids.dedup();
+      c
+    })
+    .collect();
+  clusters.sort_by(|x, y| {
+    y.score
+      .partial_cmp(&x.score)
+      .unwrap_or(std::cmp::Ordering::Equal)
+      .then_with(|| x.ids.cmp(&y.ids))
+  });
+  clusters
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Moved {
+  pub article: u64,
+  pub from: Attachment,
+  pub into: Option<u32>,
+}
+
+pub fn merge(
+  mut into: ArticleContent,
+  from: &[(u64, ArticleContent)],
+) -> (ArticleContent, Vec<Moved>) {
+  let mut moved = Vec::new();
+  for (id, other) in from.iter() {
+    if into.authors.is_empty() {
+      into.authors = other.authors.clone();
+    }
+    into.year = into.year.or(other.year);
+    into.venue = into.venue.or_else(|| other.venue.clone());
+    into.volume = into.volume.or_else(|| other.volume.clone());
+    into.pages = into.pages.or_else(|| other.pages.clone());
+    into.doi = into.doi.or_else(|| other.doi.clone());
+    into.arxiv = into.arxiv.or_else(|| other.arxiv.clone());
+    into.abstract_ = into.abstract_.or_else(|| other.abstract_.clone());
+    for keyword in other.keywords.iter() {
+      if !into
+        .keywords
+        .iter()
+        .any(|k| k.to_lowercase() == keyword.to_lowercase())
+      {
+        into.keywords.push(keyword.clone());
+      }
+    }
+    into.note = match (into.note, other.note.as_ref()) {
+      (Some(note), Some(other)) if !note.contains(other.as_str()) => {
+        Some(format!("{}\n\n{}", note, other))
+      }
+      (note, other) => note.or_else(|| other.cloned()),
+    };
+    for attachment in other.attachments.iter() {
+      let target = if into.attachments.iter().any(|a| a.hash == attachment.hash) {
+        None
+      } else {
+        let target = Attachment {
+          id: into.new_attachment_id(),
+          ..attachment.clone()
+        };
+        into.attachments.push(target.clone());
+        Some(target.id)
+      };
+      moved.push(Moved {
+        article: *id,
+        from: attachment.clone(),
+        into: target,
+      });
+    }
+  }
+  (into, moved)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::super::article::MediaType;
+  use super::super::hash::Hash;
+  use super::*;
+  use failure::Error;
+
+  fn content(title: &str, authors: &[&str]) -> ArticleContent {
+    ArticleContent::new(title, authors).unwrap()
+  }
+
+  #[test]
+  fn test_similarity() {
+    let arxiv = Signature::new(&ArticleContent {
+      doi: Some("10.48550/arXiv.1706.03762".to_owned()),
+      ..content(
+        "Attention Is All You Need",
+        &["Ashish Vaswani", "Noam Shazeer", "Łukasz Kaiser"],
+      )
+    });
+    let published = Signature::new(&ArticleContent {
+      doi: Some("10.5555/3295222.3295349".to_owned()),
+      ..content(
+        "Attention is all you need.",
+        &["Vaswani, Ashish", "Kaiser, Lukasz"],
+      )
+    });
+    assert_eq!(arxiv.arxiv, Some("1706.03762".to_owned()));
+    assert_eq!(arxiv.doi, None);
+    assert!((arxiv.similarity(&published) - 0.9).abs() < 1e-9);
+
+    let other = Signature::new(&content("Attention Is Not Explanation", &["Sarthak Jain"]));
+    assert!(arxiv.similarity(&other) < 0.5);
+
+    let by_arxiv = Signature::new(&ArticleContent {
+      arxiv: Some("1706.03762".to_owned()),
+      ..content("Transformer", &[])
+    });
+    assert_eq!(arxiv.similarity(&by_arxiv), 1.0);
+  }
+
+  #[test]
+  fn test_clusters() {
+    let docs: Vec<(u64, Signature)> = vec![
+      (
+        1,
+        content("Deep Residual Learning for Image Recognition", &["He"]),
+      ),
+      (2, content("Literate Programming", &["Knuth"])),
+      (
+        3,
+        content("Deep residual learning for image recognition", &["He"]),
+      ),
+      (
+        5,
+        content("Deep Residual Learning for Image Recognition.", &[]),
+      ),
+      (8, content("Literate programming", &["Knuth", "Levy"])),
+      (9, content("The Art of Computer Programming", &["Knuth"])),
+    ]
+    .into_iter()
+    .map(|(id, c)| (id, Signature::new(&c)))
+    .collect();
+    let found = clusters(pairs(&docs, 0.8));
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].ids, vec![1, 3, 5]);
+    assert_eq!(found[0].score, 1.0);
+    assert_eq!(found[0].pairs.len(), 3);
+    assert_eq!(found[1].ids, vec![2, 8]);
+    assert!((found[1].score - 0.85).abs() < 1e-9);
+
+    assert_eq!(clusters(pairs(&docs, 0.9)).len(), 1);
+    assert!(clusters(pairs(&docs, 0.1))
+      .iter()
+      .any(|c| c.ids.contains(&9)));
+  }
+
+  #[test]
+  fn test_merge() -> Result<(), Error> {
+    let pdf = |id, hash| Attachment {
+      id,
+      media_type: MediaType::Pdf,
+      hash: Hash([hash; 32]),
+      name: None,
+      size: 1,
+    };
+    let into = ArticleContent {
+      keywords: vec!["Transformer".to_owned()],
+      note: Some("publisher".to_owned()),
+      attachments: vec![pdf(0, 1)],
+      ..content("Attention Is All You Need", &[])
+    };
+    let from = vec![(
+      4,
+      ArticleContent {
+        arxiv: Some("1706.03762".to_owned()),
+        year: Some(2017),
+        keywords: vec!["transformer".to_owned(), "attention".to_owned()],
+        note: Some("preprint".to_owned()),
+        attachments: vec![pdf(0, 1), pdf(3, 2)],
+        ..content("Attention is all you need", &["Ashish Vaswani"])
+      },
+    )];
+    let (merged, moved) = merge(into, &from);
+    assert_eq!(merged.title, "Attention Is All You Need");
+    assert_eq!(merged.authors, vec!["Ashish Vaswani"]);
+    assert_eq!(merged.year, Some(2017));
+    assert_eq!(merged.arxiv, Some("1706.03762".to_owned()));
+    assert_eq!(merged.keywords, vec!["Transformer", "attention"]);
+    assert_eq!(merged.note, Some("publisher\n\npreprint".to_owned()));
+    assert_eq!(merged.attachments, vec![pdf(0, 1), pdf(1, 2)]);
+    assert_eq!(
+      moved,
+      vec![
+        Moved {
+          article: 4,
+          from: pdf(0, 1),
+          into: None,
+        },
+        Moved {
+          article: 4,
+          from: pdf(3, 2),
+          into: Some(1),
+        },
+      ]
+    );
+    Ok(())
+  }
+}
@@ -0,0 +1,346 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+
+use failure::Error;
+use lmdb::{ReadTransaction, WriteTransaction};
+
+use super::article::{Article, ArticleContent};
+use super::hash::Hash;
+use super::Store;
+use crate::collection::Violation;
+use crate::config::Config;
+
+/// An inconsistency found by [`Db::fsck`].
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+  Pending,
+  Corrupt(u64, String),
+  MissingFile(u64, u32, String),
+  MissingRef(Hash, u64, u32),
+  StaleRef(Hash, u64, u32),
+  OrphanFile(String),
+  OrphanRevisions(u64),
+  Unknown(String),
+  Tree(&'static str, Violation),
+  IdMissing(u64),
+  IdExtra(u64),
+  IdNext(u64),
+  DocMissing(u64),
+  DocExtra(u64),
+}
+
+impl Problem {
+  pub fn repairable(&self) -> bool {
+    !matches!(
+      self,
+      Problem::Corrupt(..) | Problem::MissingFile(..) | Problem::Unknown(_)
+    )
+  }
+
+  fn index(&self) -> bool {
+    matches!(
+      self,
+      Problem::Pending
+        | Problem::Tree(..)
+        | Problem::IdMissing(_)
+        | Problem::IdExtra(_)
+        | Problem::IdNext(_)
+        | Problem::DocMissing(_)
+        | Problem::DocExtra(_)
+    )
+  }
+}
+
+impl fmt::Display for Problem {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Problem::Pending => write!(f, "journal: an interrupted write is pending"),
+      Problem::Corrupt(id, e) => write!(f, "data: the record of id={} is unreadable ({})", id, e),
+      Problem::MissingFile(id, attachment, name) => write!(
+        f,
+        "content: the attachment {} of id={} is missing ({})",
+        attachment, id, name
+      ),
+      Problem::MissingRef(hash, id, attachment) => write!(
+        f,
+        "files: the attachment {} of id={} is not referenced ({})",
+        attachment, id, hash
+      ),
+      Problem::StaleRef(hash, id, attachment) => write!(
+        f,
+        "files: a reference to the attachment {} of id={} is stale ({})",
+        attachment, id, hash
+      ),
+      Problem::OrphanFile(name) => write!(f, "content: {} is not referenced", name),
+      Problem::OrphanRevisions(id) => {
+        write!(f, "content: the revisions of the missing id={} remain", id)
+      }
+      Problem::Unknown(name) => write!(f, "content: {} is not recognized", name),
+      Problem::Tree(name, violation) => write!(f, "index/{}: {}", name, violation),
+      Problem::IdMissing(id) => write!(f, "index/data: id={} is not indexed", id),
+      Problem::IdExtra(id) => write!(f, "index/data: id={} does not exist", id),
+      Problem::IdNext(next) => write!(f, "index/next: {} is already in use", next),
+      Problem::DocMissing(id) => write!(f, "index/docs: id={} is not searchable", id),
+      Problem::DocExtra(id) => write!(f, "index/docs: id={} does not exist", id),
+    }
+  }
+}
+
+fn is_blob(name: &str) -> bool {
+  let stem = name.split('.').next().unwrap_or("");
+  stem.len() == 64 && stem.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn diff<F>(problems: &mut Vec<Problem>, found: &BTreeSet<u64>, expected: &BTreeSet<u64>, f: F)
+where
+  F: Fn(u64, bool) -> Problem,
+{
+  problems.extend(expected.difference(found).map(|&id| f(id, true)));
+  problems.extend(found.difference(expected).map(|&id| f(id, false)));
+}
+
+impl Store {
+  /// Checks the records, the indices and the files of a store that is not
+  /// open elsewhere, and repairs what it can when `repair` is set.
+  pub fn fsck(config: &Config, repair: bool) -> Result<Vec<Problem>, Error> {
+    let mut db = Self::load(config)?;
+    let problems = db.check()?;
+    if repair && problems.iter().any(Problem::repairable) {
+      db.repair(&problems)?;
+      info!(
+        "Store {} problems found by fsck are repaired.",
+        problems.iter().filter(|p| p.repairable()).count()
+      );
+    }
+    Ok(problems)
+  }
+
+  pub(super) fn check(&self) -> Result<Vec<Problem>, Error> {
+    let mut problems = Vec::new();
+    if self.journal.pending().is_some() {
+      problems.push(Problem::Pending);
+    }
+
+    let keys: BTreeSet<u64> = Self::keys(&self.env, &self.db)?.into_iter().collect();
+    let mut readable = BTreeSet::new();
+    let mut refs = BTreeSet::new();
+    let mut blobs = BTreeSet::new();
+    let txn = ReadTransaction::new(self.env.clone())?;
+    let access = txn.access();
+    for &key in keys.iter() {
+      let bytes = access.get::<u64, [u8]>(&self.db, &key)?;
+      let content = match ArticleContent::decode(bytes) {
+        Ok(content) => content,
+        Err(e) => {
+          problems.push(Problem::Corrupt(key, e.to_string()));
+          continue;
+        }
+      };
+      readable.insert(key);
+      for attachment in content.attachments.iter() {
+        let name = attachment.filename();
+        if !self.content_dir().join(&name).is_file() {
+          problems.push(Problem::MissingFile(key, attachment.id, name.clone()));
+        }
+        refs.insert((attachment.hash, key, attachment.id));
+        blobs.insert(name);
+      }
+    }
+    let stored: BTreeSet<_> = self.blobs(&txn).all(&access)?.into_iter().collect();
+    problems.extend(
+      refs
+        .difference(&stored)
+        .map(|&(hash, id, attachment)| Problem::MissingRef(hash, id, attachment)),
+    );
+    problems.extend(
+      stored
+        .difference(&refs)
+        .filter(|(_, id, _)| readable.contains(id) || !keys.contains(id))
+        .map(|&(hash, id, attachment)| Problem::StaleRef(hash, id, attachment)),
+    );
+
+    for entry in fs::read_dir(self.content_dir())? {
+      let entry = entry?;
+      let name = entry.file_name().to_string_lossy().into_owned();
+      if entry.file_type()?.is_dir() {
+        match name
+          .trim_end_matches(".revisions")
+          .parse::<u64>()
+          .ok()
+          .filter(|_| name.ends_with(".revisions"))
+        {
+          Some(id) if !keys.contains(&id) => problems.push(Problem::OrphanRevisions(id)),
+          Some(_) => {}
+          None => problems.push(Problem::Unknown(name)),
+        }
+      } else if !is_blob(&name) {
+        problems.push(Problem::Unknown(name));
+      } else if !blobs.contains(&name) {
+        problems.push(Problem::OrphanFile(name));
+      }
+    }
+
+    let violations = self.id.violations();
+    if violations.is_empty() {
+      let ids = self.id.iter().collect();
+      diff(&mut problems, &ids, &keys, |id, missing| match missing {
+        true => Problem::IdMissing(id),
+        false => Problem::IdExtra(id),
+      });
+    }
+    problems.extend(violations.into_iter().map(|v| Problem::Tree("data", v)));
+    if let Some(&last) = keys.iter().next_back() {
+      if self.id.next_id() <= last {
+        problems.push(Problem::IdNext(self.id.next_id()));
+      }
+    }
+
+    let violations = self.search.violations();
+    if violations.is_empty() {
+      let docs = self.search.docs().collect();
+      diff(
+        &mut problems,
+        &docs,
+        &readable,
+        |id, missing| match missing {
+          true => Problem::DocMissing(id),
+          false => Problem::DocExtra(id),
+        },
+      );
+    }
+    problems.extend(
+      violations
+        .into_iter()
+        .map(|(name, v)| Problem::Tree(name, v)),
+    );
+    Ok(problems)
+  }
+
+  fn repair(&mut self, problems: &[Problem]) -> Result<(), Error> {
+    if self.journal.pending().is_some() {
+      self.resume()?;
+    } else if problems.iter().any(Problem::index) {
+      self.rebuild(&mut |_| {})?;
+    }
+    // Recovering and rebuilding may fix the rest as well, so they are found
+    // again.
+    let problems = &self.check()?;
+
+    let txn = WriteTransaction::new(self.env.clone())?;
+    {
+      let blobs = self.blobs(&txn);
+      let mut access = txn.access();
+      for problem in problems {
+        match *problem {
+          Problem::MissingRef(ref hash, id, attachment) => {
+            blobs.add(&mut access, hash, id, attachment)?
+          }
+          Problem::StaleRef(ref hash, id, attachment) => {
+            blobs.del(&mut access, hash, id, attachment)?
+          }
+          _ => {}
+        }
+      }
+    }
+    txn.commit()?;
+
+    for problem in problems {
+      match problem {
+        Problem::OrphanFile(name) => fs::remove_file(self.content_dir().join(name))?,
+        Problem::OrphanRevisions(id) => {
+          fs::remove_dir_all(Article::revision_dir_of(&self.content_dir(), *id))?
+        }
+        _ => {}
+      }
+    }
+    self.id.flush()?;
+    self.search.flush()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::super::tests::{add, check, config, crash, load, upload};
+  use super::*;
+  use tempfile::tempdir;
+
+  #[test]
+  fn test_fsck() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let mut db = load(&dir)?;
+    let a = add(&mut db, "Literate Programming", b"%PDF-1.4 literate")?;
+    let b = add(&mut db, "Concrete Mathematics", b"%PDF-1.4 concrete")?;
+    db.replace_file(b.id(), upload(b"%PDF-1.4 concrete 2")?)?;
+    let b = db.article(b.id())?;
+    drop(db);
+    assert!(Store::fsck(&config(&dir), false)?.is_empty());
+    let mut db = load(&dir)?;
+
+    let (lost, moved) = (&a.content().attachments[0], &b.content().attachments[0]);
+    fs::remove_file(a.attachment_path(lost))?;
+    let orphan = format!("{}.pdf", Hash([7; 32]));
+    fs::write(db.content_dir().join(&orphan), b"%PDF-1.4 orphan")?;
+    fs::create_dir(Article::revision_dir_of(&db.content_dir(), 99))?;
+    fs::write(db.content_dir().join("notes.txt"), b"")?;
+    db.id.del(b.id())?;
+    db.search.del(a.id())?;
+    {
+      let txn = WriteTransaction::new(db.env.clone())?;
+      {
+        let blobs = db.blobs(&txn);
+        let mut access = txn.access();
+        blobs.add(&mut access, &Hash([7; 32]), 42, 0)?;
+        blobs.del(&mut access, &moved.hash, b.id(), moved.id)?;
+      }
+      txn.commit()?;
+    }
+    drop(db);
+
+    let mut problems = Store::fsck(&config(&dir), false)?;
+    let expected = [
+      Problem::MissingFile(a.id(), lost.id, lost.filename()),
+      Problem::MissingRef(moved.hash, b.id(), moved.id),
+      Problem::StaleRef(Hash([7; 32]), 42, 0),
+      Problem::OrphanFile(orphan),
+      Problem::OrphanRevisions(99),
+      Problem::Unknown("notes.txt".to_owned()),
+      Problem::IdMissing(b.id()),
+      Problem::DocMissing(a.id()),
+    ];
+    for problem in expected.iter() {
+      assert!(problems.contains(problem), "{} is not found", problem);
+    }
+    problems.retain(|p| !expected.contains(p));
+    assert_eq!(problems, vec![]);
+
+    Store::fsck(&config(&dir), true)?;
+    assert_eq!(
+      Store::fsck(&config(&dir), false)?,
+      vec![
+        Problem::MissingFile(a.id(), lost.id, lost.filename()),
+        Problem::Unknown("notes.txt".to_owned()),
+      ]
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn test_fsck_crash() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let mut db = load(&dir)?;
+    add(&mut db, "Literate Programming", b"%PDF-1.4 literate")?;
+    crash("insert:stored", || {
+      add(&mut db, "Concrete Mathematics", b"%PDF-1.4 concrete")
+    });
+    drop(db);
+
+    let problems = Store::fsck(&config(&dir), false)?;
+    assert!(problems.contains(&Problem::Pending));
+    assert!(problems.iter().any(|p| matches!(p, Problem::OrphanFile(_))));
+    Store::fsck(&config(&dir), true)?;
+    assert_eq!(Store::fsck(&config(&dir), false)?, vec![]);
+    assert_eq!(check(&load(&dir)?)?.len(), 1);
+    Ok(())
+  }
+}
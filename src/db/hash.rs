@@ -0,0 +1,83 @@
+use std::fmt;
+use std::io::{self, Read};
+
+use serde::{Serialize, Serializer};
+use sha2::{Digest, Sha256};
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Hash(pub [u8; 32]);
+
+impl Hash {
+  pub fn of<R: Read>(mut reader: R) -> io::Result<Self> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+      match reader.read(&mut buf)? {
+        0 => return Ok(Hash::from(hasher)),
+        n => hasher.input(&buf[..n]),
+      }
+    }
+  }
+
+  pub fn is_zero(&self) -> bool {
+    *self == Hash::default()
+  }
+}
+
+impl From<Sha256> for Hash {
+  fn from(hasher: Sha256) -> Self {
+    let mut hash = Hash::default();
+    hash.0.copy_from_slice(&hasher.result());
+    hash
+  }
+}
+
+impl fmt::Display for Hash {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for b in self.0.iter() {
+      write!(f, "{:02x}", b)?;
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Debug for Hash {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Hash({})", self)
+  }
+}
+
+impl Serialize for Hash {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sha256(data: &[u8]) -> String {
+    Hash::of(data).unwrap().to_string()
+  }
+
+  #[test]
+  fn test_sha256() {
+    assert_eq!(
+      sha256(b""),
+      "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+      sha256(b"abc"),
+      "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+      sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+      "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+    );
+    assert_eq!(
+      Hash::of(&vec![b'a'; 1_000_000][..]).unwrap().to_string(),
+      "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+    );
+  }
+}
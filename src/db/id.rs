@@ -1,34 +1,190 @@
-use crate::collection::RBTree;
+use crate::collection::{RBTree, Violation};
+use crate::config::IdAllocation;
 use failure::Error;
-use std::fs::OpenOptions;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
 pub struct IdIndex {
   tree: RBTree<u64>,
+  file: File,
+  next: u64,
+  /// No id below `gap` is free, so filling gaps starts from it.
+  gap: u64,
+  allocation: IdAllocation,
 }
 impl IdIndex {
   const FILE_PATH: &'static str = "data";
+  const NEXT_PATH: &'static str = "next";
 
-  pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-    let path = path.as_ref().join(Self::FILE_PATH);
-    let file = OpenOptions::new()
+  pub fn open<P: AsRef<Path>>(path: P, allocation: IdAllocation) -> Result<Self, Error> {
+    let path = path.as_ref();
+    let tree = RBTree::open(path.join(Self::FILE_PATH))?;
+    let mut file = OpenOptions::new()
       .create(true)
+      .truncate(false)
       .write(true)
       .read(true)
-      .open(path)?;
-    let tree = RBTree::create(file)?;
-    Ok(Self { tree })
+      .open(path.join(Self::NEXT_PATH))?;
+    let mut buf = [0; 8];
+    let next = match file.read_exact(&mut buf) {
+      Ok(()) => u64::from_le_bytes(buf),
+      Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => 0,
+      Err(e) => return Err(e.into()),
+    };
+    Ok(Self {
+      tree,
+      file,
+      next,
+      gap: 0,
+      allocation,
+    })
   }
 
-  pub fn new(&mut self) -> Result<u64, Error> {
-    unimplemented!();
+  fn set_next(&mut self, next: u64) -> Result<(), Error> {
+    self.file.seek(SeekFrom::Start(0))?;
+    self.file.write_all(&next.to_le_bytes())?;
+    self.file.sync_data()?;
+    self.next = next;
+    Ok(())
+  }
+
+  pub fn recover(&mut self, mut keys: Vec<u64>) -> Result<(), Error> {
+    keys.sort();
+    if self.tree.len() != keys.len() as u64
+      || self.tree.iter().zip(keys.iter()).any(|(a, &b)| a != b)
+    {
+      warn!(
+        "IdIndex The index has drifted from the database and is rebuilt from {} ids.",
+        keys.len()
+      );
+      return self.rebuild(keys);
+    }
+    self.bump(&keys)
+  }
+
+  pub fn rebuild(&mut self, mut keys: Vec<u64>) -> Result<(), Error> {
+    keys.sort();
+    self.tree.clear()?;
+    self.gap = 0;
+    for &key in keys.iter() {
+      self.tree.add(key)?;
+    }
+    self.bump(&keys)
+  }
+
+  fn bump(&mut self, keys: &[u64]) -> Result<(), Error> {
+    if let Some(&last) = keys.last() {
+      if self.next <= last {
+        self.set_next(last + 1)?;
+      }
+    }
+    Ok(())
+  }
+
+  pub fn flush(&self) -> Result<(), Error> {
+    self.tree.flush()
+  }
+
+  pub fn len(&self) -> u64 {
+    self.tree.len()
+  }
+
+  pub fn violations(&self) -> Vec<Violation> {
+    self.tree.violations()
+  }
+
+  pub fn next_id(&self) -> u64 {
+    self.next
+  }
+
+  pub fn contains(&self, id: u64) -> bool {
+    self.tree.contains(&id)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+    self.tree.iter()
+  }
+
+  pub fn iter_rev(&self) -> impl Iterator<Item = u64> + '_ {
+    self.tree.iter_rev()
+  }
+
+  /// The next `n` ids to allocate, without reserving them. Pass them to
+  /// [`IdIndex::take`] once they are journaled.
+  pub fn peek(&self, n: usize) -> Result<Vec<u64>, Error> {
+    let mut floor = match self.allocation {
+      IdAllocation::Monotonic => self.next,
+      IdAllocation::FillGaps => self.gap,
+    };
+    let mut ids = Vec::with_capacity(n);
+    for _ in 0..n {
+      let id = self.tree.first_free(floor)?;
+      ids.push(id);
+      floor = id + 1;
+    }
+    Ok(ids)
+  }
+
+  /// Reserves the ids returned by the last [`IdIndex::peek`].
+  pub fn take(&mut self, ids: &[u64]) -> Result<(), Error> {
+    for &id in ids.iter() {
+      self.add(id)?;
+    }
+    if let (Some(&last), IdAllocation::FillGaps) = (ids.last(), self.allocation) {
+      self.gap = last + 1;
+    }
+    Ok(())
   }
 
   pub fn add(&mut self, id: u64) -> Result<(), Error> {
-    unimplemented!();
+    self.tree.add(id)?;
+    if id >= self.next {
+      self.set_next(id + 1)?;
+    }
+    Ok(())
   }
 
   pub fn del(&mut self, id: u64) -> Result<(), Error> {
-    unimplemented!();
+    self.tree.del(id)?;
+    self.gap = self.gap.min(id);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempfile::tempdir;
+
+  fn allocate(index: &mut IdIndex) -> Result<u64, Error> {
+    let ids = index.peek(1)?;
+    index.take(&ids)?;
+    Ok(ids[0])
+  }
+
+  #[test]
+  fn test_allocate() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let mut index = IdIndex::open(dir.path(), IdAllocation::FillGaps)?;
+    for _ in 0..5 {
+      allocate(&mut index)?;
+    }
+    index.del(1)?;
+    index.del(3)?;
+    assert_eq!(index.peek(3)?, vec![1, 3, 5]);
+    assert_eq!(allocate(&mut index)?, 1);
+    assert_eq!(allocate(&mut index)?, 3);
+    assert_eq!(allocate(&mut index)?, 5);
+
+    let dir = tempdir()?;
+    let mut index = IdIndex::open(dir.path(), IdAllocation::Monotonic)?;
+    for _ in 0..5 {
+      allocate(&mut index)?;
+    }
+    index.del(4)?;
+    index.del(1)?;
+    assert_eq!(allocate(&mut index)?, 5);
+    Ok(())
   }
 }
@@ -0,0 +1,205 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use failure::Error;
+use sha2::{Digest, Sha256};
+
+use super::article::MediaType;
+use super::hash::Hash;
+
+const MAGIC: &[u8; 4] = b"WEIJ";
+const CHECKSUM_LEN: usize = 8;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Intent {
+  pub ids: Vec<u64>,
+  pub blobs: Vec<(Hash, MediaType)>,
+}
+
+impl Intent {
+  pub fn ids(ids: Vec<u64>) -> Self {
+    Self {
+      ids,
+      blobs: Vec::new(),
+    }
+  }
+
+  pub fn blob(mut self, hash: Hash, media_type: MediaType) -> Self {
+    if !self.blobs.contains(&(hash, media_type)) {
+      self.blobs.push((hash, media_type));
+    }
+    self
+  }
+
+  fn encode(&self) -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&(self.ids.len() as u32).to_le_bytes());
+    for id in self.ids.iter() {
+      payload.extend_from_slice(&id.to_le_bytes());
+    }
+    payload.extend_from_slice(&(self.blobs.len() as u32).to_le_bytes());
+    for (hash, media_type) in self.blobs.iter() {
+      payload.extend_from_slice(&hash.0);
+      payload.push(*media_type as u8);
+    }
+    let mut buf = MAGIC.to_vec();
+    buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&payload);
+    buf.extend_from_slice(&checksum(&payload));
+    buf
+  }
+
+  fn decode(bytes: &[u8]) -> Option<Self> {
+    if bytes.len() < MAGIC.len() + 4 || !bytes.starts_with(MAGIC) {
+      return None;
+    }
+    let mut len = [0; 4];
+    len.copy_from_slice(&bytes[4..8]);
+    let len = u32::from_le_bytes(len) as usize;
+    let payload = bytes.get(8..8 + len)?;
+    if bytes.get(8 + len..8 + len + CHECKSUM_LEN)? != &checksum(payload)[..] {
+      return None;
+    }
+    let mut rest = payload;
+    let mut take = |n: usize| -> Option<&[u8]> {
+      if rest.len() < n {
+        return None;
+      }
+      let (head, tail) = rest.split_at(n);
+      rest = tail;
+      Some(head)
+    };
+    let mut u32_buf = [0; 4];
+    u32_buf.copy_from_slice(take(4)?);
+    let mut intent = Intent::default();
+    for _ in 0..u32::from_le_bytes(u32_buf) {
+      let mut id = [0; 8];
+      id.copy_from_slice(take(8)?);
+      intent.ids.push(u64::from_le_bytes(id));
+    }
+    u32_buf.copy_from_slice(take(4)?);
+    for _ in 0..u32::from_le_bytes(u32_buf) {
+      let mut hash = Hash::default();
+      hash.0.copy_from_slice(take(32)?);
+      let media_type = MediaType::from_u8(take(1)?[0])?;
+      intent.blobs.push((hash, media_type));
+    }
+    Some(intent)
+  }
+}
+
+fn checksum(payload: &[u8]) -> [u8; CHECKSUM_LEN] {
+  let mut sum = [0; CHECKSUM_LEN];
+  sum.copy_from_slice(&Sha256::digest(payload)[..CHECKSUM_LEN]);
+  sum
+}
+
+pub struct Journal {
+  file: File,
+  pending: Option<Intent>,
+}
+
+impl Journal {
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+    let mut file = OpenOptions::new()
+      .create(true)
+      .truncate(false)
+      .read(true)
+      .write(true)
+      .open(path)?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    let pending = if buf.is_empty() {
+      None
+    } else {
+      match Intent::decode(&buf) {
+        Some(intent) => Some(intent),
+        None => {
+          warn!("Journal A torn entry of {} bytes is discarded.", buf.len());
+          None
+        }
+      }
+    };
+    Ok(Self { file, pending })
+  }
+
+  pub fn pending(&self) -> Option<&Intent> {
+    self.pending.as_ref()
+  }
+
+  pub fn begin(&mut self, intent: &Intent) -> Result<(), Error> {
+    self.file.seek(SeekFrom::Start(0))?;
+    self.file.set_len(0)?;
+    self.file.write_all(&intent.encode())?;
+    self.file.sync_data()?;
+    self.pending = Some(intent.clone());
+    Ok(())
+  }
+
+  pub fn end(&mut self) -> Result<(), Error> {
+    self.file.set_len(0)?;
+    self.file.sync_data()?;
+    self.pending = None;
+    Ok(())
+  }
+}
+
+/// The panic payload of a crash injected at a checkpoint.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct Crash(pub &'static str);
+
+#[cfg(test)]
+thread_local! {
+  pub static CRASH_AT: std::cell::Cell<Option<&'static str>> = const { std::cell::Cell::new(None) };
+}
+
+/// Marks a step of a journaled write. Tests crash there by unwinding past
+/// the journal, which leaves the write pending as a real crash would.
+#[cfg(test)]
+pub fn checkpoint(step: &'static str) {
+  if CRASH_AT.with(|c| c.get()) == Some(step) {
+    std::panic::resume_unwind(Box::new(Crash(step)));
+  }
+}
+
+#[cfg(not(test))]
+#[inline]
+pub fn checkpoint(_: &'static str) {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempfile::tempdir;
+
+  #[test]
+  fn test_journal() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let path = dir.path().join("journal");
+    let intent = Intent::ids(vec![3, 1 << 40])
+      .blob(Hash([9; 32]), MediaType::Pdf)
+      .blob(Hash([9; 32]), MediaType::Pdf);
+    assert_eq!(intent.blobs.len(), 1);
+
+    let mut journal = Journal::open(&path)?;
+    assert_eq!(journal.pending(), None);
+    journal.begin(&intent)?;
+    drop(journal);
+    let mut journal = Journal::open(&path)?;
+    assert_eq!(journal.pending(), Some(&intent));
+    journal.end()?;
+    assert_eq!(journal.pending(), None);
+    drop(journal);
+    assert_eq!(Journal::open(&path)?.pending(), None);
+
+    let bytes = intent.encode();
+    for len in 1..bytes.len() {
+      assert_eq!(Intent::decode(&bytes[..len]), None);
+    }
+    let mut corrupted = bytes.clone();
+    corrupted[10] ^= 1;
+    assert_eq!(Intent::decode(&corrupted), None);
+    Ok(())
+  }
+}
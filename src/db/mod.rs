@@ -1,72 +1,747 @@
 mod article;
+mod blob;
+mod collection;
+mod dedup;
+mod fsck;
+mod hash;
 mod id;
+mod journal;
 mod msgs;
 mod search;
 
 use std::borrow::Borrow;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind};
+use std::mem;
+use std::os::unix::io::AsRawFd;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use crate::lmdb::{open, Database, DatabaseOptions, EnvBuilder, Environment};
-use actix::{Actor, Addr, Arbiter, Context};
+use crate::config::Config;
+use crate::lmdb::{
+  db, open, put, ConstTransaction, Database, DatabaseOptions, EnvBuilder, Environment,
+  LmdbResultExt, ReadTransaction, WriteTransaction,
+};
+use actix::{
+  Actor, Addr, Arbiter, Context, Handler, Message, ResponseFuture, SyncArbiter, SyncContext,
+};
 use failure::Error;
+use futures::Future;
 
 pub use self::article::*;
+use self::blob::Blobs;
+use self::collection::Collections;
+pub use self::collection::{CollectionError, Kind};
+pub use self::dedup::MergeError;
 use self::id::*;
+use self::journal::{Intent, Journal};
 pub use self::msgs::*;
+pub use self::search::Query;
 use self::search::*;
 
-pub struct Db {
+/// The article store: owns the LMDB environment, the on-disk indices and the
+/// attached files, and performs every operation synchronously.
+pub struct Store {
   path: PathBuf,
   env: Arc<Environment>,
   db: Database<'static>,
+  text: Database<'static>,
+  collections: Database<'static>,
+  members: Database<'static>,
+  memberships: Database<'static>,
+  files: Database<'static>,
   id: IdIndex,
   search: SearchIndex,
+  journal: Journal,
+  _lock: File,
 }
 
-impl Db {
+impl Store {
   const DATA_DIR: &'static str = "data";
   const INDEX_DIR: &'static str = "index";
   const CONTENT_DIR: &'static str = "content";
+  const TEXT_DB: &'static str = "text";
+  const COLLECTIONS_DB: &'static str = "collections";
+  const MEMBERS_DB: &'static str = "members";
+  const MEMBERSHIPS_DB: &'static str = "memberships";
+  const FILES_DB: &'static str = "files";
+  const JOURNAL_PATH: &'static str = "journal";
+  const LOCK_PATH: &'static str = "lock";
+  const MAX_DBS: u32 = 8;
+
+  /// Opens the store at `config.db_path` and finishes or rolls back an
+  /// interrupted write.
+  pub fn open(config: &Config) -> Result<Self, Error> {
+    let mut store = Self::load(config)?;
+    store.prepare()?;
+    Ok(store)
+  }
 
-  pub fn open<P: AsRef<Path>>(path: P) -> Result<Addr<Self>, Error> {
-    let path = path.as_ref().to_owned();
-    std::fs::create_dir_all(path.join(Self::CONTENT_DIR))?;
+  fn load(config: &Config) -> Result<Self, Error> {
+    let path = config.db_path.clone();
+    fs::create_dir_all(path.join(Self::CONTENT_DIR))?;
+    let lock = Self::lock(&path)?;
 
     let data_dir = path.join(Self::DATA_DIR);
-    std::fs::create_dir_all(&data_dir)?;
+    fs::create_dir_all(&data_dir)?;
     let env = Arc::new(unsafe {
-      EnvBuilder::new()?.open(
+      let mut builder = EnvBuilder::new()?;
+      builder.set_mapsize(config.map_size)?;
+      builder.set_maxdbs(Self::MAX_DBS)?;
+      builder.open(
         data_dir.to_string_lossy().borrow(),
         open::Flags::empty(),
         0o600,
       )?
     });
     let db = Database::open(env.clone(), None, &DatabaseOptions::defaults())?;
+    let named =
+      |name: &str| Database::open(env.clone(), Some(name), &DatabaseOptions::new(db::CREATE));
+    let text = named(Self::TEXT_DB)?;
+    let collections = named(Self::COLLECTIONS_DB)?;
+    let members = named(Self::MEMBERS_DB)?;
+    let memberships = named(Self::MEMBERSHIPS_DB)?;
+    let files = named(Self::FILES_DB)?;
+
+    Self::migrate(&env, &db, &files, &path)?;
 
     let index_dir = path.join(Self::INDEX_DIR);
-    std::fs::create_dir_all(&index_dir)?;
-    let id = IdIndex::open(&index_dir)?;
+    fs::create_dir_all(&index_dir)?;
+    let id = IdIndex::open(&index_dir, config.id_allocation)?;
     let search = SearchIndex::open(&index_dir)?;
+    let journal = Journal::open(path.join(Self::JOURNAL_PATH))?;
 
-    let arb = Arbiter::new();
-    Ok(Self::start_in_arbiter(&arb, |_: &mut Context<Self>| Self {
+    Ok(Self {
       path,
       env,
       db,
+      text,
+      collections,
+      members,
+      memberships,
+      files,
       id,
       search,
+      journal,
+      _lock: lock,
+    })
+  }
+
+  /// Takes an exclusive lock on the store, which is released when the
+  /// returned file is closed.
+  fn lock(path: &Path) -> Result<File, Error> {
+    let file = OpenOptions::new()
+      .create(true)
+      .truncate(false)
+      .write(true)
+      .open(path.join(Self::LOCK_PATH))?;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+      let e = io::Error::last_os_error();
+      if e.kind() == ErrorKind::WouldBlock {
+        bail!(
+          "{} is in use by another process. Stop it before opening the database.",
+          path.display()
+        );
+      }
+      return Err(e.into());
+    }
+    Ok(file)
+  }
+
+  fn prepare(&mut self) -> Result<(), Error> {
+    if self.journal.pending().is_some() {
+      self.resume()
+    } else {
+      let keys = Self::keys(&self.env, &self.db)?;
+      self.id.recover(keys)
+    }
+  }
+
+  fn recover(&mut self, intent: &Intent) -> Result<(), Error> {
+    warn!(
+      "Store An interrupted write is found ({:?}) and the indices are rebuilt.",
+      intent
+    );
+    self.rebuild(&mut |_| {})?;
+    self.settle(intent, false)?;
+    self.journal.end()
+  }
+
+  fn rebuild(&mut self, progress: &mut dyn FnMut(Progress)) -> Result<(), Error> {
+    let keys = Self::keys(&self.env, &self.db)?;
+    let total = keys.len() as u64;
+    self.id.rebuild(keys.clone())?;
+    progress(Progress::new("ids", total, total));
+    self.search.clear()?;
+    let txn = ReadTransaction::new(self.env.clone())?;
+    let access = txn.access();
+    for (done, key) in keys.into_iter().enumerate() {
+      if (done as u64).is_multiple_of(Progress::STEP) {
+        progress(Progress::new("search", done as u64, total));
+      }
+      let content = match ArticleContent::decode(access.get::<u64, [u8]>(&self.db, &key)?) {
+        Ok(content) => content,
+        Err(e) => {
+          error!("Store The record of id={} is not indexed: {}", key, e);
+          continue;
+        }
+      };
+      let text = access.get::<u64, str>(&self.text, &key).to_opt()?;
+      self.search.add(key, &content, text)?;
+    }
+    progress(Progress::new("search", total, total));
+    Ok(())
+  }
+
+  fn resume(&mut self) -> Result<(), Error> {
+    match self.journal.pending().cloned() {
+      Some(intent) => self.recover(&intent),
+      None => Ok(()),
+    }
+  }
+
+  fn journaled<T, F>(&mut self, intent: Intent, f: F) -> Result<T, Error>
+  where
+    F: FnOnce(&mut Self) -> Result<T, Error>,
+  {
+    self.resume()?;
+    self.journal.begin(&intent)?;
+    let result = f(self);
+    let intent = self.journal.pending().cloned().unwrap_or(intent);
+    if let Err(e) = self.settle(&intent, result.is_err()) {
+      error!("Store Failed to settle {:?}: {}", intent, e);
+      return result.and(Err(e));
+    }
+    self.journal.end()?;
+    result
+  }
+
+  /// Allocates `n` ids within a journaled write. They join its intent before
+  /// they are reserved, so an interrupted write releases them.
+  fn allocate(&mut self, n: usize) -> Result<Vec<u64>, Error> {
+    let mut intent = match self.journal.pending() {
+      Some(intent) => intent.clone(),
+      None => bail!("Ids are only allocated within a journaled write."),
+    };
+    let ids = self.id.peek(n)?;
+    intent.ids.extend_from_slice(&ids);
+    self.journal.begin(&intent)?;
+    self.id.take(&ids)?;
+    Ok(ids)
+  }
+
+  fn settle(&mut self, intent: &Intent, resync: bool) -> Result<(), Error> {
+    let txn = ReadTransaction::new(self.env.clone())?;
+    let access = txn.access();
+    for &key in intent.ids.iter() {
+      let content = match access.get::<u64, [u8]>(&self.db, &key).to_opt()? {
+        Some(bytes) => Some(ArticleContent::decode(bytes)?),
+        None => None,
+      };
+      if resync {
+        match content {
+          Some(ref content) => {
+            if !self.id.contains(key) {
+              self.id.add(key)?;
+            }
+            let text = access.get::<u64, str>(&self.text, &key).to_opt()?;
+            self.search.add(key, content, text)?;
+          }
+          None => {
+            self.id.del(key)?;
+            self.search.del(key)?;
+          }
+        }
+      }
+      if content.is_none() {
+        let dir = Article::revision_dir_of(&self.content_dir(), key);
+        match fs::remove_dir_all(&dir) {
+          Err(ref e) if e.kind() != ErrorKind::NotFound => {
+            warn!("Store Failed to remove the revisions of id={}: {}", key, e)
+          }
+          _ => {}
+        }
+      }
+    }
+    let blobs = self.blobs(&txn);
+    for (hash, media_type) in intent.blobs.iter() {
+      if !blobs.refs(&access, hash)?.is_empty() {
+        continue;
+      }
+      let blob = self
+        .content_dir()
+        .join(format!("{}.{}", hash, media_type.extension()));
+      match fs::remove_file(&blob) {
+        Err(ref e) if e.kind() != ErrorKind::NotFound => warn!(
+          "Store Failed to remove the unreferenced file {}: {}",
+          blob.display(),
+          e
+        ),
+        _ => {}
+      }
+    }
+    self.id.flush()?;
+    self.search.flush()
+  }
+
+  fn blobs<'a, 'env>(&'a self, txn: &'a ConstTransaction<'env>) -> Blobs<'a, 'env> {
+    Blobs {
+      txn,
+      files: &self.files,
+    }
+  }
+
+  fn collections<'a, 'env>(&'a self, txn: &'a ConstTransaction<'env>) -> Collections<'a, 'env> {
+    Collections {
+      txn,
+      collections: &self.collections,
+      members: &self.members,
+      memberships: &self.memberships,
+    }
+  }
+
+  fn decode_key(key: &[u8]) -> Option<u64> {
+    if key.len() != mem::size_of::<u64>() {
+      return None;
+    }
+    let mut buf = [0; 8];
+    buf.copy_from_slice(key);
+    Some(u64::from_ne_bytes(buf))
+  }
+
+  fn keys(env: &Arc<Environment>, db: &Database) -> Result<Vec<u64>, Error> {
+    let txn = ReadTransaction::new(env.clone())?;
+    let access = txn.access();
+    let mut cursor = txn.cursor(db)?;
+    let mut keys = Vec::new();
+    let mut item = cursor.first::<[u8], [u8]>(&access).to_opt()?;
+    while let Some((key, _)) = item {
+      if let Some(key) = Self::decode_key(key) {
+        keys.push(key);
+      }
+      item = cursor.next::<[u8], [u8]>(&access).to_opt()?;
+    }
+    Ok(keys)
+  }
+
+  fn migrate(
+    env: &Arc<Environment>,
+    db: &Database,
+    files: &Database<'static>,
+    path: &Path,
+  ) -> Result<(), Error> {
+    let dir = path.join(Self::CONTENT_DIR);
+    let txn = WriteTransaction::new(env.clone())?;
+    let outdated = {
+      let access = txn.access();
+      let mut cursor = txn.cursor(db)?;
+      let mut outdated = Vec::new();
+      let mut item = cursor.first::<[u8], [u8]>(&access).to_opt()?;
+      while let Some((key, val)) = item {
+        if let Some(key) = Self::decode_key(key) {
+          if !ArticleContent::is_current(val) {
+            outdated.push((key, ArticleContent::decode(val)?));
+          }
+        }
+        item = cursor.next::<[u8], [u8]>(&access).to_opt()?;
+      }
+      outdated
+    };
+    if outdated.is_empty() {
+      return Ok(());
+    }
+    let mut moved = Vec::new();
+    {
+      let blobs = Blobs { txn: &txn, files };
+      let mut access = txn.access();
+      for (key, mut content) in outdated.iter().cloned() {
+        if content.attachments.is_empty() {
+          content.attachments = Self::legacy_attachments(&dir, key);
+        }
+        let mut attachments = Vec::with_capacity(content.attachments.len());
+        for mut attachment in content.attachments {
+          let old = dir.join(attachment.default_name(key));
+          if !attachment.hash.is_zero() {
+            attachments.push(attachment);
+            continue;
+          }
+          let file = match File::open(&old) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => {
+              warn!(
+                "Store The attachment {} of id={} is missing and dropped.",
+                attachment.id, key
+              );
+              continue;
+            }
+            Err(e) => return Err(e.into()),
+          };
+          attachment.size = file.metadata()?.len();
+          attachment.hash = hash::Hash::of(file)?;
+          let blob = dir.join(attachment.filename());
+          if !blob.exists() {
+            if let Err(e) = fs::hard_link(&old, &blob) {
+              warn!(
+                "Store Failed to link the attachment {} of id={} ({}), copying instead.",
+                attachment.id, key, e
+              );
+              fs::copy(&old, &blob)?;
+            }
+          }
+          blobs.add(&mut access, &attachment.hash, key, attachment.id)?;
+          moved.push(old);
+          attachments.push(attachment);
+        }
+        content.attachments = attachments;
+        access.put(db, &key, &content.encode()[..], put::Flags::empty())?;
+      }
+    }
+    txn.commit()?;
+    for old in moved {
+      if let Err(e) = fs::remove_file(&old) {
+        warn!("Store Failed to remove {}: {}", old.display(), e);
+      }
+    }
+    info!(
+      "Store {} articles are migrated to the current record format.",
+      outdated.len()
+    );
+    Ok(())
+  }
+
+  fn article(&self, key: u64) -> Result<Article, Error> {
+    let txn = ReadTransaction::new(self.env.clone())?;
+    let content = ArticleContent::decode(txn.access().get::<u64, [u8]>(&self.db, &key)?)?;
+    Ok(Article::new(self.content_dir(), key, content))
+  }
+
+  fn legacy_attachments(dir: &Path, key: u64) -> Vec<Attachment> {
+    let attachment = Attachment {
+      id: 0,
+      media_type: MediaType::Pdf,
+      hash: Default::default(),
+      name: None,
+      size: 0,
+    };
+    if dir.join(attachment.default_name(key)).exists() {
+      vec![attachment]
+    } else {
+      Vec::new()
+    }
+  }
+
+  fn content_dir(&self) -> PathBuf {
+    self.path.join(Self::CONTENT_DIR)
+  }
+
+  fn store(&self, upload: Upload) -> Result<(), Error> {
+    let dir = self.content_dir();
+    let path = dir.join(upload.attachment(0).filename());
+    if path.exists() {
+      return Ok(());
+    }
+    upload.persist(&path)?;
+    File::open(dir)?.sync_all()?;
+    Ok(())
+  }
+}
+
+/// An actix actor serving a [`Store`] in its own arbiter. Operations are sent
+/// to it as messages: writes are performed one at a time by the actor itself,
+/// while reads are forwarded to a pool of `config.workers` [`Reader`]s that
+/// run them concurrently.
+pub struct Db {
+  store: Arc<RwLock<Store>>,
+  readers: Addr<Reader>,
+}
+
+impl Db {
+  /// Opens the store at `config.db_path` and starts the actor and its
+  /// readers.
+  pub fn open(config: &Config) -> Result<Addr<Self>, Error> {
+    let store = Arc::new(RwLock::new(Store::open(config)?));
+    let shared = store.clone();
+    let readers = SyncArbiter::start(config.workers.max(1), move || Reader(shared.clone()));
+    let arb = Arbiter::new();
+    Ok(Self::start_in_arbiter(&arb, |_: &mut Context<Self>| Db {
+      store,
+      readers,
     }))
   }
 
-  fn content_path(&self, key: u64) -> PathBuf {
+  fn store(&self) -> Result<RwLockWriteGuard<'_, Store>, Error> {
     self
-      .path
-      .join(Self::CONTENT_DIR)
-      .join(format!("{}.pdf", key))
+      .store
+      .write()
+      .map_err(|_| format_err!("The store is poisoned by a panicked operation."))
+  }
+
+  fn read<M, T>(&self, msg: M) -> ResponseFuture<T, Error>
+  where
+    M: Message<Result = Result<T, Error>> + Send + 'static,
+    T: Send + 'static,
+    Reader: Handler<M>,
+  {
+    Box::new(self.readers.send(msg).from_err().and_then(|result| result))
   }
 }
 
 impl Actor for Db {
   type Context = Context<Self>;
 }
+
+/// A thread of the [`Db`]'s pool that performs read-only operations under a
+/// shared lock of the store.
+pub struct Reader(Arc<RwLock<Store>>);
+
+impl Reader {
+  fn store(&self) -> Result<RwLockReadGuard<'_, Store>, Error> {
+    self
+      .0
+      .read()
+      .map_err(|_| format_err!("The store is poisoned by a panicked operation."))
+  }
+}
+
+impl Actor for Reader {
+  type Context = SyncContext<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::journal::{Crash, CRASH_AT};
+  use super::*;
+  use std::collections::BTreeSet;
+  use std::io::Write;
+  use std::panic;
+  use tempfile::{tempdir, NamedTempFile, TempDir};
+
+  pub(super) fn config(dir: &TempDir) -> Config {
+    Config {
+      db_path: dir.path().to_owned(),
+      map_size: 1 << 24,
+      ..Default::default()
+    }
+  }
+
+  pub(super) fn load(dir: &TempDir) -> Result<Store, Error> {
+    Store::open(&config(dir))
+  }
+
+  pub(super) fn upload(data: &[u8]) -> Result<Upload, Error> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(data)?;
+    Upload::new(file, Some("paper.pdf".to_owned()))
+  }
+
+  pub(super) fn add(db: &mut Store, title: &str, data: &[u8]) -> Result<Article, Error> {
+    let content = ArticleContent::new(title, ["Knuth"])?;
+    db.add(content, Some(upload(data)?))
+  }
+
+  pub(super) fn crash<T>(step: &'static str, f: impl FnOnce() -> Result<T, Error>) {
+    CRASH_AT.with(|c| c.set(Some(step)));
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(f));
+    CRASH_AT.with(|c| c.set(None));
+    match result {
+      Err(payload) => assert_eq!(payload.downcast_ref::<Crash>().map(|c| c.0), Some(step)),
+      Ok(Err(e)) => panic!("'{}' failed before crashing: {}", step, e),
+      Ok(Ok(_)) => panic!("No crash happened at '{}'.", step),
+    }
+  }
+
+  pub(super) fn check(db: &Store) -> Result<BTreeSet<u64>, Error> {
+    let keys: BTreeSet<u64> = Store::keys(&db.env, &db.db)?.into_iter().collect();
+    assert!(db.journal.pending().is_none());
+    assert_eq!(db.id.iter().collect::<BTreeSet<_>>(), keys);
+    assert_eq!(db.search.docs().collect::<BTreeSet<_>>(), keys);
+    let mut blobs = BTreeSet::new();
+    for &key in keys.iter() {
+      for attachment in db.article(key)?.content().attachments.iter() {
+        blobs.insert(attachment.filename());
+      }
+    }
+    let (mut files, mut revisions) = (BTreeSet::new(), BTreeSet::new());
+    for entry in fs::read_dir(db.content_dir())? {
+      let entry = entry?;
+      let name = entry.file_name().to_string_lossy().into_owned();
+      if entry.file_type()?.is_dir() {
+        revisions.insert(name.trim_end_matches(".revisions").parse::<u64>()?);
+      } else {
+        files.insert(name);
+      }
+    }
+    assert_eq!(files, blobs);
+    assert!(revisions.is_subset(&keys));
+    Ok(keys)
+  }
+
+  #[test]
+  fn test_lock() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let db = load(&dir)?;
+    assert!(load(&dir).is_err());
+    drop(db);
+    load(&dir)?;
+    Ok(())
+  }
+
+  #[test]
+  fn test_add_crash() -> Result<(), Error> {
+    for &(step, committed) in [
+      ("insert:stored", false),
+      ("insert:indexed", false),
+      ("insert:committed", true),
+    ]
+    .iter()
+    {
+      let dir = tempdir()?;
+      let mut db = load(&dir)?;
+      add(&mut db, "Literate Programming", b"%PDF-1.4 literate")?;
+      crash(step, || {
+        add(&mut db, "Concrete Mathematics", b"%PDF-1.4 concrete")
+      });
+      drop(db);
+
+      let mut db = load(&dir)?;
+      assert_eq!(check(&db)?.len(), if committed { 2 } else { 1 });
+      let added = add(&mut db, "Concrete Mathematics", b"%PDF-1.4 concrete");
+      assert_eq!(added.is_ok(), !committed);
+      check(&db)?;
+    }
+
+    let dir = tempdir()?;
+    let mut db = load(&dir)?;
+    crash("insert:indexed", || {
+      add(&mut db, "Literate Programming", b"%PDF-1.4 literate")
+    });
+    add(&mut db, "Concrete Mathematics", b"%PDF-1.4 concrete")?;
+    assert_eq!(check(&db)?.len(), 1);
+    Ok(())
+  }
+
+  #[test]
+  fn test_remove_crash() -> Result<(), Error> {
+    for &(step, committed) in [("remove:unindexed", false), ("remove:committed", true)].iter() {
+      let dir = tempdir()?;
+      let mut db = load(&dir)?;
+      let id = add(&mut db, "Literate Programming", b"%PDF-1.4 first")?.id();
+      db.replace_file(id, upload(b"%PDF-1.4 second")?)?;
+      assert!(db.article(id)?.revision_dir().exists());
+      crash(step, || db.remove(id));
+      drop(db);
+
+      let db = load(&dir)?;
+      let keys = check(&db)?;
+      assert_eq!(keys.contains(&id), !committed);
+      assert_eq!(
+        Article::revision_dir_of(&db.content_dir(), id).exists(),
+        !committed
+      );
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn test_replace_crash() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let mut db = load(&dir)?;
+    let article = add(&mut db, "Literate Programming", b"%PDF-1.4 first")?;
+    crash("replace:linked", || {
+      db.replace_file(article.id(), upload(b"%PDF-1.4 second")?)
+    });
+    drop(db);
+
+    let db = load(&dir)?;
+    check(&db)?;
+    assert_eq!(db.article(article.id())?.content(), article.content());
+    Ok(())
+  }
+
+  #[test]
+  fn test_update_crash() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let mut db = load(&dir)?;
+    let article = add(&mut db, "Literate Programming", b"%PDF-1.4 literate")?;
+    let patch = ArticlePatch {
+      title: Some("Concrete Mathematics".to_owned()),
+      ..Default::default()
+    };
+    crash("update:indexed", || db.update(article.id(), patch));
+    drop(db);
+
+    let db = load(&dir)?;
+    check(&db)?;
+    assert_eq!(db.article(article.id())?.content(), article.content());
+    assert!(db.search(&Query::parse("concrete")?)?.is_empty());
+    assert_eq!(db.search(&Query::parse("literate")?)?.len(), 1);
+    Ok(())
+  }
+
+  #[test]
+  fn test_text_crash() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let mut db = load(&dir)?;
+    let id = add(&mut db, "Literate Programming", b"%PDF-1.4 literate")?.id();
+    crash("text:indexed", || db.set_text(id, "obsolete".to_owned()));
+    drop(db);
+
+    let db = load(&dir)?;
+    check(&db)?;
+    assert!(db.search(&Query::parse("obsolete")?)?.is_empty());
+    Ok(())
+  }
+
+  #[test]
+  fn test_merge_crash() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let mut db = load(&dir)?;
+    let a = add(&mut db, "Literate Programming", b"%PDF-1.4 first")?;
+    let b = add(&mut db, "Literate programming", b"%PDF-1.4 second")?;
+    crash("merge:indexed", || db.merge(a.id(), vec![b.id()]));
+    drop(db);
+
+    let mut db = load(&dir)?;
+    assert_eq!(check(&db)?.len(), 2);
+    assert_eq!(db.article(a.id())?.content(), a.content());
+    assert_eq!(db.article(b.id())?.content(), b.content());
+    assert_eq!(db.search(&Query::parse("literate")?)?.len(), 2);
+    db.merge(a.id(), vec![b.id()])?;
+    assert_eq!(check(&db)?.len(), 1);
+    Ok(())
+  }
+
+  #[test]
+  fn test_store_failure() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let mut db = load(&dir)?;
+    let lost = |data: &[u8]| -> Result<Upload, Error> {
+      let upload = upload(data)?;
+      fs::remove_file(upload.file().path())?;
+      Ok(upload)
+    };
+    let content = || ArticleContent::new("Literate Programming", ["Knuth"]);
+    assert!(db.add(content()?, Some(lost(b"%PDF-1.4 first")?)).is_err());
+    let article = db.add(content()?, None)?;
+    assert!(db.attach(article.id(), lost(b"%PDF-1.4 second")?).is_err());
+    assert!(db
+      .replace_file(article.id(), lost(b"%PDF-1.4 third")?)
+      .is_err());
+    assert!(db.article(article.id())?.content().attachments.is_empty());
+    assert_eq!(check(&db)?.len(), 1);
+    Ok(())
+  }
+
+  #[test]
+  fn test_failure_is_settled() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let mut db = load(&dir)?;
+    let a = add(&mut db, "Literate Programming", b"%PDF-1.4 first")?.id();
+    let b = add(&mut db, "Literate programming", b"%PDF-1.4 second")?.id();
+    assert!(db.merge(a, vec![b, b + 1]).is_err());
+    assert_eq!(check(&db)?.len(), 2);
+    db.merge(a, vec![b])?;
+    assert_eq!(check(&db)?.len(), 1);
+    Ok(())
+  }
+}
@@ -1,44 +1,87 @@
 use actix::{Handler, Message};
 use failure::Error;
-use lmdb::{put, WriteTransaction};
-use std::sync::Arc;
+use lmdb::{put, ReadTransaction, WriteTransaction};
 
-use super::super::article::{Article, ArticleContent};
-use super::super::Db;
+use super::super::article::{Article, ArticleContent, AttachmentError};
+use super::super::journal::{checkpoint, Intent};
+use super::super::{Db, Store};
+use super::Upload;
 
+/// Adds an article, optionally with a file as its primary attachment.
 pub struct Add {
-  title: Arc<str>,
-  authors: Arc<[String]>,
+  content: ArticleContent,
+  upload: Option<Upload>,
 }
 
 impl Add {
-  pub fn new<S: AsRef<str>, A: AsRef<[String]>>(title: S, authors: A) -> Self {
-    Self {
-      title: Arc::from(title.as_ref()),
-      authors: Arc::from(authors.as_ref()),
-    }
+  pub fn new(content: ArticleContent, upload: Option<Upload>) -> Self {
+    Self { content, upload }
   }
 }
 impl Message for Add {
   type Result = Result<Article, Error>;
 }
 
+impl Store {
+  pub(super) fn insert(
+    &mut self,
+    content: ArticleContent,
+    upload: Option<Upload>,
+  ) -> Result<Article, Error> {
+    let intent = content
+      .attachments
+      .iter()
+      .fold(Intent::default(), |intent, a| {
+        intent.blob(a.hash, a.media_type)
+      });
+    self.journaled(intent, |db| {
+      let key = db.allocate(1)?[0];
+      if let Some(upload) = upload {
+        db.store(upload)?;
+      }
+      checkpoint("insert:stored");
+      let txn = WriteTransaction::new(db.env.clone())?;
+      {
+        let blobs = db.blobs(&txn);
+        let mut access = txn.access();
+        if let Err(e) = access.put(&db.db, &key, &content.encode()[..], put::NOOVERWRITE) {
+          return Err(format_err!("{:?}", e));
+        }
+        for attachment in content.attachments.iter() {
+          blobs.add(&mut access, &attachment.hash, key, attachment.id)?;
+        }
+      }
+      db.search.add(key, &content, None)?;
+      checkpoint("insert:indexed");
+      txn.commit()?;
+      checkpoint("insert:committed");
+      Ok(Article::new(db.content_dir(), key, content))
+    })
+  }
+}
+
+impl Store {
+  pub fn add(&mut self, content: ArticleContent, upload: Option<Upload>) -> Result<Article, Error> {
+    let mut content = content.normalized()?;
+    if let Some(ref upload) = upload {
+      let txn = ReadTransaction::new(self.env.clone())?;
+      let access = txn.access();
+      if let Some(&(id, _)) = self.blobs(&txn).refs(&access, upload.hash())?.first() {
+        return Err(AttachmentError::Duplicate(id).into());
+      }
+      let attachment = upload.attachment(content.new_attachment_id());
+      content.attachments = vec![attachment];
+    }
+    let article = self.insert(content, upload)?;
+    info!("Store An article is added with id={}.", article.id());
+    Ok(article)
+  }
+}
+
 impl Handler<Add> for Db {
   type Result = Result<Article, Error>;
 
   fn handle(&mut self, msg: Add, _: &mut Self::Context) -> Self::Result {
-    let content = ArticleContent::new(msg.title, msg.authors);
-    let txn = WriteTransaction::new(self.env.clone())?;
-    let key = self.id.new()?;
-    {
-      let mut access = txn.access();
-      if let Err(e) = access.put(&self.db, &key, &content, put::NOOVERWRITE) {
-        return Err(format_err!("{:?}", e));
-      }
-      info!("Db[Add] An article is added with id={}.", key);
-      self.search.add(key, &content)?;
-    }
-    txn.commit()?;
-    Ok(Article::new(self.content_path(key), key, content))
+    self.store()?.add(msg.content, msg.upload)
   }
 }
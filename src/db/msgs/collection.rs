@@ -0,0 +1,260 @@
+use actix::{Handler, Message, ResponseFuture};
+use failure::Error;
+use lmdb::{ReadTransaction, WriteTransaction};
+
+use super::super::collection::{Collection, Kind};
+use super::super::{Db, Reader, Store};
+
+/// Lists the collections, optionally of one kind.
+pub struct ListCollections(Option<Kind>);
+
+impl ListCollections {
+  pub fn new(kind: Option<Kind>) -> Self {
+    Self(kind)
+  }
+}
+impl Message for ListCollections {
+  type Result = Result<Vec<Collection>, Error>;
+}
+
+impl Store {
+  pub fn list_collections(&self, kind: Option<Kind>) -> Result<Vec<Collection>, Error> {
+    let txn = ReadTransaction::new(self.env.clone())?;
+    let access = txn.access();
+    let mut collections = self.collections(&txn).all(&access)?;
+    if let Some(kind) = kind {
+      collections.retain(|c| c.kind == kind);
+    }
+    Ok(collections)
+  }
+}
+
+impl Handler<ListCollections> for Db {
+  type Result = ResponseFuture<Vec<Collection>, Error>;
+
+  fn handle(&mut self, msg: ListCollections, _: &mut Self::Context) -> Self::Result {
+    self.read(msg)
+  }
+}
+
+impl Handler<ListCollections> for Reader {
+  type Result = Result<Vec<Collection>, Error>;
+
+  fn handle(&mut self, msg: ListCollections, _: &mut Self::Context) -> Self::Result {
+    self.store()?.list_collections(msg.0)
+  }
+}
+
+/// Creates a folder or a tag.
+pub struct CreateCollection {
+  kind: Kind,
+  name: String,
+  parent: Option<u64>,
+}
+
+impl CreateCollection {
+  pub fn new<S: Into<String>>(kind: Kind, name: S, parent: Option<u64>) -> Self {
+    Self {
+      kind,
+      name: name.into(),
+      parent,
+    }
+  }
+}
+impl Message for CreateCollection {
+  type Result = Result<Collection, Error>;
+}
+
+impl Store {
+  pub fn create_collection(
+    &mut self,
+    kind: Kind,
+    name: String,
+    parent: Option<u64>,
+  ) -> Result<Collection, Error> {
+    let txn = WriteTransaction::new(self.env.clone())?;
+    let collection = {
+      let mut access = txn.access();
+      self
+        .collections(&txn)
+        .create(&mut access, kind, name, parent)?
+    };
+    txn.commit()?;
+    info!(
+      "Store The collection '{}' is created with id={}.",
+      collection.name, collection.id
+    );
+    Ok(collection)
+  }
+}
+
+impl Handler<CreateCollection> for Db {
+  type Result = Result<Collection, Error>;
+
+  fn handle(&mut self, msg: CreateCollection, _: &mut Self::Context) -> Self::Result {
+    self
+      .store()?
+      .create_collection(msg.kind, msg.name, msg.parent)
+  }
+}
+
+/// Renames a collection.
+pub struct RenameCollection {
+  id: u64,
+  name: String,
+}
+
+impl RenameCollection {
+  pub fn new<S: Into<String>>(id: u64, name: S) -> Self {
+    Self {
+      id,
+      name: name.into(),
+    }
+  }
+}
+impl Message for RenameCollection {
+  type Result = Result<Collection, Error>;
+}
+
+impl Store {
+  pub fn rename_collection(&mut self, id: u64, name: String) -> Result<Collection, Error> {
+    let txn = WriteTransaction::new(self.env.clone())?;
+    let collection = {
+      let mut access = txn.access();
+      self.collections(&txn).rename(&mut access, id, name)?
+    };
+    txn.commit()?;
+    Ok(collection)
+  }
+}
+
+impl Handler<RenameCollection> for Db {
+  type Result = Result<Collection, Error>;
+
+  fn handle(&mut self, msg: RenameCollection, _: &mut Self::Context) -> Self::Result {
+    self.store()?.rename_collection(msg.id, msg.name)
+  }
+}
+
+/// Deletes a collection and its descendants.
+pub struct DeleteCollection(u64);
+
+impl DeleteCollection {
+  pub fn new(id: u64) -> Self {
+    Self(id)
+  }
+}
+impl Message for DeleteCollection {
+  type Result = Result<Collection, Error>;
+}
+
+impl Store {
+  pub fn delete_collection(&mut self, id: u64) -> Result<Collection, Error> {
+    let txn = WriteTransaction::new(self.env.clone())?;
+    let collection = {
+      let mut access = txn.access();
+      self.collections(&txn).delete(&mut access, id)?
+    };
+    txn.commit()?;
+    info!(
+      "Store The collection '{}' (id={}) is deleted.",
+      collection.name, collection.id
+    );
+    Ok(collection)
+  }
+}
+
+impl Handler<DeleteCollection> for Db {
+  type Result = Result<Collection, Error>;
+
+  fn handle(&mut self, msg: DeleteCollection, _: &mut Self::Context) -> Self::Result {
+    self.store()?.delete_collection(msg.0)
+  }
+}
+
+/// Adds an article to or removes it from a collection.
+pub struct Assign {
+  collection: u64,
+  article: u64,
+  member: bool,
+}
+
+impl Assign {
+  pub fn new(collection: u64, article: u64, member: bool) -> Self {
+    Self {
+      collection,
+      article,
+      member,
+    }
+  }
+}
+impl Message for Assign {
+  type Result = Result<Collection, Error>;
+}
+
+impl Store {
+  pub fn assign(
+    &mut self,
+    collection: u64,
+    article: u64,
+    member: bool,
+  ) -> Result<Collection, Error> {
+    let txn = WriteTransaction::new(self.env.clone())?;
+    let collection = {
+      let mut access = txn.access();
+      let collections = self.collections(&txn);
+      collections.get(&access, collection)?;
+      access.get::<u64, [u8]>(&self.db, &article)?;
+      collections.assign(&mut access, collection, article, member)?
+    };
+    txn.commit()?;
+    Ok(collection)
+  }
+}
+
+impl Handler<Assign> for Db {
+  type Result = Result<Collection, Error>;
+
+  fn handle(&mut self, msg: Assign, _: &mut Self::Context) -> Self::Result {
+    self
+      .store()?
+      .assign(msg.collection, msg.article, msg.member)
+  }
+}
+
+/// Lists the collections an article belongs to.
+pub struct Memberships(u64);
+
+impl Memberships {
+  pub fn new(article: u64) -> Self {
+    Self(article)
+  }
+}
+impl Message for Memberships {
+  type Result = Result<Vec<Collection>, Error>;
+}
+
+impl Store {
+  pub fn memberships(&self, article: u64) -> Result<Vec<Collection>, Error> {
+    let txn = ReadTransaction::new(self.env.clone())?;
+    let access = txn.access();
+    access.get::<u64, [u8]>(&self.db, &article)?;
+    self.collections(&txn).memberships(&access, article)
+  }
+}
+
+impl Handler<Memberships> for Db {
+  type Result = ResponseFuture<Vec<Collection>, Error>;
+
+  fn handle(&mut self, msg: Memberships, _: &mut Self::Context) -> Self::Result {
+    self.read(msg)
+  }
+}
+
+impl Handler<Memberships> for Reader {
+  type Result = Result<Vec<Collection>, Error>;
+
+  fn handle(&mut self, msg: Memberships, _: &mut Self::Context) -> Self::Result {
+    self.store()?.memberships(msg.0)
+  }
+}
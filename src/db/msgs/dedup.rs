@@ -0,0 +1,194 @@
+use actix::{Handler, Message, ResponseFuture};
+use failure::Error;
+use lmdb::{put, LmdbResultExt, ReadTransaction, WriteTransaction};
+
+use super::super::article::{Article, ArticleContent};
+use super::super::dedup::{self, MergeError, Pair, Signature};
+use super::super::journal::{checkpoint, Intent};
+use super::super::{Db, Reader, Store};
+
+#[derive(Serialize)]
+pub struct DuplicateCluster {
+  score: f64,
+  pairs: Vec<Pair>,
+  articles: Vec<Article>,
+}
+
+/// Groups the articles whose metadata are at least this similar.
+pub struct Duplicates(f64);
+
+impl Duplicates {
+  pub fn new(threshold: f64) -> Self {
+    Self(threshold)
+  }
+}
+impl Message for Duplicates {
+  type Result = Result<Vec<DuplicateCluster>, Error>;
+}
+
+impl Store {
+  pub fn duplicates(&self, threshold: f64) -> Result<Vec<DuplicateCluster>, Error> {
+    let txn = ReadTransaction::new(self.env.clone())?;
+    let access = txn.access();
+    let mut cursor = txn.cursor(&self.db)?;
+    let mut docs = Vec::new();
+    let mut item = cursor.first::<[u8], [u8]>(&access).to_opt()?;
+    while let Some((key, bytes)) = item {
+      if let Some(key) = Self::decode_key(key) {
+        docs.push((key, Signature::new(&ArticleContent::decode(bytes)?)));
+      }
+      item = cursor.next::<[u8], [u8]>(&access).to_opt()?;
+    }
+    dedup::clusters(dedup::pairs(&docs, threshold))
+      .into_iter()
+      .map(|cluster| {
+        let articles = cluster
+          .ids
+          .iter()
+          .map(|&key| {
+            let content = ArticleContent::decode(access.get::<u64, [u8]>(&self.db, &key)?)?;
+            Ok(Article::new(self.content_dir(), key, content))
+          })
+          .collect::<Result<_, Error>>()?;
+        Ok(DuplicateCluster {
+          score: cluster.score,
+          pairs: cluster.pairs,
+          articles,
+        })
+      })
+      .collect()
+  }
+}
+
+impl Handler<Duplicates> for Db {
+  type Result = ResponseFuture<Vec<DuplicateCluster>, Error>;
+
+  fn handle(&mut self, msg: Duplicates, _: &mut Self::Context) -> Self::Result {
+    self.read(msg)
+  }
+}
+
+impl Handler<Duplicates> for Reader {
+  type Result = Result<Vec<DuplicateCluster>, Error>;
+
+  fn handle(&mut self, msg: Duplicates, _: &mut Self::Context) -> Self::Result {
+    self.store()?.duplicates(msg.0)
+  }
+}
+
+/// Merges articles into one, moving their attachments and memberships.
+pub struct Merge {
+  into: u64,
+  from: Vec<u64>,
+}
+
+impl Merge {
+  pub fn new(into: u64, from: Vec<u64>) -> Self {
+    Self { into, from }
+  }
+}
+impl Message for Merge {
+  type Result = Result<(Article, Vec<Article>), Error>;
+}
+
+impl Store {
+  pub fn merge(&mut self, into: u64, mut from: Vec<u64>) -> Result<(Article, Vec<Article>), Error> {
+    from.sort();
+    from.dedup();
+    if from.is_empty() {
+      return Err(MergeError::Empty.into());
+    }
+    if from.contains(&into) {
+      return Err(MergeError::SameArticle(into).into());
+    }
+    let mut intent = Intent::ids(from.clone());
+    intent.ids.push(into);
+    {
+      let txn = ReadTransaction::new(self.env.clone())?;
+      let access = txn.access();
+      for &key in from.iter() {
+        let content = match access.get::<u64, [u8]>(&self.db, &key).to_opt()? {
+          Some(bytes) => ArticleContent::decode(bytes)?,
+          None => return Err(MergeError::NotFound(key).into()),
+        };
+        for a in content.attachments.iter() {
+          intent = intent.blob(a.hash, a.media_type);
+        }
+      }
+    }
+    let (content, sources) = self.journaled(intent, |db| {
+      let txn = WriteTransaction::new(db.env.clone())?;
+      let (content, text, sources) = {
+        let collections = db.collections(&txn);
+        let blobs = db.blobs(&txn);
+        let mut access = txn.access();
+        let read = |key: u64| -> Result<(ArticleContent, Option<String>), Error> {
+          let content = match access.get::<u64, [u8]>(&db.db, &key).to_opt()? {
+            Some(bytes) => ArticleContent::decode(bytes)?,
+            None => return Err(MergeError::NotFound(key).into()),
+          };
+          let text = access
+            .get::<u64, str>(&db.text, &key)
+            .to_opt()?
+            .map(str::to_owned);
+          Ok((content, text))
+        };
+        let (prev, mut text) = read(into)?;
+        let mut sources = Vec::with_capacity(from.len());
+        for &key in from.iter() {
+          let (content, other) = read(key)?;
+          if text.as_ref().is_none_or(|t| t.is_empty()) {
+            text = other.or(text);
+          }
+          sources.push((key, content));
+        }
+        let (content, moved) = dedup::merge(prev, &sources);
+        let content = content.normalized()?;
+
+        access.put(&db.db, &into, &content.encode()[..], put::Flags::empty())?;
+        if let Some(text) = text.as_ref() {
+          access.put(&db.text, &into, text.as_str(), put::Flags::empty())?;
+        }
+        for m in moved {
+          blobs.del(&mut access, &m.from.hash, m.article, m.from.id)?;
+          if let Some(id) = m.into {
+            blobs.add(&mut access, &m.from.hash, into, id)?;
+          }
+        }
+        for (key, _) in sources.iter() {
+          for collection in collections.memberships(&access, *key)? {
+            collections.assign(&mut access, collection.id, into, true)?;
+          }
+          collections.forget(&mut access, *key)?;
+          access.del_key(&db.db, key)?;
+          access.del_key(&db.text, key).to_opt()?;
+        }
+        (content, text, sources)
+      };
+      for (key, _) in sources.iter() {
+        db.search.del(*key)?;
+        db.id.del(*key)?;
+      }
+      db.search.add(into, &content, text.as_deref())?;
+      checkpoint("merge:indexed");
+      txn.commit()?;
+      Ok((content, sources))
+    })?;
+    info!("Store The articles {:?} are merged into id={}.", from, into);
+    Ok((
+      Article::new(self.content_dir(), into, content),
+      sources
+        .into_iter()
+        .map(|(key, content)| Article::new(self.content_dir(), key, content))
+        .collect(),
+    ))
+  }
+}
+
+impl Handler<Merge> for Db {
+  type Result = Result<(Article, Vec<Article>), Error>;
+
+  fn handle(&mut self, msg: Merge, _: &mut Self::Context) -> Self::Result {
+    self.store()?.merge(msg.into, msg.from)
+  }
+}
@@ -0,0 +1,491 @@
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+use actix::{Handler, Message, ResponseFuture};
+use failure::Error;
+use lmdb::{put, WriteTransaction};
+use tempfile::{NamedTempFile, TempPath};
+
+use super::super::article::{Article, Attachment, AttachmentError, MediaType};
+use super::super::hash::Hash;
+use super::super::journal::{checkpoint, Intent};
+use super::super::{Db, Reader, Store};
+
+const HEAD_SIZE: usize = 64;
+
+/// A file to be attached, hashed and typed on creation.
+pub struct Upload {
+  file: NamedTempFile,
+  name: Option<String>,
+  media_type: MediaType,
+  hash: Hash,
+  size: u64,
+}
+
+impl Upload {
+  pub fn new(file: NamedTempFile, name: Option<String>) -> Result<Self, Error> {
+    let name = name
+      .map(|name| name.rsplit(['/', '\\']).next().unwrap_or("").to_owned())
+      .filter(|name| !name.is_empty());
+    let mut head = Vec::with_capacity(HEAD_SIZE);
+    File::open(file.path())?
+      .take(HEAD_SIZE as u64)
+      .read_to_end(&mut head)?;
+    let hash = Hash::of(File::open(file.path())?)?;
+    file.as_file().sync_all()?;
+    Ok(Self {
+      size: file.as_file().metadata()?.len(),
+      file,
+      name,
+      media_type: MediaType::detect(&head),
+      hash,
+    })
+  }
+
+  pub fn file(&self) -> &NamedTempFile {
+    &self.file
+  }
+
+  pub fn media_type(&self) -> MediaType {
+    self.media_type
+  }
+
+  pub fn hash(&self) -> &Hash {
+    &self.hash
+  }
+
+  pub fn attachment(&self, id: u32) -> Attachment {
+    Attachment {
+      id,
+      media_type: self.media_type,
+      hash: self.hash,
+      name: self.name.clone(),
+      size: self.size,
+    }
+  }
+
+  pub fn persist(self, path: &Path) -> Result<(), Error> {
+    self
+      .file
+      .persist(path)
+      .map_err(|e| format_err!("The file failed to move: {}", e))?;
+    Ok(())
+  }
+}
+
+impl Store {
+  fn revision_files(article: &Article) -> Result<Vec<(u32, PathBuf)>, Error> {
+    let entries = match fs::read_dir(article.revision_dir()) {
+      Ok(entries) => entries,
+      Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+      Err(e) => return Err(e.into()),
+    };
+    let mut revisions = Vec::new();
+    for entry in entries {
+      let path = entry?.path();
+      if let Some(revision) = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .and_then(|s| s.parse().ok())
+      {
+        revisions.push((revision, path));
+      }
+    }
+    revisions.sort();
+    Ok(revisions)
+  }
+}
+
+/// A new primary file of an article together with the old one linked aside,
+/// prepared ahead of [`ReplaceFile`] so that the write itself only renames
+/// and commits.
+pub struct Replacement {
+  upload: Upload,
+  previous: Option<(Hash, TempPath)>,
+}
+
+impl Replacement {
+  /// Links, or copies if it cannot, the current primary file of `article`
+  /// into its revisions under a temporary name. This blocks on the file
+  /// system and runs outside the store.
+  pub fn new(article: &Article, upload: Upload) -> Result<Self, Error> {
+    let previous = match article.content().primary() {
+      Some(previous) if article.attachment_path(previous).exists() => {
+        let old = article.attachment_path(previous);
+        let dir = article.revision_dir();
+        fs::create_dir_all(&dir)?;
+        let path = NamedTempFile::new_in(&dir)?.into_temp_path();
+        fs::remove_file(&path)?;
+        if let Err(e) = fs::hard_link(&old, &path) {
+          warn!(
+            "Store Failed to link the replaced file of id={} into its revisions ({}), copying instead.",
+            article.id(),
+            e
+          );
+          fs::copy(&old, &path)?;
+        }
+        Some((previous.hash, path))
+      }
+      _ => None,
+    };
+    Ok(Self { upload, previous })
+  }
+}
+
+/// Replaces the primary attachment, keeping the old file as a revision.
+pub struct ReplaceFile {
+  id: u64,
+  replacement: Replacement,
+}
+
+impl ReplaceFile {
+  pub fn new(id: u64, replacement: Replacement) -> Self {
+    Self { id, replacement }
+  }
+}
+impl Message for ReplaceFile {
+  type Result = Result<(Article, Option<u32>), Error>;
+}
+
+impl Store {
+  pub fn replace_file(&mut self, id: u64, upload: Upload) -> Result<(Article, Option<u32>), Error> {
+    let replacement = Replacement::new(&self.article(id)?, upload)?;
+    let (article, revision) = self.replace(id, replacement)?;
+    self.index_text(&article)?;
+    Ok((article, revision))
+  }
+
+  fn replace(
+    &mut self,
+    id: u64,
+    replacement: Replacement,
+  ) -> Result<(Article, Option<u32>), Error> {
+    let Replacement {
+      upload,
+      previous: linked,
+    } = replacement;
+    let article = self.article(id)?;
+    let previous = article.content().primary().cloned();
+    if let Some((ref hash, _)) = linked {
+      if previous.as_ref().map(|p| &p.hash) != Some(hash) {
+        bail!(
+          "The file of id={} was replaced while uploading another.",
+          id
+        );
+      }
+    }
+    let mut content = article.content().clone();
+    let attachment = upload.attachment(
+      previous
+        .as_ref()
+        .map_or_else(|| content.new_attachment_id(), |p| p.id),
+    );
+    let mut intent = Intent::ids(vec![id]).blob(attachment.hash, attachment.media_type);
+    match previous {
+      Some(ref previous) => {
+        content.attachments[0] = attachment.clone();
+        intent = intent.blob(previous.hash, previous.media_type);
+      }
+      None => content.attachments.push(attachment.clone()),
+    }
+    let revision = self.journaled(intent, |db| {
+      db.store(upload)?;
+      let revision = match (linked, previous.as_ref()) {
+        (Some((_, linked)), Some(previous)) => {
+          let revision = Self::revision_files(&article)?
+            .last()
+            .map_or(1, |r| r.0 + 1);
+          let path = article.revision_path(revision, previous.media_type);
+          linked
+            .persist(&path)
+            .map_err(|e| format_err!("The revision failed to move: {}", e))?;
+          Some((revision, path))
+        }
+        _ => None,
+      };
+      checkpoint("replace:linked");
+      let result = (|| -> Result<(), Error> {
+        let txn = WriteTransaction::new(db.env.clone())?;
+        {
+          let blobs = db.blobs(&txn);
+          let mut access = txn.access();
+          access.put(&db.db, &id, &content.encode()[..], put::Flags::empty())?;
+          if let Some(ref previous) = previous {
+            blobs.del(&mut access, &previous.hash, id, previous.id)?;
+          }
+          blobs.add(&mut access, &attachment.hash, id, attachment.id)?;
+        }
+        txn.commit()?;
+        Ok(())
+      })();
+      if let Err(e) = result {
+        if let Some((_, ref path)) = revision {
+          let _ = fs::remove_file(path);
+        }
+        return Err(e);
+      }
+      Ok(revision.map(|(revision, _)| revision))
+    })?;
+    match revision {
+      Some(revision) => info!(
+        "Store The file of id={} is replaced, and the old one is kept as revision {}.",
+        id, revision
+      ),
+      None => info!(
+        "Store The article id={} had no file, so the new one is attached.",
+        id
+      ),
+    }
+    Ok((Article::new(self.content_dir(), id, content), revision))
+  }
+}
+
+impl Handler<ReplaceFile> for Db {
+  type Result = Result<(Article, Option<u32>), Error>;
+
+  fn handle(&mut self, msg: ReplaceFile, _: &mut Self::Context) -> Self::Result {
+    self.store()?.replace(msg.id, msg.replacement)
+  }
+}
+
+/// Adds a secondary attachment to an article.
+pub struct Attach {
+  id: u64,
+  upload: Upload,
+}
+
+impl Attach {
+  pub fn new(id: u64, upload: Upload) -> Self {
+    Self { id, upload }
+  }
+}
+impl Message for Attach {
+  type Result = Result<Article, Error>;
+}
+
+impl Store {
+  pub fn attach(&mut self, id: u64, upload: Upload) -> Result<Article, Error> {
+    let article = self.article(id)?;
+    let mut content = article.content().clone();
+    let attachment = upload.attachment(content.new_attachment_id());
+    content.attachments.push(attachment.clone());
+    let intent = Intent::ids(vec![id]).blob(attachment.hash, attachment.media_type);
+    self.journaled(intent, |db| {
+      db.store(upload)?;
+      let txn = WriteTransaction::new(db.env.clone())?;
+      {
+        let blobs = db.blobs(&txn);
+        let mut access = txn.access();
+        access.put(&db.db, &id, &content.encode()[..], put::Flags::empty())?;
+        blobs.add(&mut access, &attachment.hash, id, attachment.id)?;
+      }
+      txn.commit()?;
+      Ok(())
+    })?;
+    info!(
+      "Store The attachment {} is added to id={}.",
+      attachment.id, id
+    );
+    Ok(Article::new(self.content_dir(), id, content))
+  }
+}
+
+impl Handler<Attach> for Db {
+  type Result = Result<Article, Error>;
+
+  fn handle(&mut self, msg: Attach, _: &mut Self::Context) -> Self::Result {
+    self.store()?.attach(msg.id, msg.upload)
+  }
+}
+
+/// Removes an attachment from an article.
+pub struct Detach {
+  id: u64,
+  attachment: u32,
+}
+
+impl Detach {
+  pub fn new(id: u64, attachment: u32) -> Self {
+    Self { id, attachment }
+  }
+}
+impl Message for Detach {
+  type Result = Result<Article, Error>;
+}
+
+impl Store {
+  pub fn detach(&mut self, id: u64, attachment: u32) -> Result<Article, Error> {
+    let article = self.article(id)?;
+    let detached = article
+      .content()
+      .attachment(attachment)
+      .ok_or(AttachmentError::NotFound(id, attachment))?;
+    let mut content = article.content().clone();
+    content.attachments.retain(|a| a.id != attachment);
+    let intent = Intent::ids(vec![id]).blob(detached.hash, detached.media_type);
+    self.journaled(intent, |db| {
+      let txn = WriteTransaction::new(db.env.clone())?;
+      {
+        let blobs = db.blobs(&txn);
+        let mut access = txn.access();
+        access.put(&db.db, &id, &content.encode()[..], put::Flags::empty())?;
+        blobs.del(&mut access, &detached.hash, id, detached.id)?;
+      }
+      txn.commit()?;
+      Ok(())
+    })?;
+    info!(
+      "Store The attachment {} is removed from id={}.",
+      attachment, id
+    );
+    Ok(Article::new(self.content_dir(), id, content))
+  }
+}
+
+impl Handler<Detach> for Db {
+  type Result = Result<Article, Error>;
+
+  fn handle(&mut self, msg: Detach, _: &mut Self::Context) -> Self::Result {
+    self.store()?.detach(msg.id, msg.attachment)
+  }
+}
+
+/// Lists the previous primary files of an article.
+pub struct Revisions(u64);
+
+impl Revisions {
+  pub fn new(id: u64) -> Self {
+    Self(id)
+  }
+}
+impl Message for Revisions {
+  type Result = Result<Vec<u32>, Error>;
+}
+
+impl Store {
+  pub fn revisions(&self, id: u64) -> Result<Vec<u32>, Error> {
+    Ok(
+      Self::revision_files(&self.article(id)?)?
+        .into_iter()
+        .map(|(revision, _)| revision)
+        .collect(),
+    )
+  }
+}
+
+impl Handler<Revisions> for Db {
+  type Result = ResponseFuture<Vec<u32>, Error>;
+
+  fn handle(&mut self, msg: Revisions, _: &mut Self::Context) -> Self::Result {
+    self.read(msg)
+  }
+}
+
+impl Handler<Revisions> for Reader {
+  type Result = Result<Vec<u32>, Error>;
+
+  fn handle(&mut self, msg: Revisions, _: &mut Self::Context) -> Self::Result {
+    self.store()?.revisions(msg.0)
+  }
+}
+
+/// Locates a previous primary file of an article.
+pub struct Revision {
+  id: u64,
+  revision: u32,
+}
+
+impl Revision {
+  pub fn new(id: u64, revision: u32) -> Self {
+    Self { id, revision }
+  }
+}
+impl Message for Revision {
+  type Result = Result<Option<(PathBuf, MediaType)>, Error>;
+}
+
+impl Store {
+  pub fn revision(&self, id: u64, revision: u32) -> Result<Option<(PathBuf, MediaType)>, Error> {
+    Ok(
+      Self::revision_files(&self.article(id)?)?
+        .into_iter()
+        .find(|&(r, _)| r == revision)
+        .map(|(_, path)| {
+          let media_type = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(MediaType::from_extension)
+            .unwrap_or(MediaType::Other);
+          (path, media_type)
+        }),
+    )
+  }
+}
+
+impl Handler<Revision> for Db {
+  type Result = ResponseFuture<Option<(PathBuf, MediaType)>, Error>;
+
+  fn handle(&mut self, msg: Revision, _: &mut Self::Context) -> Self::Result {
+    self.read(msg)
+  }
+}
+
+impl Handler<Revision> for Reader {
+  type Result = Result<Option<(PathBuf, MediaType)>, Error>;
+
+  fn handle(&mut self, msg: Revision, _: &mut Self::Context) -> Self::Result {
+    self.store()?.revision(msg.id, msg.revision)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::super::super::tests::{add, check, load, upload};
+  use super::super::super::Query;
+  use super::*;
+
+  #[test]
+  fn test_replace_stale() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let mut db = load(&dir)?;
+    let article = add(&mut db, "Literate Programming", b"%PDF-1.4 first")?;
+    let replacement = Replacement::new(&article, upload(b"%PDF-1.4 second")?)?;
+    db.replace_file(article.id(), upload(b"%PDF-1.4 third")?)?;
+    assert!(db.replace(article.id(), replacement).is_err());
+    assert_eq!(fs::read_dir(article.revision_dir())?.count(), 1);
+    check(&db)?;
+    Ok(())
+  }
+
+  #[test]
+  fn test_replace_text() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let mut db = load(&dir)?;
+    let article = add(&mut db, "Literate Programming", b"%PDF-1.4 first")?;
+    let stale = Query::parse("obsolete")?;
+    db.set_text(article.id(), "obsolete".to_owned())?;
+    assert_eq!(db.search(&stale)?.len(), 1);
+    db.replace_file(article.id(), upload(b"%PDF-1.4 second")?)?;
+    assert!(db.search(&stale)?.is_empty());
+    check(&db)?;
+    Ok(())
+  }
+
+  #[test]
+  fn test_attachment_ids() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let mut db = load(&dir)?;
+    let id = add(&mut db, "Literate Programming", b"%PDF-1.4 first")?.id();
+    let attached = |db: &Article| db.content().attachments.last().map(|a| a.id);
+    let article = db.attach(id, upload(b"%PDF-1.4 second")?)?;
+    assert_eq!(attached(&article), Some(1));
+    db.detach(id, 1)?;
+    drop(db);
+    let mut db = load(&dir)?;
+    let article = db.attach(id, upload(b"%PDF-1.4 third")?)?;
+    assert_eq!(attached(&article), Some(2));
+    check(&db)?;
+    Ok(())
+  }
+}
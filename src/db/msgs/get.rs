@@ -1,10 +1,10 @@
-use actix::{Handler, Message};
+use actix::{Handler, Message, ResponseFuture};
 use failure::Error;
-use lmdb::ReadTransaction;
 
-use super::super::article::{Article, ArticleContent};
-use super::super::Db;
+use super::super::article::Article;
+use super::super::{Db, Reader, Store};
 
+/// Fetches an article by id.
 pub struct Get(u64);
 
 impl Get {
@@ -16,13 +16,24 @@ impl Message for Get {
   type Result = Result<Article, Error>;
 }
 
+impl Store {
+  pub fn get(&self, id: u64) -> Result<Article, Error> {
+    self.article(id)
+  }
+}
+
 impl Handler<Get> for Db {
+  type Result = ResponseFuture<Article, Error>;
+
+  fn handle(&mut self, msg: Get, _: &mut Self::Context) -> Self::Result {
+    self.read(msg)
+  }
+}
+
+impl Handler<Get> for Reader {
   type Result = Result<Article, Error>;
 
   fn handle(&mut self, msg: Get, _: &mut Self::Context) -> Self::Result {
-    let key = msg.0;
-    let txn = ReadTransaction::new(self.env.clone())?;
-    let content = (txn.access().get(&self.db, &key)? as &ArticleContent).to_owned();
-    Ok(Article::new(self.content_path(key), key, content))
+    self.store()?.get(msg.0)
   }
 }
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use actix::{Handler, Message};
+use failure::Error;
+use lmdb::{put, LmdbResultExt, ReadTransaction, WriteTransaction};
+
+use super::super::article::ArticleContent;
+use super::super::journal::{checkpoint, Intent};
+use super::super::{Db, Store};
+
+/// The outcome of one entry of an [`Import`].
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Imported {
+  Added { id: u64 },
+  Duplicate { id: u64 },
+  Invalid { error: String },
+}
+
+/// Adds many articles at once, skipping duplicates of existing ones.
+pub struct Import(Vec<ArticleContent>);
+
+impl Import {
+  pub fn new(contents: Vec<ArticleContent>) -> Self {
+    Self(contents)
+  }
+}
+impl Message for Import {
+  type Result = Result<Vec<Imported>, Error>;
+}
+
+fn title_key(title: &str) -> String {
+  title
+    .to_lowercase()
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|w| !w.is_empty())
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// An article that an entry may duplicate: a stored one, or the n-th new one
+/// of the batch, whose id is allocated only once the batch is written.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Ref {
+  Stored(u64),
+  New(usize),
+}
+
+#[derive(Default)]
+struct Known {
+  titles: HashMap<String, Vec<(Ref, Option<String>)>>,
+  dois: HashMap<String, Ref>,
+}
+
+impl Known {
+  fn insert(&mut self, key: Ref, content: &ArticleContent) {
+    let doi = content.doi.as_ref().map(|d| d.to_lowercase());
+    if let Some(doi) = doi.as_ref() {
+      self.dois.insert(doi.clone(), key);
+    }
+    self
+      .titles
+      .entry(title_key(&content.title))
+      .or_default()
+      .push((key, doi));
+  }
+
+  fn find(&self, content: &ArticleContent) -> Option<Ref> {
+    let doi = content.doi.as_ref().map(|d| d.to_lowercase());
+    if let Some(&key) = doi.as_ref().and_then(|d| self.dois.get(d)) {
+      return Some(key);
+    }
+    self
+      .titles
+      .get(&title_key(&content.title))?
+      .iter()
+      .find(|(_, other)| doi.is_none() || other.is_none())
+      .map(|&(key, _)| key)
+  }
+}
+
+impl Store {
+  /// Adds the new entries of `contents` in one journaled write, so either
+  /// all of them are added or none.
+  pub fn import(&mut self, contents: Vec<ArticleContent>) -> Result<Vec<Imported>, Error> {
+    self.resume()?;
+    let mut known = Known::default();
+    {
+      let txn = ReadTransaction::new(self.env.clone())?;
+      let access = txn.access();
+      let mut cursor = txn.cursor(&self.db)?;
+      let mut item = cursor.first::<[u8], [u8]>(&access).to_opt()?;
+      while let Some((key, bytes)) = item {
+        if let Some(key) = Self::decode_key(key) {
+          known.insert(Ref::Stored(key), &ArticleContent::decode(bytes)?);
+        }
+        item = cursor.next::<[u8], [u8]>(&access).to_opt()?;
+      }
+    }
+
+    let mut outcomes = Vec::with_capacity(contents.len());
+    let mut added = Vec::new();
+    for content in contents {
+      let content = match content.normalized() {
+        Ok(content) => content,
+        Err(e) => {
+          outcomes.push(Err(e.to_string()));
+          continue;
+        }
+      };
+      if let Some(key) = known.find(&content) {
+        outcomes.push(Ok((key, false)));
+        continue;
+      }
+      let key = Ref::New(added.len());
+      known.insert(key, &content);
+      outcomes.push(Ok((key, true)));
+      added.push(content);
+    }
+    let keys = if added.is_empty() {
+      Vec::new()
+    } else {
+      let intent = added.iter().fold(Intent::default(), |intent, content| {
+        content
+          .attachments
+          .iter()
+          .fold(intent, |intent, a| intent.blob(a.hash, a.media_type))
+      });
+      self.journaled(intent, |db| {
+        let keys = db.allocate(added.len())?;
+        let txn = WriteTransaction::new(db.env.clone())?;
+        {
+          let blobs = db.blobs(&txn);
+          let mut access = txn.access();
+          for (key, content) in keys.iter().zip(added.iter()) {
+            if let Err(e) = access.put(&db.db, key, &content.encode()[..], put::NOOVERWRITE) {
+              return Err(format_err!("{:?}", e));
+            }
+            for attachment in content.attachments.iter() {
+              blobs.add(&mut access, &attachment.hash, *key, attachment.id)?;
+            }
+          }
+        }
+        for (key, content) in keys.iter().zip(added.iter()) {
+          db.search.add(*key, content, None)?;
+        }
+        checkpoint("import:indexed");
+        txn.commit()?;
+        Ok(keys)
+      })?
+    };
+    let imported: Vec<Imported> = outcomes
+      .into_iter()
+      .map(|outcome| match outcome {
+        Err(error) => Imported::Invalid { error },
+        Ok((key, new)) => {
+          let id = match key {
+            Ref::Stored(id) => id,
+            Ref::New(n) => keys[n],
+          };
+          if new {
+            Imported::Added { id }
+          } else {
+            Imported::Duplicate { id }
+          }
+        }
+      })
+      .collect();
+    info!(
+      "Store {} of {} imported entries are added.",
+      imported
+        .iter()
+        .filter(|i| matches!(i, Imported::Added { .. }))
+        .count(),
+      imported.len()
+    );
+    Ok(imported)
+  }
+}
+
+impl Handler<Import> for Db {
+  type Result = Result<Vec<Imported>, Error>;
+
+  fn handle(&mut self, msg: Import, _: &mut Self::Context) -> Self::Result {
+    self.store()?.import(msg.0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::super::super::tests::{add, check, crash, load};
+  use super::*;
+
+  #[test]
+  fn test_known() {
+    let mut known = Known::default();
+    let doi = |title: &str, doi: Option<&str>| ArticleContent {
+      doi: doi.map(str::to_owned),
+      ..ArticleContent::new(title, ["Knuth"]).unwrap()
+    };
+    known.insert(
+      Ref::Stored(0),
+      &doi("Literate Programming", Some("10.1093/comjnl/27.2.97")),
+    );
+    known.insert(Ref::New(0), &doi("The Art of Computer Programming", None));
+    assert_eq!(
+      known.find(&doi("Other", Some("10.1093/COMJNL/27.2.97"))),
+      Some(Ref::Stored(0))
+    );
+    assert_eq!(
+      known.find(&doi("literate  programming.", None)),
+      Some(Ref::Stored(0))
+    );
+    assert_eq!(
+      known.find(&doi("Literate Programming", Some("10.1/x"))),
+      None
+    );
+    assert_eq!(
+      known.find(&doi("The art of computer programming", Some("10.1/y"))),
+      Some(Ref::New(0))
+    );
+    assert_eq!(known.find(&doi("Concrete Mathematics", None)), None);
+  }
+
+  #[test]
+  fn test_import_crash() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let mut db = load(&dir)?;
+    add(&mut db, "Literate Programming", b"%PDF-1.4 literate")?;
+    let contents = || {
+      vec![
+        ArticleContent::new("Literate Programming", ["Knuth"]).unwrap(),
+        ArticleContent::new("Concrete Mathematics", ["Graham"]).unwrap(),
+        ArticleContent::new("Surreal Numbers", ["Knuth"]).unwrap(),
+        ArticleContent::new("Concrete mathematics", ["Graham"]).unwrap(),
+      ]
+    };
+    crash("import:indexed", || db.import(contents()));
+    drop(db);
+    let mut db = load(&dir)?;
+    assert_eq!(check(&db)?.len(), 1);
+
+    let imported = db.import(contents())?;
+    assert_eq!(imported[0], Imported::Duplicate { id: 0 });
+    match imported[1] {
+      Imported::Added { id } => assert_eq!(imported[3], Imported::Duplicate { id }),
+      ref other => panic!("Unexpected {:?}", other),
+    }
+    assert_eq!(check(&db)?.len(), 3);
+    Ok(())
+  }
+}
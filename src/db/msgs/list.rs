@@ -0,0 +1,193 @@
+use actix::{Handler, Message, ResponseFuture};
+use failure::Error;
+use lmdb::{LmdbResultExt, ReadTransaction};
+
+use super::super::article::{Article, ArticleContent};
+use super::super::{Db, Reader, Store};
+
+#[derive(Deserialize, Default, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Sort {
+  #[default]
+  Id,
+  Title,
+  Author,
+}
+
+#[derive(Deserialize, Default, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Order {
+  #[default]
+  Asc,
+  Desc,
+}
+
+/// A window of the articles returned by [`List`].
+#[derive(Serialize)]
+pub struct Page {
+  total: u64,
+  offset: usize,
+  limit: usize,
+  articles: Vec<Article>,
+}
+
+impl Page {
+  pub fn into_articles(self) -> Vec<Article> {
+    self.articles
+  }
+}
+
+/// Lists the articles in the given order, `limit` at a time.
+pub struct List {
+  offset: usize,
+  limit: usize,
+  sort: Sort,
+  order: Order,
+}
+
+impl List {
+  pub fn new(offset: usize, limit: usize, sort: Sort, order: Order) -> Self {
+    Self {
+      offset,
+      limit,
+      sort,
+      order,
+    }
+  }
+}
+impl Message for List {
+  type Result = Result<Page, Error>;
+}
+
+impl Store {
+  /// Sorts every record by a key built from its title and authors and returns
+  /// the keys of one page.
+  fn sorted_by<F>(
+    &self,
+    offset: usize,
+    limit: usize,
+    order: Order,
+    sort_key: F,
+  ) -> Result<Vec<u64>, Error>
+  where
+    F: Fn(String, Vec<String>) -> String,
+  {
+    let txn = ReadTransaction::new(self.env.clone())?;
+    let access = txn.access();
+    let mut cursor = txn.cursor(&self.db)?;
+    let mut entries = Vec::new();
+    let mut item = cursor.first::<[u8], [u8]>(&access).to_opt()?;
+    while let Some((key, bytes)) = item {
+      if let Some(key) = Self::decode_key(key) {
+        let (title, authors) = ArticleContent::decode_names(bytes)?;
+        entries.push((sort_key(title, authors), key));
+      }
+      item = cursor.next::<[u8], [u8]>(&access).to_opt()?;
+    }
+    entries.sort();
+    if order == Order::Desc {
+      entries.reverse();
+    }
+    Ok(
+      entries
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|(_, key)| key)
+        .collect(),
+    )
+  }
+
+  pub fn list(&self, offset: usize, limit: usize, sort: Sort, order: Order) -> Result<Page, Error> {
+    let keys = match (sort, order) {
+      (Sort::Id, Order::Asc) => self.id.iter().skip(offset).take(limit).collect(),
+      (Sort::Id, Order::Desc) => self.id.iter_rev().skip(offset).take(limit).collect(),
+      (Sort::Title, _) => self.sorted_by(offset, limit, order, |title, _| title.to_lowercase())?,
+      (Sort::Author, _) => self.sorted_by(offset, limit, order, |_, authors| {
+        authors.first().map_or(String::new(), |a| a.to_lowercase())
+      })?,
+    };
+    let txn = ReadTransaction::new(self.env.clone())?;
+    let access = txn.access();
+    let mut contents = Vec::new();
+    for key in keys {
+      match access.get::<u64, [u8]>(&self.db, &key).to_opt()? {
+        Some(bytes) => contents.push((key, ArticleContent::decode(bytes)?)),
+        None => warn!(
+          "Store The article id={} is listed in the id index but not in the database.",
+          key
+        ),
+      }
+    }
+    Ok(Page {
+      total: self.id.len(),
+      offset,
+      limit,
+      articles: contents
+        .into_iter()
+        .map(|(key, content)| Article::new(self.content_dir(), key, content))
+        .collect(),
+    })
+  }
+}
+
+impl Handler<List> for Db {
+  type Result = ResponseFuture<Page, Error>;
+
+  fn handle(&mut self, msg: List, _: &mut Self::Context) -> Self::Result {
+    self.read(msg)
+  }
+}
+
+impl Handler<List> for Reader {
+  type Result = Result<Page, Error>;
+
+  fn handle(&mut self, msg: List, _: &mut Self::Context) -> Self::Result {
+    self
+      .store()?
+      .list(msg.offset, msg.limit, msg.sort, msg.order)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::super::super::tests::{add, load};
+  use super::*;
+
+  #[test]
+  fn test_list() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let mut db = load(&dir)?;
+    for (title, data) in [
+      ("b", b"%PDF-1.4 b"),
+      ("C", b"%PDF-1.4 c"),
+      ("a", b"%PDF-1.4 a"),
+    ]
+    .iter()
+    {
+      add(&mut db, title, *data)?;
+    }
+    let titles = |page: Page| -> Vec<String> {
+      page
+        .into_articles()
+        .iter()
+        .map(|a| a.content().title.clone())
+        .collect()
+    };
+    assert_eq!(
+      titles(db.list(0, 10, Sort::Id, Order::Asc)?),
+      vec!["b", "C", "a"]
+    );
+    assert_eq!(
+      titles(db.list(0, 10, Sort::Title, Order::Asc)?),
+      vec!["a", "b", "C"]
+    );
+    assert_eq!(titles(db.list(1, 1, Sort::Title, Order::Desc)?), vec!["b"]);
+    assert_eq!(
+      titles(db.list(1, 5, Sort::Id, Order::Desc)?),
+      vec!["C", "b"]
+    );
+    assert_eq!(db.list(2, 5, Sort::Author, Order::Asc)?.total, 3);
+    Ok(())
+  }
+}
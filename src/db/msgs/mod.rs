@@ -1,9 +1,25 @@
 mod add;
+mod collection;
+mod dedup;
+mod file;
 mod get;
+mod import;
+mod list;
+mod reindex;
 mod remove;
 mod search;
+mod text;
+mod update;
 
 pub use self::add::*;
+pub use self::collection::*;
+pub use self::dedup::*;
+pub use self::file::*;
 pub use self::get::*;
+pub use self::import::*;
+pub use self::list::*;
+pub use self::reindex::*;
 pub use self::remove::*;
 pub use self::search::*;
+pub use self::text::*;
+pub use self::update::*;
@@ -0,0 +1,163 @@
+use actix::{Handler, Message};
+use failure::Error;
+use lmdb::{LmdbResultExt, WriteTransaction};
+
+use super::super::article::ArticleContent;
+use super::super::journal::{checkpoint, Intent};
+use super::super::{Db, Store};
+use crate::config::Config;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Progress {
+  pub stage: &'static str,
+  pub done: u64,
+  pub total: u64,
+}
+
+impl Progress {
+  pub const STEP: u64 = 1000;
+
+  pub fn new(stage: &'static str, done: u64, total: u64) -> Self {
+    Self { stage, done, total }
+  }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Reindexed {
+  pub articles: u64,
+  pub attachments: u64,
+}
+
+/// Regenerates the indices of the open store under its write lock.
+pub struct Reindex;
+
+impl Message for Reindex {
+  type Result = Result<Reindexed, Error>;
+}
+
+impl Store {
+  /// Regenerates the indices of a store that is not open elsewhere,
+  /// reporting each stage to `progress`.
+  pub fn reindex<F: FnMut(Progress)>(config: &Config, mut progress: F) -> Result<Reindexed, Error> {
+    let mut db = Self::load(config)?;
+    db.resume()?;
+    db.regenerate(&mut progress)
+  }
+
+  /// Regenerates the id, search and file indices from the records.
+  pub fn regenerate(&mut self, progress: &mut dyn FnMut(Progress)) -> Result<Reindexed, Error> {
+    self.journaled(Intent::default(), |db| {
+      db.rebuild(progress)?;
+      checkpoint("reindex:rebuilt");
+      let keys = Self::keys(&db.env, &db.db)?;
+      let total = keys.len() as u64;
+      let txn = WriteTransaction::new(db.env.clone())?;
+      let mut reindexed = Reindexed {
+        articles: 0,
+        attachments: 0,
+      };
+      {
+        let blobs = db.blobs(&txn);
+        let mut access = txn.access();
+        access.clear_db(&db.files)?;
+        for key in keys {
+          if reindexed.articles.is_multiple_of(Progress::STEP) {
+            progress(Progress::new("files", reindexed.articles, total));
+          }
+          reindexed.articles += 1;
+          let content = match access.get::<u64, [u8]>(&db.db, &key).to_opt()? {
+            Some(bytes) => match ArticleContent::decode(bytes) {
+              Ok(content) => content,
+              Err(_) => continue,
+            },
+            None => continue,
+          };
+          for attachment in content.attachments.iter() {
+            blobs.add(&mut access, &attachment.hash, key, attachment.id)?;
+            reindexed.attachments += 1;
+          }
+        }
+        progress(Progress::new("files", total, total));
+      }
+      txn.commit()?;
+      Ok(reindexed)
+    })
+  }
+}
+
+impl Handler<Reindex> for Db {
+  type Result = Result<Reindexed, Error>;
+
+  fn handle(&mut self, _: Reindex, _: &mut Self::Context) -> Self::Result {
+    let reindexed = self.store()?.regenerate(&mut |p| {
+      info!(
+        "Db[Reindex] {}: {} of {} articles.",
+        p.stage, p.done, p.total
+      )
+    })?;
+    info!(
+      "Db[Reindex] {} articles and {} attachments are reindexed.",
+      reindexed.articles, reindexed.attachments
+    );
+    Ok(reindexed)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::super::super::hash::Hash;
+  use super::super::super::tests::{add, check, crash, load};
+  use super::*;
+
+  #[test]
+  fn test_reindex() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let mut db = load(&dir)?;
+    let a = add(&mut db, "Literate Programming", b"%PDF-1.4 literate")?;
+    let b = add(&mut db, "Concrete Mathematics", b"%PDF-1.4 concrete")?;
+    db.id.del(a.id())?;
+    db.search.del(b.id())?;
+    {
+      let txn = WriteTransaction::new(db.env.clone())?;
+      {
+        let blobs = db.blobs(&txn);
+        let mut access = txn.access();
+        let lost = &a.content().attachments[0];
+        blobs.del(&mut access, &lost.hash, a.id(), lost.id)?;
+        blobs.add(&mut access, &Hash([7; 32]), 42, 0)?;
+      }
+      txn.commit()?;
+    }
+    assert!(!db.check()?.is_empty());
+
+    let mut stages = Vec::new();
+    let reindexed = db.regenerate(&mut |p| stages.push(p))?;
+    assert_eq!(
+      reindexed,
+      Reindexed {
+        articles: 2,
+        attachments: 2
+      }
+    );
+    assert_eq!(
+      stages,
+      vec![
+        Progress::new("ids", 2, 2),
+        Progress::new("search", 0, 2),
+        Progress::new("search", 2, 2),
+        Progress::new("files", 0, 2),
+        Progress::new("files", 2, 2),
+      ]
+    );
+    check(&db)?;
+    assert!(db.check()?.is_empty());
+
+    db.search.del(a.id())?;
+    crash("reindex:rebuilt", || db.regenerate(&mut |_| {}));
+    db.search.del(a.id())?;
+    drop(db);
+    let db = load(&dir)?;
+    assert_eq!(check(&db)?.len(), 2);
+    Ok(())
+  }
+}
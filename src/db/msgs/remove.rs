@@ -1,10 +1,12 @@
 use actix::{Handler, Message};
 use failure::Error;
-use lmdb::WriteTransaction;
+use lmdb::{LmdbResultExt, WriteTransaction};
 
-use super::super::article::{Article, ArticleContent};
-use super::super::Db;
+use super::super::article::Article;
+use super::super::journal::{checkpoint, Intent};
+use super::super::{Db, Store};
 
+/// Removes an article with its files and memberships.
 pub struct Remove(u64);
 
 impl Remove {
@@ -16,24 +18,41 @@ impl Message for Remove {
   type Result = Result<Article, Error>;
 }
 
+impl Store {
+  pub fn remove(&mut self, id: u64) -> Result<Article, Error> {
+    let content = self.article(id)?.content().clone();
+    let intent = content
+      .attachments
+      .iter()
+      .fold(Intent::ids(vec![id]), |intent, a| {
+        intent.blob(a.hash, a.media_type)
+      });
+    self.journaled(intent, |db| {
+      let txn = WriteTransaction::new(db.env.clone())?;
+      {
+        let blobs = db.blobs(&txn);
+        let mut access = txn.access();
+        access.del_key(&db.db, &id)?;
+        access.del_key(&db.text, &id).to_opt()?;
+        db.collections(&txn).forget(&mut access, id)?;
+        for attachment in content.attachments.iter() {
+          blobs.del(&mut access, &attachment.hash, id, attachment.id)?;
+        }
+      }
+      db.id.del(id)?;
+      checkpoint("remove:unindexed");
+      db.search.del(id)?;
+      txn.commit()?;
+      checkpoint("remove:committed");
+      Ok(Article::new(db.content_dir(), id, content))
+    })
+  }
+}
+
 impl Handler<Remove> for Db {
   type Result = Result<Article, Error>;
 
   fn handle(&mut self, msg: Remove, _: &mut Self::Context) -> Self::Result {
-    let key = msg.0;
-    let txn = WriteTransaction::new(self.env.clone())?;
-    let content = {
-      let mut access = txn.access();
-      let content = (access.get(&self.db, &key)? as &ArticleContent).to_owned();
-      access.del_key(&self.db, &key)?;
-      content
-    };
-    self.id.del(key)?;
-    if let Err(e) = self.search.del(key) {
-      self.id.add(key)?;
-      return Err(e);
-    }
-    txn.commit()?;
-    Ok(Article::new(self.content_path(key), key, content))
+    self.store()?.remove(msg.0)
   }
 }
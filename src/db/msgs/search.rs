@@ -1,27 +1,97 @@
-use actix::{Handler, Message};
+use actix::{Handler, Message, ResponseFuture};
 use failure::Error;
+use lmdb::{ConstAccessor, LmdbResultExt, ReadTransaction};
 
-use super::super::article::Article;
-use super::super::Db;
+use super::super::article::{Article, ArticleContent};
+use super::super::collection::{Collections, Kind};
+use super::super::search::{Filter, Query, Source};
+use super::super::{Db, Reader, Store};
 
-pub struct Search(String);
+/// Finds the articles matching a [`Query`].
+pub struct Search(Query);
 
 impl Search {
-  pub fn new<S: AsRef<str>>(query: S) -> Self {
-    Self(query.as_ref().to_owned())
+  pub fn new(query: Query) -> Self {
+    Self(query)
   }
 }
 impl Message for Search {
   type Result = Result<Vec<Article>, Error>;
 }
 
+struct Stored<'a, 'env> {
+  db: &'a Store,
+  access: &'a ConstAccessor<'a>,
+  collections: Collections<'a, 'env>,
+}
+
+impl<'a, 'env> Source for Stored<'a, 'env> {
+  fn fetch(&mut self, key: u64) -> Result<Option<(ArticleContent, Option<String>)>, Error> {
+    let content = match self.access.get::<u64, [u8]>(&self.db.db, &key).to_opt()? {
+      Some(bytes) => ArticleContent::decode(bytes)?,
+      None => return Ok(None),
+    };
+    let text = self
+      .access
+      .get::<u64, str>(&self.db.text, &key)
+      .to_opt()?
+      .map(str::to_owned);
+    Ok(Some((content, text)))
+  }
+
+  fn filter(&mut self, filter: &Filter) -> Result<Vec<u64>, Error> {
+    match filter {
+      Filter::Tag(name) => self.collections.resolve(self.access, Kind::Tag, name),
+      Filter::Collection(name) => self
+        .collections
+        .resolve(self.access, Kind::Collection, name),
+    }
+  }
+}
+
+impl Store {
+  pub fn search(&self, query: &Query) -> Result<Vec<Article>, Error> {
+    let txn = ReadTransaction::new(self.env.clone())?;
+    let access = txn.access();
+    let keys = self.search.search(
+      query,
+      &mut Stored {
+        db: self,
+        access: &access,
+        collections: self.collections(&txn),
+      },
+    )?;
+
+    let mut articles = Vec::with_capacity(keys.len());
+    for key in keys {
+      match access.get::<u64, [u8]>(&self.db, &key).to_opt()? {
+        Some(bytes) => articles.push(Article::new(
+          self.content_dir(),
+          key,
+          ArticleContent::decode(bytes)?,
+        )),
+        None => warn!(
+          "Store The article id={} is found by a search but not in the database.",
+          key
+        ),
+      }
+    }
+    Ok(articles)
+  }
+}
+
 impl Handler<Search> for Db {
-  type Result = Result<Vec<Article>, Error>;
+  type Result = ResponseFuture<Vec<Article>, Error>;
 
   fn handle(&mut self, msg: Search, _: &mut Self::Context) -> Self::Result {
-    let query = msg.0;
-    self.search.search(query)?;
+    self.read(msg)
+  }
+}
+
+impl Handler<Search> for Reader {
+  type Result = Result<Vec<Article>, Error>;
 
-    Ok(Vec::new())
+  fn handle(&mut self, msg: Search, _: &mut Self::Context) -> Self::Result {
+    self.store()?.search(&msg.0)
   }
 }
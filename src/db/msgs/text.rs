@@ -0,0 +1,73 @@
+use actix::{Handler, Message};
+use failure::Error;
+use lmdb::{put, WriteTransaction};
+
+use super::super::article::{Article, ArticleContent, MediaType};
+use super::super::journal::{checkpoint, Intent};
+use super::super::{Db, Store};
+use crate::pdf;
+
+/// Replaces the full text indexed for an article.
+pub struct SetText {
+  id: u64,
+  text: String,
+}
+
+impl SetText {
+  pub fn new<S: Into<String>>(id: u64, text: S) -> Self {
+    Self {
+      id,
+      text: text.into(),
+    }
+  }
+}
+impl Message for SetText {
+  type Result = Result<(), Error>;
+}
+
+impl Store {
+  pub fn set_text(&mut self, id: u64, text: String) -> Result<(), Error> {
+    self.journaled(Intent::ids(vec![id]), |db| {
+      let txn = WriteTransaction::new(db.env.clone())?;
+      let content = {
+        let mut access = txn.access();
+        let content = ArticleContent::decode(access.get::<u64, [u8]>(&db.db, &id)?)?;
+        access.put(&db.text, &id, text.as_str(), put::Flags::empty())?;
+        content
+      };
+      db.search.add(id, &content, Some(&text))?;
+      checkpoint("text:indexed");
+      txn.commit()?;
+      Ok(())
+    })?;
+    info!(
+      "Store The text of id={} is indexed ({} bytes).",
+      id,
+      text.len()
+    );
+    Ok(())
+  }
+
+  /// Indexes the text of the primary attachment, or clears the text when the
+  /// article has no PDF to read it from.
+  pub fn index_text(&mut self, article: &Article) -> Result<(), Error> {
+    let text = match article.content().primary() {
+      Some(attachment) if attachment.media_type == MediaType::Pdf => {
+        pdf::extract_text(article.attachment_path(attachment)).unwrap_or_else(|e| {
+          error!("Failed to extract the text of id={}: {}", article.id(), e);
+          String::new()
+        })
+      }
+      _ => String::new(),
+    };
+    self.set_text(article.id(), text)
+  }
+}
+
+impl Handler<SetText> for Db {
+  type Result = Result<(), Error>;
+
+  fn handle(&mut self, msg: SetText, _: &mut Self::Context) -> Self::Result {
+    self.store()?.set_text(msg.id, msg.text)
+  }
+}
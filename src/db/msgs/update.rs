@@ -0,0 +1,55 @@
+use actix::{Handler, Message};
+use failure::Error;
+use lmdb::{put, LmdbResultExt, WriteTransaction};
+
+use super::super::article::{Article, ArticleContent, ArticlePatch};
+use super::super::journal::{checkpoint, Intent};
+use super::super::{Db, Store};
+
+/// Applies a partial update to the metadata of an article.
+pub struct Update {
+  id: u64,
+  patch: ArticlePatch,
+}
+
+impl Update {
+  pub fn new(id: u64, patch: ArticlePatch) -> Self {
+    Self { id, patch }
+  }
+}
+impl Message for Update {
+  type Result = Result<Article, Error>;
+}
+
+impl Store {
+  pub fn update(&mut self, id: u64, patch: ArticlePatch) -> Result<Article, Error> {
+    let article = self.journaled(Intent::ids(vec![id]), |db| {
+      let txn = WriteTransaction::new(db.env.clone())?;
+      let (content, text) = {
+        let mut access = txn.access();
+        let prev = ArticleContent::decode(access.get::<u64, [u8]>(&db.db, &id)?)?;
+        let content = patch.apply(prev).normalized()?;
+        let text = access
+          .get::<u64, str>(&db.text, &id)
+          .to_opt()?
+          .map(str::to_owned);
+        access.put(&db.db, &id, &content.encode()[..], put::Flags::empty())?;
+        (content, text)
+      };
+      db.search.add(id, &content, text.as_deref())?;
+      checkpoint("update:indexed");
+      txn.commit()?;
+      Ok(Article::new(db.content_dir(), id, content))
+    })?;
+    info!("Store The article id={} is updated.", id);
+    Ok(article)
+  }
+}
+
+impl Handler<Update> for Db {
+  type Result = Result<Article, Error>;
+
+  fn handle(&mut self, msg: Update, _: &mut Self::Context) -> Self::Result {
+    self.store()?.update(msg.id, msg.patch)
+  }
+}
@@ -0,0 +1,521 @@
+mod query;
+mod token;
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::Path;
+
+use failure::Error;
+
+use super::ArticleContent;
+use crate::collection::{BTree, RBTree, Violation};
+
+pub use self::query::*;
+pub(super) use self::token::*;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Field {
+  Title = 1,
+  Author = 2,
+  Body = 3,
+  Venue = 4,
+  Abstract = 5,
+  Keyword = 6,
+  Note = 7,
+  Year = 8,
+  Doi = 9,
+  Arxiv = 10,
+}
+
+impl Field {
+  const ALL: [Field; 10] = [
+    Field::Title,
+    Field::Author,
+    Field::Body,
+    Field::Venue,
+    Field::Abstract,
+    Field::Keyword,
+    Field::Note,
+    Field::Year,
+    Field::Doi,
+    Field::Arxiv,
+  ];
+
+  pub fn from_name<S: AsRef<str>>(name: S) -> Option<Self> {
+    match name.as_ref() {
+      "title" => Some(Field::Title),
+      "author" => Some(Field::Author),
+      "body" | "text" => Some(Field::Body),
+      "venue" | "journal" => Some(Field::Venue),
+      "abstract" => Some(Field::Abstract),
+      "keyword" => Some(Field::Keyword),
+      "note" => Some(Field::Note),
+      "year" => Some(Field::Year),
+      "doi" => Some(Field::Doi),
+      "arxiv" => Some(Field::Arxiv),
+      _ => None,
+    }
+  }
+
+  fn scope(scope: Option<Field>) -> impl Iterator<Item = Field> {
+    Field::ALL
+      .iter()
+      .cloned()
+      .filter(move |&field| scope.is_none_or(|scope| scope == field))
+  }
+
+  fn weight(self) -> f64 {
+    match self {
+      Field::Title => 2.0,
+      Field::Author => 1.5,
+      Field::Body => 1.0,
+      Field::Venue => 1.0,
+      Field::Abstract => 1.2,
+      Field::Keyword => 1.5,
+      Field::Note => 0.8,
+      Field::Year => 1.0,
+      Field::Doi => 2.0,
+      Field::Arxiv => 2.0,
+    }
+  }
+
+  fn term<S: AsRef<str>>(self, token: S) -> u64 {
+    if self == Field::Year {
+      if let Ok(year) = token.as_ref().parse() {
+        return Field::year(year);
+      }
+    }
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in std::iter::once(self as u8).chain(token.as_ref().bytes()) {
+      hash ^= u64::from(b);
+      hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+  }
+
+  /// The term of a year. Unlike the other terms it is not hashed, so the
+  /// postings of a range of years are adjacent.
+  fn year(year: u16) -> u64 {
+    (Field::Year as u64) << 56 | u64::from(year)
+  }
+}
+
+pub fn texts<'a>(
+  content: &'a ArticleContent,
+  text: Option<&'a str>,
+  field: Field,
+) -> Vec<Cow<'a, str>> {
+  let opt = |val: &'a Option<String>| val.iter().map(|v| Cow::from(v.as_str())).collect();
+  match field {
+    Field::Title => vec![Cow::from(content.title.as_str())],
+    Field::Author => content
+      .authors
+      .iter()
+      .map(|a| Cow::from(a.as_str()))
+      .collect(),
+    Field::Body => text.into_iter().map(Cow::from).collect(),
+    Field::Venue => opt(&content.venue),
+    Field::Abstract => opt(&content.abstract_),
+    Field::Keyword => content
+      .keywords
+      .iter()
+      .map(|k| Cow::from(k.as_str()))
+      .collect(),
+    Field::Note => opt(&content.note),
+    Field::Year => content
+      .year
+      .iter()
+      .map(|y| Cow::from(y.to_string()))
+      .collect(),
+    Field::Doi => opt(&content.doi),
+    Field::Arxiv => opt(&content.arxiv),
+  }
+}
+
+type Hits = HashMap<u64, (usize, f64)>;
+
+pub trait Source {
+  fn fetch(&mut self, id: u64) -> Result<Option<(ArticleContent, Option<String>)>, Error>;
+  fn filter(&mut self, filter: &Filter) -> Result<Vec<u64>, Error>;
+}
+
+pub struct SearchIndex {
+  /// The frequency of each term in each article, keyed by `(term, id)`.
+  postings: BTree<(u64, u64), u64>,
+  /// The same entries keyed by `(id, term)`, to find the postings of an
+  /// article.
+  terms: BTree<(u64, u64), u64>,
+  docs: RBTree<u64>,
+}
+
+impl SearchIndex {
+  const POSTINGS_PATH: &'static str = "postings";
+  const TERMS_PATH: &'static str = "terms";
+  const DOCS_PATH: &'static str = "docs";
+
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+    let path = path.as_ref();
+    Ok(Self {
+      postings: BTree::open(path.join(Self::POSTINGS_PATH))?,
+      terms: BTree::open(path.join(Self::TERMS_PATH))?,
+      docs: RBTree::open(path.join(Self::DOCS_PATH))?,
+    })
+  }
+
+  pub fn clear(&mut self) -> Result<(), Error> {
+    self.postings.clear()?;
+    self.terms.clear()?;
+    self.docs.clear()
+  }
+
+  pub fn flush(&self) -> Result<(), Error> {
+    self.postings.flush()?;
+    self.terms.flush()?;
+    self.docs.flush()
+  }
+
+  pub fn violations(&self) -> Vec<(&'static str, Violation)> {
+    let tagged =
+      |name: &'static str, found: Vec<Violation>| found.into_iter().map(move |v| (name, v));
+    tagged(Self::POSTINGS_PATH, self.postings.violations())
+      .chain(tagged(Self::TERMS_PATH, self.terms.violations()))
+      .chain(tagged(Self::DOCS_PATH, self.docs.violations()))
+      .collect()
+  }
+
+  pub fn docs(&self) -> impl Iterator<Item = u64> + '_ {
+    self.docs.iter()
+  }
+
+  fn count<S: AsRef<str>>(freqs: &mut HashMap<u64, u64>, field: Field, text: S) {
+    for token in tokenize(text) {
+      *freqs.entry(field.term(token)).or_insert(0) += 1;
+    }
+  }
+
+  /// The ids and frequencies of the articles containing `term`.
+  fn postings(&self, term: u64) -> impl Iterator<Item = (u64, u64)> + '_ {
+    self
+      .postings
+      .range((term, 0)..=(term, u64::MAX))
+      .map(|((_, id), freq)| (id, freq))
+  }
+
+  pub fn add(
+    &mut self,
+    key: u64,
+    content: &ArticleContent,
+    text: Option<&str>,
+  ) -> Result<(), Error> {
+    if self.docs.contains(&key) {
+      self.del(key)?;
+    }
+    let mut freqs = HashMap::new();
+    for &field in Field::ALL.iter() {
+      for val in texts(content, text, field) {
+        Self::count(&mut freqs, field, val);
+      }
+    }
+    self.docs.add(key)?;
+    for (term, freq) in freqs {
+      self.postings.insert((term, key), freq)?;
+      self.terms.insert((key, term), freq)?;
+    }
+    Ok(())
+  }
+
+  pub fn del(&mut self, key: u64) -> Result<(), Error> {
+    let terms: Vec<u64> = self
+      .terms
+      .range((key, 0)..=(key, u64::MAX))
+      .map(|((_, term), _)| term)
+      .collect();
+    for term in terms {
+      self.postings.remove(&(term, key))?;
+      self.terms.remove(&(key, term))?;
+    }
+    self.docs.del(key)
+  }
+
+  fn scores(&self, field: Field, word: &str) -> HashMap<u64, f64> {
+    self.weigh(field, self.postings(field.term(word)).collect())
+  }
+
+  fn weigh(&self, field: Field, postings: Vec<(u64, u64)>) -> HashMap<u64, f64> {
+    let total = self.docs.len() as f64;
+    let idf = (1.0 + total / postings.len().max(1) as f64).ln();
+    postings
+      .into_iter()
+      .map(|(id, freq)| (id, field.weight() * (1.0 + (freq as f64).ln()) * idf))
+      .collect()
+  }
+
+  fn all(&self) -> Hits {
+    self.docs.iter().map(|id| (id, (0, 0.0))).collect()
+  }
+
+  fn phrase(
+    &self,
+    scope: Option<Field>,
+    words: &[String],
+    source: &mut dyn Source,
+  ) -> Result<Hits, Error> {
+    let mut candidates: HashMap<u64, Vec<(Field, f64)>> = HashMap::new();
+    for field in Field::scope(scope) {
+      let mut matched: Option<HashMap<u64, f64>> = None;
+      for word in words {
+        let scores = self.scores(field, word);
+        matched = Some(match matched {
+          None => scores,
+          Some(matched) => matched
+            .into_iter()
+            .filter_map(|(id, score)| scores.get(&id).map(|s| (id, score + s)))
+            .collect(),
+        });
+      }
+      for (id, score) in matched.unwrap_or_default() {
+        candidates.entry(id).or_default().push((field, score));
+      }
+    }
+    let mut hits = Hits::new();
+    for (id, fields) in candidates {
+      let (content, text) = match source.fetch(id)? {
+        Some(doc) => doc,
+        None => continue,
+      };
+      for (field, score) in fields {
+        let found = texts(&content, text.as_deref(), field)
+          .iter()
+          .any(|t| tokenize(t).windows(words.len()).any(|w| w == words));
+        if found {
+          let entry = hits.entry(id).or_insert((words.len(), 0.0));
+          entry.1 += score;
+        }
+      }
+    }
+    Ok(hits)
+  }
+
+  fn eval(&self, query: &Query, source: &mut dyn Source) -> Result<Hits, Error> {
+    let mut hits = Hits::new();
+    match query {
+      Query::Term(scope, word) => {
+        for field in Field::scope(*scope) {
+          for (id, score) in self.scores(field, word) {
+            hits.entry(id).or_insert((1, 0.0)).1 += score;
+          }
+        }
+      }
+      Query::Phrase(scope, words) => hits = self.phrase(*scope, words, source)?,
+      Query::Year(lo, hi) => {
+        let lo = (Field::year(*lo), 0);
+        let hi = (Field::year(*hi), u64::MAX);
+        let postings: Vec<((u64, u64), u64)> = self.postings.range(lo..=hi).collect();
+        for year in postings.chunk_by(|((a, _), _), ((b, _), _)| a == b) {
+          let year = year.iter().map(|&((_, id), freq)| (id, freq)).collect();
+          for (id, score) in self.weigh(Field::Year, year) {
+            hits.entry(id).or_insert((1, 0.0)).1 += score;
+          }
+        }
+      }
+      Query::Filter(filter) => {
+        for id in source.filter(filter)? {
+          if self.docs.contains(&id) {
+            hits.insert(id, (0, 0.0));
+          }
+        }
+      }
+      Query::Not(query) => {
+        hits = self.all();
+        for id in self.eval(query, source)?.keys() {
+          hits.remove(id);
+        }
+      }
+      Query::And(queries) => {
+        if queries.is_empty() {
+          return Ok(hits);
+        }
+        let mut matched: Option<Hits> = None;
+        let mut excluded = Vec::new();
+        for query in queries {
+          if let Query::Not(query) = query {
+            excluded.push(query);
+            continue;
+          }
+          let next = self.eval(query, source)?;
+          matched = Some(match matched {
+            None => next,
+            Some(matched) => matched
+              .into_iter()
+              .filter_map(|(id, (n, score))| next.get(&id).map(|(m, s)| (id, (n + m, score + s))))
+              .collect(),
+          });
+        }
+        hits = matched.unwrap_or_else(|| self.all());
+        for query in excluded {
+          for id in self.eval(query, source)?.keys() {
+            hits.remove(id);
+          }
+        }
+      }
+      Query::Or(queries) => {
+        for query in queries {
+          for (id, (n, score)) in self.eval(query, source)? {
+            let entry = hits.entry(id).or_insert((0, 0.0));
+            entry.0 += n;
+            entry.1 += score;
+          }
+        }
+      }
+    }
+    Ok(hits)
+  }
+
+  pub fn search(&self, query: &Query, source: &mut dyn Source) -> Result<Vec<u64>, Error> {
+    let mut ranked: Vec<(u64, (usize, f64))> = self.eval(query, source)?.into_iter().collect();
+    ranked.sort_by(|(a, (an, ascore)), (b, (bn, bscore))| {
+      bn.cmp(an)
+        .then(bscore.partial_cmp(ascore).unwrap_or(Ordering::Equal))
+        .then(a.cmp(b))
+    });
+    Ok(ranked.into_iter().map(|(id, _)| id).collect())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempfile::tempdir;
+
+  struct Library {
+    index: SearchIndex,
+    docs: HashMap<u64, (ArticleContent, Option<String>)>,
+  }
+
+  struct Docs<'a>(&'a HashMap<u64, (ArticleContent, Option<String>)>);
+
+  impl<'a> Source for Docs<'a> {
+    fn fetch(&mut self, id: u64) -> Result<Option<(ArticleContent, Option<String>)>, Error> {
+      Ok(self.0.get(&id).cloned())
+    }
+
+    fn filter(&mut self, filter: &Filter) -> Result<Vec<u64>, Error> {
+      Ok(match filter {
+        Filter::Tag(tag) if tag == "classic" => vec![0, 2],
+        _ => Vec::new(),
+      })
+    }
+  }
+
+  impl Library {
+    fn add(&mut self, key: u64, content: &ArticleContent, text: Option<&str>) -> Result<(), Error> {
+      self.index.add(key, content, text)?;
+      self
+        .docs
+        .insert(key, (content.clone(), text.map(str::to_owned)));
+      Ok(())
+    }
+
+    fn del(&mut self, key: u64) -> Result<(), Error> {
+      self.docs.remove(&key);
+      self.index.del(key)
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<u64>, Error> {
+      self
+        .index
+        .search(&Query::parse(query)?, &mut Docs(&self.docs))
+    }
+  }
+
+  #[test]
+  fn test_add_search_del() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let mut index = Library {
+      index: SearchIndex::open(dir.path())?,
+      docs: HashMap::new(),
+    };
+    index.add(
+      0,
+      &ArticleContent::new("Literate Programming", ["Donald E. Knuth"])?,
+      Some("The WEB system combines TeX with a programming language."),
+    )?;
+    index.add(
+      1,
+      &ArticleContent::new("Structured Programming with go to Statements", ["Knuth"])?,
+      None,
+    )?;
+    index.add(
+      2,
+      &ArticleContent::new("Go To Statement Considered Harmful", ["Dijkstra"])?,
+      Some("For a number of years I have been familiar with the observation that the quality of programmers is a decreasing function of the density of go to statements."),
+    )?;
+
+    assert_eq!(index.search("literate programming")?, vec![0]);
+    assert_eq!(index.search("knuth")?.len(), 2);
+    assert_eq!(index.search("go to")?, vec![2, 1]);
+    assert_eq!(index.search("web system")?, vec![0]);
+    assert_eq!(index.search("programming")?, vec![0, 1]);
+    assert_eq!(index.search("")?, Vec::<u64>::new());
+    assert_eq!(index.search("title:knuth")?, Vec::<u64>::new());
+    assert_eq!(index.search("author:knuth title:programming")?, vec![0, 1]);
+    assert_eq!(index.search("text:programming")?, vec![0]);
+    assert_eq!(index.search("programming OR dijkstra")?, vec![0, 2, 1]);
+    assert_eq!(index.search("programming -knuth")?, Vec::<u64>::new());
+    assert_eq!(index.search("-programming")?, vec![2]);
+    assert_eq!(index.search("go to -(author:dijkstra)")?, vec![1]);
+    assert_eq!(index.search(r#""go to statements""#)?, vec![1, 2]);
+    assert_eq!(index.search(r#"title:"go to statements""#)?, vec![1]);
+    assert_eq!(index.search(r#"text:"go to statements""#)?, vec![2]);
+    assert_eq!(index.search(r#""statements go to""#)?, Vec::<u64>::new());
+    assert_eq!(index.search("tag:classic")?, vec![0, 2]);
+    assert_eq!(index.search("tag:classic programming")?, vec![0]);
+    assert_eq!(index.search("-tag:classic")?, vec![1]);
+    assert_eq!(index.search("tag:unknown")?, Vec::<u64>::new());
+
+    index.add(
+      3,
+      &ArticleContent {
+        year: Some(1968),
+        venue: Some("Communications of the ACM".to_owned()),
+        doi: Some("10.1145/362929.362947".to_owned()),
+        keywords: vec!["goto".to_owned()],
+        ..ArticleContent::new("A Case against the GO TO Statement", ["Dijkstra"])?
+      },
+      None,
+    )?;
+    assert_eq!(index.search("year:1968")?, vec![3]);
+    assert_eq!(index.search("year:1960..1970")?, vec![3]);
+    assert_eq!(index.search("year:1969..")?, Vec::<u64>::new());
+    assert_eq!(index.search("dijkstra year:..1968")?, vec![3]);
+    assert_eq!(index.search("1968")?, vec![3]);
+    assert_eq!(index.search("venue:acm")?, vec![3]);
+    assert_eq!(index.search("doi:10.1145/362929.362947")?, vec![3]);
+    assert_eq!(index.search("keyword:goto")?, vec![3]);
+    assert_eq!(index.search("title:1968")?, Vec::<u64>::new());
+    index.add(
+      4,
+      &ArticleContent {
+        year: Some(1974),
+        ..ArticleContent::new("Structured Programming with go to Statements", ["Knuth"])?
+      },
+      None,
+    )?;
+    assert_eq!(index.search("year:1960..1980")?.len(), 2);
+    assert_eq!(index.search("year:1969..1980")?, vec![4]);
+    index.del(4)?;
+    index.del(3)?;
+
+    index.del(1)?;
+    assert_eq!(index.search("programming")?, vec![0]);
+    index.add(
+      0,
+      &ArticleContent::new("Literate Programming", ["Knuth"])?,
+      None,
+    )?;
+    assert_eq!(index.search("donald")?, Vec::<u64>::new());
+    assert_eq!(index.search("web")?, Vec::<u64>::new());
+    Ok(())
+  }
+}
@@ -0,0 +1,390 @@
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use super::token::tokenize;
+use super::Field;
+
+const MAX_YEAR: u16 = 9999;
+
+#[derive(Debug, Fail, PartialEq)]
+pub enum QueryError {
+  #[fail(display = "The phrase starting at {} is not closed with '\"'.", _0)]
+  UnterminatedPhrase(usize),
+  #[fail(display = "The '(' at {} is not closed.", _0)]
+  UnclosedParen(usize),
+  #[fail(display = "The ')' at {} has no matching '('.", _0)]
+  UnmatchedParen(usize),
+  #[fail(display = "'OR' at {} needs a query on both sides.", _0)]
+  DanglingOr(usize),
+  #[fail(display = "'-' at {} is not followed by a query.", _0)]
+  EmptyNegation(usize),
+  #[fail(display = "'{}:' at {} is not followed by a value.", _0, _1)]
+  MissingValue(String, usize),
+  #[fail(
+    display = "'{}' is not a year or a year range such as 2010, 2010..2015, 2010.. or ..2015.",
+    _0
+  )]
+  InvalidYear(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+  Tag(String),
+  Collection(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+  Term(Option<Field>, String),
+  Phrase(Option<Field>, Vec<String>),
+  Year(u16, u16),
+  Filter(Filter),
+  Not(Box<Query>),
+  And(Vec<Query>),
+  Or(Vec<Query>),
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+  LParen,
+  RParen,
+  Minus,
+  Or,
+  Atom {
+    field: Option<String>,
+    value: String,
+  },
+}
+
+fn is_delimiter(c: char) -> bool {
+  c.is_whitespace() || c == '(' || c == ')' || c == '"'
+}
+
+/// Whether `name` followed by ':' restricts a term to a field. Any other
+/// word followed by ':' is searched as it is.
+fn is_field(name: &str) -> bool {
+  let lower = name.to_lowercase();
+  lower == "tag" || lower == "collection" || lower == "in" || Field::from_name(&lower).is_some()
+}
+
+fn lex(query: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+  let mut tokens = Vec::new();
+  let mut chars = query.char_indices().peekable();
+  while let Some(&(pos, c)) = chars.peek() {
+    if c.is_whitespace() {
+      chars.next();
+    } else if c == '(' {
+      chars.next();
+      tokens.push((pos, Token::LParen));
+    } else if c == ')' {
+      chars.next();
+      tokens.push((pos, Token::RParen));
+    } else if c == '-' {
+      chars.next();
+      match chars.peek() {
+        Some(&(_, c)) if !c.is_whitespace() && c != ')' => tokens.push((pos, Token::Minus)),
+        _ => return Err(QueryError::EmptyNegation(pos)),
+      }
+    } else {
+      let mut field = None;
+      let mut word = String::new();
+      while let Some(&(_, c)) = chars.peek() {
+        if is_delimiter(c) {
+          break;
+        }
+        chars.next();
+        let prefix = c == ':'
+          && field.is_none()
+          && is_field(&word)
+          && chars.peek().is_some_and(|&(_, c)| !c.is_whitespace());
+        if prefix {
+          field = Some(std::mem::take(&mut word));
+        } else {
+          word.push(c);
+        }
+      }
+      if word.is_empty() {
+        if let Some(&(start, '"')) = chars.peek() {
+          chars.next();
+          let mut closed = false;
+          for (_, c) in chars.by_ref() {
+            if c == '"' {
+              closed = true;
+              break;
+            }
+            word.push(c);
+          }
+          if !closed {
+            return Err(QueryError::UnterminatedPhrase(start));
+          }
+        }
+        if let (Some(field), true) = (&field, word.trim().is_empty()) {
+          return Err(QueryError::MissingValue(field.clone(), pos));
+        }
+      }
+      if field.is_none() && word == "OR" {
+        tokens.push((pos, Token::Or));
+      } else {
+        tokens.push((pos, Token::Atom { field, value: word }));
+      }
+    }
+  }
+  Ok(tokens)
+}
+
+fn parse_year(val: &str) -> Option<u16> {
+  val.parse().ok().filter(|&y| y > 0 && y <= MAX_YEAR)
+}
+
+fn year_range(val: &str) -> Result<Query, QueryError> {
+  let invalid = || QueryError::InvalidYear(val.to_owned());
+  let (lo, hi) = match val.find("..") {
+    Some(i) => {
+      let (lo, hi) = (&val[..i], &val[i + 2..]);
+      if lo.is_empty() && hi.is_empty() {
+        return Err(invalid());
+      }
+      let lo = if lo.is_empty() {
+        1
+      } else {
+        parse_year(lo).ok_or_else(invalid)?
+      };
+      let hi = if hi.is_empty() {
+        MAX_YEAR
+      } else {
+        parse_year(hi).ok_or_else(invalid)?
+      };
+      (lo, hi)
+    }
+    None => {
+      let year = parse_year(val).ok_or_else(invalid)?;
+      (year, year)
+    }
+  };
+  if lo > hi {
+    return Err(invalid());
+  }
+  Ok(Query::Year(lo, hi))
+}
+
+fn atom(field: Option<String>, value: String) -> Result<Option<Query>, QueryError> {
+  let field = match field {
+    Some(name) => match name.to_lowercase().as_str() {
+      "tag" => return Ok(Some(Query::Filter(Filter::Tag(value)))),
+      "collection" | "in" => return Ok(Some(Query::Filter(Filter::Collection(value)))),
+      lower => Field::from_name(lower),
+    },
+    None => None,
+  };
+  if field == Some(Field::Year) {
+    return year_range(value.trim()).map(Some);
+  }
+  let mut words = tokenize(value);
+  Ok(match words.len() {
+    0 => None,
+    1 => Some(Query::Term(field, words.remove(0))),
+    _ => Some(Query::Phrase(field, words)),
+  })
+}
+
+struct Parser {
+  tokens: Peekable<IntoIter<(usize, Token)>>,
+  end: usize,
+}
+
+impl Parser {
+  fn peek(&mut self) -> Option<&Token> {
+    self.tokens.peek().map(|t| &t.1)
+  }
+
+  fn offset(&mut self) -> usize {
+    self.tokens.peek().map_or(self.end, |t| t.0)
+  }
+
+  fn next(&mut self) -> Option<(usize, Token)> {
+    self.tokens.next()
+  }
+
+  fn or(&mut self) -> Result<Query, QueryError> {
+    let mut alts = vec![self.and()?];
+    while self.peek() == Some(&Token::Or) {
+      let pos = self.offset();
+      self.next();
+      let alt = self.and()?;
+      if alts.last() == Some(&Query::And(Vec::new())) || alt == Query::And(Vec::new()) {
+        return Err(QueryError::DanglingOr(pos));
+      }
+      alts.push(alt);
+    }
+    Ok(if alts.len() == 1 {
+      alts.remove(0)
+    } else {
+      Query::Or(alts)
+    })
+  }
+
+  fn and(&mut self) -> Result<Query, QueryError> {
+    let mut items = Vec::new();
+    loop {
+      match self.peek() {
+        None | Some(Token::Or) | Some(Token::RParen) => break,
+        _ => {
+          if let Some(item) = self.unary()? {
+            items.push(item);
+          }
+        }
+      }
+    }
+    Ok(if items.len() == 1 {
+      items.remove(0)
+    } else {
+      Query::And(items)
+    })
+  }
+
+  fn unary(&mut self) -> Result<Option<Query>, QueryError> {
+    if self.peek() == Some(&Token::Minus) {
+      let pos = self.offset();
+      self.next();
+      return match self.primary()? {
+        Some(query) => Ok(Some(Query::Not(Box::new(query)))),
+        None => Err(QueryError::EmptyNegation(pos)),
+      };
+    }
+    self.primary()
+  }
+
+  fn primary(&mut self) -> Result<Option<Query>, QueryError> {
+    match self.next() {
+      Some((pos, Token::LParen)) => {
+        let query = self.or()?;
+        match self.next() {
+          Some((_, Token::RParen)) => Ok(Some(query).filter(|q| *q != Query::And(Vec::new()))),
+          _ => Err(QueryError::UnclosedParen(pos)),
+        }
+      }
+      Some((_, Token::Atom { field, value })) => atom(field, value),
+      Some((pos, Token::Minus)) => Err(QueryError::EmptyNegation(pos)),
+      Some((pos, _)) => Err(QueryError::UnmatchedParen(pos)),
+      None => Ok(None),
+    }
+  }
+}
+
+impl Query {
+  pub fn parse<S: AsRef<str>>(query: S) -> Result<Self, QueryError> {
+    let query = query.as_ref();
+    let mut parser = Parser {
+      tokens: lex(query)?.into_iter().peekable(),
+      end: query.len(),
+    };
+    let parsed = parser.or()?;
+    if let Some((pos, _)) = parser.next() {
+      return Err(QueryError::UnmatchedParen(pos));
+    }
+    Ok(parsed)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn term(field: Option<Field>, word: &str) -> Query {
+    Query::Term(field, word.to_owned())
+  }
+
+  #[test]
+  fn test_parse() {
+    assert_eq!(Query::parse(""), Ok(Query::And(vec![])));
+    assert_eq!(Query::parse("knuth"), Ok(term(None, "knuth")));
+    assert_eq!(
+      Query::parse(r#"author:knuth title:"literate programming" -draft year:2010..2015"#),
+      Ok(Query::And(vec![
+        term(Some(Field::Author), "knuth"),
+        Query::Phrase(
+          Some(Field::Title),
+          vec!["literate".to_owned(), "programming".to_owned()]
+        ),
+        Query::Not(Box::new(term(None, "draft"))),
+        Query::Year(2010, 2015),
+      ]))
+    );
+    assert_eq!(
+      Query::parse("tex OR (web -cweb) year:..1990"),
+      Ok(Query::Or(vec![
+        term(None, "tex"),
+        Query::And(vec![
+          Query::And(vec![
+            term(None, "web"),
+            Query::Not(Box::new(term(None, "cweb"))),
+          ]),
+          Query::Year(1, 1990),
+        ]),
+      ]))
+    );
+    assert_eq!(
+      Query::parse(r#"tag:toread in:"Thesis/Related work" knuth"#),
+      Ok(Query::And(vec![
+        Query::Filter(Filter::Tag("toread".to_owned())),
+        Query::Filter(Filter::Collection("Thesis/Related work".to_owned())),
+        term(None, "knuth"),
+      ]))
+    );
+    assert_eq!(
+      Query::parse("Go-To"),
+      Ok(Query::Phrase(None, vec!["go".to_owned(), "to".to_owned()]))
+    );
+  }
+
+  #[test]
+  fn test_parse_literal_colon() {
+    let phrase =
+      |words: &[&str]| Query::Phrase(None, words.iter().map(|&w| w.to_owned()).collect());
+    assert_eq!(
+      Query::parse("BERT: Pre-training"),
+      Ok(Query::And(vec![
+        term(None, "bert"),
+        phrase(&["pre", "training"])
+      ]))
+    );
+    assert_eq!(
+      Query::parse("http://example.com/paper"),
+      Ok(phrase(&["http", "example", "com", "paper"]))
+    );
+    assert_eq!(Query::parse("re:invent"), Ok(phrase(&["re", "invent"])));
+    assert_eq!(
+      Query::parse("Note: title:"),
+      Ok(Query::And(vec![term(None, "note"), term(None, "title")]))
+    );
+  }
+
+  #[test]
+  fn test_parse_error() {
+    assert_eq!(
+      Query::parse(r#"title:"literate"#),
+      Err(QueryError::UnterminatedPhrase(6))
+    );
+    assert_eq!(Query::parse("(tex"), Err(QueryError::UnclosedParen(0)));
+    assert_eq!(Query::parse("tex)"), Err(QueryError::UnmatchedParen(3)));
+    assert_eq!(Query::parse("OR tex"), Err(QueryError::DanglingOr(0)));
+    assert_eq!(Query::parse("tex OR"), Err(QueryError::DanglingOr(4)));
+    assert_eq!(Query::parse("tex -"), Err(QueryError::EmptyNegation(4)));
+    assert_eq!(
+      Query::parse(r#"title:"""#),
+      Err(QueryError::MissingValue("title".to_owned(), 0))
+    );
+    assert_eq!(
+      Query::parse("knuth tag:()"),
+      Err(QueryError::MissingValue("tag".to_owned(), 6))
+    );
+    assert_eq!(
+      Query::parse("year:2015..2010"),
+      Err(QueryError::InvalidYear("2015..2010".to_owned()))
+    );
+    assert_eq!(
+      Query::parse("year:recent"),
+      Err(QueryError::InvalidYear("recent".to_owned()))
+    );
+  }
+}
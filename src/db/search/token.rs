@@ -0,0 +1,72 @@
+fn is_cjk(c: char) -> bool {
+  matches!(
+    c as u32,
+    0x3040..=0x30ff
+      | 0x3400..=0x4dbf
+      | 0x4e00..=0x9fff
+      | 0xac00..=0xd7af
+      | 0xf900..=0xfaff
+      | 0xff66..=0xff9f
+  )
+}
+
+fn flush_word(word: &mut String, tokens: &mut Vec<String>) {
+  if !word.is_empty() {
+    tokens.push(std::mem::take(word));
+  }
+}
+
+fn flush_cjk(cjk: &mut Vec<char>, tokens: &mut Vec<String>) {
+  match cjk.len() {
+    0 => {}
+    1 => tokens.push(cjk[0].to_string()),
+    _ => {
+      for w in cjk.windows(2) {
+        tokens.push(w.iter().collect());
+      }
+    }
+  }
+  cjk.clear();
+}
+
+pub fn tokenize<S: AsRef<str>>(text: S) -> Vec<String> {
+  let mut tokens = Vec::new();
+  let mut word = String::new();
+  let mut cjk = Vec::new();
+  for c in text.as_ref().chars() {
+    if is_cjk(c) {
+      flush_word(&mut word, &mut tokens);
+      cjk.push(c);
+    } else if c.is_alphanumeric() {
+      flush_cjk(&mut cjk, &mut tokens);
+      word.extend(c.to_lowercase());
+    } else {
+      flush_word(&mut word, &mut tokens);
+      flush_cjk(&mut cjk, &mut tokens);
+    }
+  }
+  flush_word(&mut word, &mut tokens);
+  flush_cjk(&mut cjk, &mut tokens);
+  tokens
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_tokenize() {
+    assert_eq!(
+      tokenize("The Art of Computer-Programming, Vol. 1"),
+      vec!["the", "art", "of", "computer", "programming", "vol", "1"]
+    );
+    assert_eq!(tokenize("  ,, "), Vec::<String>::new());
+  }
+
+  #[test]
+  fn test_tokenize_cjk() {
+    assert_eq!(tokenize("深層学習"), vec!["深層", "層学", "学習"]);
+    assert_eq!(tokenize("BERTの解析"), vec!["bert", "の解", "解析"]);
+    assert_eq!(tokenize("森 鴎外"), vec!["森", "鴎外"]);
+  }
+}
@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+
+use failure::Error;
+
+use super::{keywords, person, year, Entry};
+use crate::bibtex::decode;
+use crate::db::ArticleContent;
+
+const MONTHS: &[&str] = &[
+  "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+struct Parser {
+  chars: Vec<char>,
+  pos: usize,
+  line: usize,
+  strings: HashMap<String, String>,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<char> {
+    self.chars.get(self.pos).cloned()
+  }
+
+  fn bump(&mut self) -> Option<char> {
+    let c = self.peek()?;
+    self.pos += 1;
+    if c == '\n' {
+      self.line += 1;
+    }
+    Some(c)
+  }
+
+  fn skip_whitespace(&mut self) {
+    while self.peek().is_some_and(char::is_whitespace) {
+      self.bump();
+    }
+  }
+
+  fn ident(&mut self) -> String {
+    let mut ident = String::new();
+    while let Some(c) = self.peek() {
+      if !(c.is_alphanumeric() || "-_:.+/'".contains(c)) {
+        break;
+      }
+      ident.push(c);
+      self.bump();
+    }
+    ident
+  }
+
+  fn delimited(&mut self, line: usize, end: char) -> Result<String, Error> {
+    let mut depth = 0;
+    let mut val = String::new();
+    loop {
+      let c = match self.bump() {
+        Some(c) => c,
+        None => bail!("The entry at line {} is not closed.", line),
+      };
+      match c {
+        c if c == end && depth == 0 => return Ok(val),
+        '{' => depth += 1,
+        '}' => depth -= 1,
+        '\\' => {
+          val.push(c);
+          val.extend(self.bump());
+          continue;
+        }
+        _ => {}
+      }
+      val.push(c);
+    }
+  }
+
+  fn value(&mut self, line: usize) -> Result<String, Error> {
+    let mut val = String::new();
+    loop {
+      self.skip_whitespace();
+      match self.peek() {
+        Some('{') => {
+          self.bump();
+          val.push_str(&self.delimited(line, '}')?);
+        }
+        Some('"') => {
+          self.bump();
+          val.push_str(&self.delimited(line, '"')?);
+        }
+        Some(_) => {
+          let name = self.ident();
+          if name.is_empty() {
+            bail!("The value at line {} is malformed.", self.line);
+          }
+          let lower = name.to_lowercase();
+          match self.strings.get(&lower) {
+            Some(s) => val.push_str(s),
+            None if MONTHS.contains(&lower.as_str()) => val.push_str(&name),
+            None if name.chars().all(|c| c.is_ascii_digit()) => val.push_str(&name),
+            None => bail!(
+              "The string '{}' at line {} is not defined.",
+              name,
+              self.line
+            ),
+          }
+        }
+        None => bail!("The entry at line {} is not closed.", line),
+      }
+      self.skip_whitespace();
+      if self.peek() != Some('#') {
+        return Ok(val);
+      }
+      self.bump();
+    }
+  }
+
+  fn fields(&mut self, line: usize, close: char) -> Result<HashMap<String, String>, Error> {
+    let mut fields = HashMap::new();
+    loop {
+      self.skip_whitespace();
+      match self.peek() {
+        Some(',') => {
+          self.bump();
+          continue;
+        }
+        Some(c) if c == close => {
+          self.bump();
+          return Ok(fields);
+        }
+        None => bail!("The entry at line {} is not closed.", line),
+        _ => {}
+      }
+      let name = self.ident().to_lowercase();
+      self.skip_whitespace();
+      if name.is_empty() || self.bump() != Some('=') {
+        bail!("A field at line {} is malformed.", self.line);
+      }
+      let val = self.value(line)?;
+      fields.insert(name, val);
+    }
+  }
+
+  fn skip_balanced(&mut self, close: char) {
+    let open = if close == '}' { '{' } else { '(' };
+    let mut depth = 0;
+    while let Some(c) = self.bump() {
+      if c == open {
+        depth += 1;
+      } else if c == close {
+        if depth == 0 {
+          return;
+        }
+        depth -= 1;
+      }
+    }
+  }
+
+  fn entry(&mut self, line: usize, close: char) -> Entry {
+    self.skip_whitespace();
+    let mut key = String::new();
+    while let Some(c) = self.peek() {
+      if c == ',' || c == close || c.is_whitespace() {
+        break;
+      }
+      key.push(c);
+      self.bump();
+    }
+    let content = self.fields(line, close).map(content);
+    if content.is_err() {
+      self.skip_balanced(close);
+    }
+    Entry {
+      line,
+      key: Some(key).filter(|k| !k.is_empty()),
+      content,
+    }
+  }
+}
+
+fn split_authors(raw: &str) -> Vec<String> {
+  let mut authors = Vec::new();
+  let mut current = String::new();
+  let mut depth = 0;
+  for word in raw.split_whitespace() {
+    if depth == 0 && word.eq_ignore_ascii_case("and") {
+      authors.push(std::mem::take(&mut current));
+      continue;
+    }
+    depth += word.matches('{').count() as i32 - word.matches('}').count() as i32;
+    if !current.is_empty() {
+      current.push(' ');
+    }
+    current.push_str(word);
+  }
+  authors.push(current);
+  authors
+    .iter()
+    .map(|a| person(&decode(a)))
+    .filter(|a| !a.is_empty() && a != "others")
+    .collect()
+}
+
+fn content(mut fields: HashMap<String, String>) -> ArticleContent {
+  let authors = fields
+    .remove("author")
+    .map_or_else(Vec::new, |a| split_authors(&a));
+  let mut take = |name: &str| {
+    fields
+      .remove(name)
+      .map(|v| decode(&v))
+      .filter(|v| !v.is_empty())
+  };
+  let eprint = take("eprint");
+  let prefix = take("archiveprefix").or_else(|| take("eprinttype"));
+  let arxiv = match (eprint, prefix) {
+    (Some(eprint), Some(ref prefix)) if prefix.eq_ignore_ascii_case("arxiv") => Some(eprint),
+    _ => take("arxiv"),
+  };
+  ArticleContent {
+    title: take("title").unwrap_or_default(),
+    authors,
+    year: take("year").and_then(|y| year(&y)),
+    venue: take("journal").or_else(|| take("booktitle")),
+    volume: take("volume"),
+    pages: take("pages").map(|p| p.replace(['–', '—'], "-")),
+    doi: take("doi"),
+    arxiv,
+    abstract_: take("abstract"),
+    keywords: take("keywords").map_or_else(Vec::new, |k| keywords(&k)),
+    note: take("note"),
+    attachments: Vec::new(),
+    next_attachment: 0,
+  }
+}
+
+pub fn parse(text: &str) -> Vec<Entry> {
+  let mut parser = Parser {
+    chars: text.chars().collect(),
+    pos: 0,
+    line: 1,
+    strings: HashMap::new(),
+  };
+  let mut entries = Vec::new();
+  loop {
+    while parser.peek().is_some_and(|c| c != '@') {
+      parser.bump();
+    }
+    let line = parser.line;
+    if parser.bump().is_none() {
+      return entries;
+    }
+    let kind = parser.ident().to_lowercase();
+    parser.skip_whitespace();
+    let close = match parser.peek() {
+      Some('{') => '}',
+      Some('(') => ')',
+      _ => continue,
+    };
+    parser.bump();
+    match kind.as_str() {
+      "comment" | "preamble" => parser.skip_balanced(close),
+      "string" => match parser.fields(line, close) {
+        Ok(fields) => parser.strings.extend(fields),
+        Err(e) => entries.push(Entry {
+          line,
+          key: None,
+          content: Err(e),
+        }),
+      },
+      _ => entries.push(parser.entry(line, close)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const BIB: &str = r#"
+@string{cj = "The Computer Journal"}
+@comment{ignored @misc{x, title={X}} }
+@Article{knuth1984,
+  author = {Knuth, Donald E.},
+  title = "Literate {P}rogramming",
+  journal = cj,
+  year = 1984, volume = {27},
+  pages = {97--111},
+  keywords = {literate programming; WEB},
+}
+
+@inproceedings(godel,
+  author = {G{\"o}del, Kurt and Erd{\H o}s, Paul and others},
+  title = {{\"U}ber formal unentscheidbare S{\"a}tze},
+  booktitle = "Proc. " # "Vienna",
+  eprint = {1234.5678}, archivePrefix = {arXiv},
+)
+
+@misc{undefined, title = {Undefined}, note = undefined}
+@misc{unclosed, title = {Unclosed
+"#;
+
+  #[test]
+  fn test_parse() {
+    let entries = parse(BIB);
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0].line, 4);
+    assert_eq!(entries[0].key.as_deref(), Some("knuth1984"));
+    assert_eq!(
+      entries[0].content.as_ref().unwrap(),
+      &ArticleContent {
+        year: Some(1984),
+        venue: Some("The Computer Journal".to_owned()),
+        volume: Some("27".to_owned()),
+        pages: Some("97-111".to_owned()),
+        keywords: vec!["literate programming".to_owned(), "WEB".to_owned()],
+        ..ArticleContent::new("Literate Programming", ["Donald E. Knuth"]).unwrap()
+      }
+    );
+    assert_eq!(
+      entries[1].content.as_ref().unwrap(),
+      &ArticleContent {
+        venue: Some("Proc. Vienna".to_owned()),
+        arxiv: Some("1234.5678".to_owned()),
+        ..ArticleContent::new(
+          "Über formal unentscheidbare Sätze",
+          ["Kurt Gödel", "Paul Erdős"]
+        )
+        .unwrap()
+      }
+    );
+    assert_eq!(entries[2].line, 20);
+    assert!(entries[2].content.is_err());
+    assert_eq!(entries[3].key.as_deref(), Some("unclosed"));
+    assert!(entries[3].content.is_err());
+  }
+}
@@ -0,0 +1,191 @@
+mod bibtex;
+mod ris;
+
+use std::fs;
+use std::path::Path;
+
+use failure::Error;
+
+use crate::db::{ArticleContent, Imported};
+
+#[derive(Debug, Fail)]
+pub enum ImportError {
+  #[fail(display = "The file is {} bytes, larger than {} bytes.", _0, _1)]
+  TooLarge(u64, u64),
+  #[fail(display = "The file is not UTF-8 text.")]
+  NotUtf8,
+  #[fail(display = "The file is neither BibTeX nor RIS.")]
+  UnknownFormat,
+}
+
+pub struct Entry {
+  pub line: usize,
+  pub key: Option<String>,
+  pub content: Result<ArticleContent, Error>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+  Bibtex,
+  Ris,
+}
+
+impl Format {
+  pub fn detect(filename: Option<&str>, text: &str) -> Option<Self> {
+    let extension = filename
+      .and_then(|name| name.rsplit('.').next())
+      .map(str::to_lowercase);
+    match extension.as_deref() {
+      Some("bib") | Some("bibtex") => return Some(Format::Bibtex),
+      Some("ris") => return Some(Format::Ris),
+      _ => {}
+    }
+    let first = text.lines().map(str::trim).find(|l| !l.is_empty())?;
+    if ris::is_tag_line(first) {
+      Some(Format::Ris)
+    } else if text.contains('@') {
+      Some(Format::Bibtex)
+    } else {
+      None
+    }
+  }
+}
+
+pub fn parse(text: &str, format: Format) -> Vec<Entry> {
+  match format {
+    Format::Bibtex => bibtex::parse(text),
+    Format::Ris => ris::parse(text),
+  }
+}
+
+pub const MAX_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Serialize)]
+pub struct ImportedEntry {
+  pub line: usize,
+  pub key: Option<String>,
+  pub title: Option<String>,
+  #[serde(flatten)]
+  pub result: Imported,
+}
+
+#[derive(Serialize, Default)]
+pub struct ImportReport {
+  pub added: usize,
+  pub duplicates: usize,
+  pub invalid: usize,
+  pub entries: Vec<ImportedEntry>,
+}
+
+/// Parses a BibTeX or RIS file of at most [`MAX_SIZE`] bytes.
+pub fn read(filename: Option<&str>, path: &Path) -> Result<Vec<Entry>, Error> {
+  let size = fs::metadata(path)?.len();
+  if size > MAX_SIZE {
+    return Err(ImportError::TooLarge(size, MAX_SIZE).into());
+  }
+  let text = String::from_utf8(fs::read(path)?).map_err(|_| ImportError::NotUtf8)?;
+  let format = Format::detect(filename, &text).ok_or(ImportError::UnknownFormat)?;
+  Ok(parse(&text, format))
+}
+
+/// The articles of the valid entries, in the order [`Import`] expects.
+///
+/// [`Import`]: crate::db::Import
+pub fn contents(entries: &[Entry]) -> Vec<ArticleContent> {
+  entries
+    .iter()
+    .filter_map(|entry| entry.content.as_ref().ok().cloned())
+    .collect()
+}
+
+/// Pairs the entries with the outcomes of importing their [`contents`].
+pub fn report(entries: Vec<Entry>, imported: Vec<Imported>) -> Result<ImportReport, Error> {
+  let mut imported = imported.into_iter();
+  let mut report = ImportReport::default();
+  for entry in entries {
+    let (title, result) = match entry.content {
+      Ok(content) => (
+        Some(content.title).filter(|t| !t.is_empty()),
+        imported
+          .next()
+          .ok_or_else(|| format_err!("An imported entry is missing"))?,
+      ),
+      Err(e) => (
+        None,
+        Imported::Invalid {
+          error: e.to_string(),
+        },
+      ),
+    };
+    match result {
+      Imported::Added { .. } => report.added += 1,
+      Imported::Duplicate { .. } => report.duplicates += 1,
+      Imported::Invalid { .. } => report.invalid += 1,
+    }
+    report.entries.push(ImportedEntry {
+      line: entry.line,
+      key: entry.key,
+      title,
+      result,
+    });
+  }
+  Ok(report)
+}
+
+fn person(name: &str) -> String {
+  let parts: Vec<_> = name.split(',').map(str::trim).collect();
+  match parts.as_slice() {
+    [last, first] if !first.is_empty() => format!("{} {}", first, last),
+    [last, suffix, first] => format!("{} {}, {}", first, last, suffix),
+    _ => name.trim().to_owned(),
+  }
+}
+
+fn year(val: &str) -> Option<u16> {
+  let digits: String = val
+    .chars()
+    .skip_while(|c| !c.is_ascii_digit())
+    .take_while(char::is_ascii_digit)
+    .collect();
+  if digits.len() == 4 {
+    digits.parse().ok()
+  } else {
+    None
+  }
+}
+
+fn keywords(val: &str) -> Vec<String> {
+  val
+    .split([',', ';'])
+    .map(str::trim)
+    .filter(|k| !k.is_empty())
+    .map(str::to_owned)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_detect() {
+    assert_eq!(Format::detect(Some("refs.BIB"), ""), Some(Format::Bibtex));
+    assert_eq!(Format::detect(Some("refs.ris"), ""), Some(Format::Ris));
+    assert_eq!(
+      Format::detect(Some("refs.txt"), "\nTY  - JOUR\nER  - \n"),
+      Some(Format::Ris)
+    );
+    assert_eq!(
+      Format::detect(None, "% comment\n@article{a, title={A}}"),
+      Some(Format::Bibtex)
+    );
+    assert_eq!(Format::detect(None, "plain text"), None);
+  }
+
+  #[test]
+  fn test_person() {
+    assert_eq!(person("Knuth, Donald E."), "Donald E. Knuth");
+    assert_eq!(person("Steele, Jr., Guy L."), "Guy L. Steele, Jr.");
+    assert_eq!(person("Edsger W. Dijkstra"), "Edsger W. Dijkstra");
+  }
+}
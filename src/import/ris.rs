@@ -0,0 +1,151 @@
+use super::{keywords, person, year, Entry};
+use crate::db::ArticleContent;
+
+const VENUE_TAGS: &[&str] = &["JF", "JO", "T2", "BT", "JA"];
+
+pub fn is_tag_line(line: &str) -> bool {
+  let bytes = line.as_bytes();
+  bytes.len() >= 5
+    && bytes[0].is_ascii_uppercase()
+    && (bytes[1].is_ascii_uppercase() || bytes[1].is_ascii_digit())
+    && &bytes[2..5] == b"  -"
+}
+
+fn content(fields: &[(String, String)]) -> ArticleContent {
+  let first = |tags: &[&str]| {
+    tags.iter().find_map(|tag| {
+      fields
+        .iter()
+        .find(|(t, v)| t == tag && !v.is_empty())
+        .map(|(_, v)| v.clone())
+    })
+  };
+  let all = |tags: &[&str]| {
+    fields
+      .iter()
+      .filter(|(t, v)| tags.contains(&t.as_str()) && !v.is_empty())
+      .map(|(_, v)| v.clone())
+      .collect::<Vec<_>>()
+  };
+  let pages = match (first(&["SP"]), first(&["EP"])) {
+    (Some(start), Some(end)) => Some(format!("{}-{}", start, end)),
+    (start, _) => start,
+  };
+  let arxiv = all(&["UR"]).into_iter().find_map(|url| {
+    url
+      .find("arxiv.org/abs/")
+      .map(|i| url[i + "arxiv.org/abs/".len()..].to_owned())
+  });
+  ArticleContent {
+    title: first(&["TI", "T1", "CT"]).unwrap_or_default(),
+    authors: all(&["AU", "A1"]).iter().map(|a| person(a)).collect(),
+    year: first(&["PY", "Y1", "DA"]).and_then(|y| year(&y)),
+    venue: first(VENUE_TAGS),
+    volume: first(&["VL"]),
+    pages,
+    doi: first(&["DO"]),
+    arxiv,
+    abstract_: first(&["AB", "N2"]),
+    keywords: all(&["KW"]).iter().flat_map(|k| keywords(k)).collect(),
+    note: first(&["N1"]),
+    attachments: Vec::new(),
+    next_attachment: 0,
+  }
+}
+
+pub fn parse(text: &str) -> Vec<Entry> {
+  let mut entries = Vec::new();
+  let mut current: Option<(usize, Vec<(String, String)>)> = None;
+  let finish = |line, fields: Vec<(String, String)>| Entry {
+    line,
+    key: fields
+      .iter()
+      .find(|(t, v)| t == "ID" && !v.is_empty())
+      .map(|(_, v)| v.clone()),
+    content: Ok(content(&fields)),
+  };
+  for (i, raw) in text.lines().enumerate() {
+    let line = raw.trim_start_matches('\u{feff}').trim_end();
+    if !is_tag_line(line) {
+      if let Some((_, fields)) = current.as_mut() {
+        if let (Some((_, val)), false) = (fields.last_mut(), line.trim().is_empty()) {
+          val.push(' ');
+          val.push_str(line.trim());
+        }
+      }
+      continue;
+    }
+    let (tag, val) = (&line[..2], line[5..].trim());
+    match tag {
+      "TY" => {
+        if let Some((start, fields)) = current.take() {
+          entries.push(finish(start, fields));
+        }
+        current = Some((i + 1, Vec::new()));
+      }
+      "ER" => {
+        if let Some((start, fields)) = current.take() {
+          entries.push(finish(start, fields));
+        }
+      }
+      _ => {
+        if let Some((_, fields)) = current.as_mut() {
+          fields.push((tag.to_owned(), val.to_owned()));
+        }
+      }
+    }
+  }
+  if let Some((start, _)) = current {
+    entries.push(Entry {
+      line: start,
+      key: None,
+      content: Err(format_err!(
+        "The record at line {} is not terminated by 'ER'.",
+        start
+      )),
+    });
+  }
+  entries
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse() {
+    let entries = parse(
+      "TY  - JOUR
+AU  - Dijkstra, Edsger W.
+TI  - Go To Statement Considered
+  Harmful
+T2  - Communications of the ACM
+PY  - 1968/03/01/
+VL  - 11
+SP  - 147
+EP  - 148
+DO  - 10.1145/362929.362947
+KW  - goto; structured programming
+ER  - 
+
+TY  - RPRT
+TI  - Unterminated
+",
+    );
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+      entries[0].content.as_ref().unwrap(),
+      &ArticleContent {
+        venue: Some("Communications of the ACM".to_owned()),
+        year: Some(1968),
+        volume: Some("11".to_owned()),
+        pages: Some("147-148".to_owned()),
+        doi: Some("10.1145/362929.362947".to_owned()),
+        keywords: vec!["goto".to_owned(), "structured programming".to_owned()],
+        ..ArticleContent::new("Go To Statement Considered Harmful", ["Edsger W. Dijkstra"]).unwrap()
+      }
+    );
+    assert_eq!(entries[1].line, 14);
+    assert!(entries[1].content.is_err());
+  }
+}
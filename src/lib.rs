@@ -0,0 +1,52 @@
+//! An article store backed by LMDB with full-text search, collections and
+//! file attachments.
+//!
+//! [`db::Store`] performs every operation synchronously. [`db::Db`] serves a
+//! store as an actix actor, where every operation is a message such as
+//! [`db::Add`] or [`db::Search`]. The `weid` binary serves the same
+//! operations over HTTP and from the command line.
+//!
+//! ```no_run
+//! use weid::config::Config;
+//! use weid::db::{ArticleContent, Query, Store};
+//!
+//! let mut store = Store::open(&Config::default())?;
+//! let content = ArticleContent::new("Literate Programming", ["Donald E. Knuth"])?;
+//! let article = store.add(content, None)?;
+//! assert_eq!(store.search(&Query::parse("knuth")?)?[0].id(), article.id());
+//! # Ok::<(), failure::Error>(())
+//! ```
+
+#![feature(rustc_private)]
+// `#[derive(Fail)]` expands to impls inside a `const` item, and failure is no
+// longer maintained.
+#![allow(non_local_definitions)]
+
+extern crate actix;
+extern crate serde;
+extern crate serde_json;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate log;
+extern crate num_cpus;
+extern crate simplelog;
+#[macro_use]
+extern crate failure;
+extern crate libc;
+extern crate lmdb_zero as lmdb;
+extern crate lopdf;
+extern crate mime;
+extern crate pdf_extract;
+#[cfg(test)]
+extern crate rand;
+extern crate roxmltree;
+extern crate sha2;
+extern crate tempfile;
+
+pub mod bibtex;
+pub mod collection;
+pub mod config;
+pub mod db;
+pub mod import;
+pub mod pdf;
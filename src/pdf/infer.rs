@@ -0,0 +1,98 @@
+use failure::Error;
+
+use super::Metadata;
+use crate::db::ArticleContent;
+
+/// The fields of an article given when adding it. The missing ones may be
+/// filled by [`infer`].
+#[derive(Default)]
+pub struct Fields {
+  pub title: Option<String>,
+  pub authors: Option<Vec<String>>,
+  pub year: Option<u16>,
+  pub venue: Option<String>,
+  pub volume: Option<String>,
+  pub pages: Option<String>,
+  pub doi: Option<String>,
+  pub arxiv: Option<String>,
+  pub abstract_: Option<String>,
+  pub keywords: Option<Vec<String>>,
+  pub note: Option<String>,
+}
+
+impl Fields {
+  /// Whether any field that [`infer`] can fill is missing.
+  pub fn inferable(&self) -> bool {
+    self.title.is_none()
+      || self.authors.is_none()
+      || self.year.is_none()
+      || self.abstract_.is_none()
+      || self.keywords.is_none()
+  }
+}
+
+fn fill<T>(
+  inferred: &mut Vec<&'static str>,
+  name: &'static str,
+  val: Option<T>,
+  meta: Option<T>,
+) -> Option<T> {
+  match (val, meta) {
+    (Some(val), _) => Some(val),
+    (None, Some(val)) => {
+      inferred.push(name);
+      Some(val)
+    }
+    (None, None) => None,
+  }
+}
+
+/// Fills the fields missing from `fields` with the metadata of a PDF and
+/// returns the content with the names of the filled fields.
+pub fn infer(fields: Fields, meta: Metadata) -> Result<(ArticleContent, Vec<&'static str>), Error> {
+  let mut inferred = Vec::new();
+  let title = match fill(&mut inferred, "title", fields.title, meta.title) {
+    Some(val) => val,
+    None => bail!("'title' is not provided and is not found in a PDF"),
+  };
+  let content = ArticleContent {
+    title,
+    authors: fill(&mut inferred, "authors", fields.authors, meta.authors).unwrap_or_default(),
+    year: fill(&mut inferred, "year", fields.year, meta.year),
+    venue: fields.venue,
+    volume: fields.volume,
+    pages: fields.pages,
+    doi: fields.doi,
+    arxiv: fields.arxiv,
+    abstract_: fill(&mut inferred, "abstract", fields.abstract_, meta.subject),
+    keywords: fill(&mut inferred, "keywords", fields.keywords, meta.keywords).unwrap_or_default(),
+    note: fields.note,
+    attachments: Vec::new(),
+    next_attachment: 0,
+  };
+  Ok((content, inferred))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_infer() -> Result<(), Error> {
+    let fields = Fields {
+      authors: Some(vec!["Knuth".to_owned()]),
+      ..Default::default()
+    };
+    let meta = Metadata {
+      title: Some("Literate Programming".to_owned()),
+      authors: Some(vec!["Someone Else".to_owned()]),
+      ..Default::default()
+    };
+    let (content, inferred) = infer(fields, meta)?;
+    assert_eq!(content.title, "Literate Programming");
+    assert_eq!(content.authors, vec!["Knuth"]);
+    assert_eq!(inferred, vec!["title"]);
+    assert!(infer(Default::default(), Default::default()).is_err());
+    Ok(())
+  }
+}
@@ -0,0 +1,266 @@
+use std::panic;
+use std::path::Path;
+
+use failure::Error;
+use lopdf::{Dictionary, Document, Object};
+
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const DC_NS: &str = "http://purl.org/dc/elements/1.1/";
+const XMP_NS: &str = "http://ns.adobe.com/xap/1.0/";
+const PDF_NS: &str = "http://ns.adobe.com/pdf/1.3/";
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Metadata {
+  pub title: Option<String>,
+  pub authors: Option<Vec<String>>,
+  pub year: Option<u16>,
+  pub subject: Option<String>,
+  pub keywords: Option<Vec<String>>,
+}
+
+impl Metadata {
+  fn merge(self, other: Self) -> Self {
+    Self {
+      title: self.title.or(other.title),
+      authors: self.authors.or(other.authors),
+      year: self.year.or(other.year),
+      subject: self.subject.or(other.subject),
+      keywords: self.keywords.or(other.keywords),
+    }
+  }
+}
+
+fn decode_text_string(bytes: &[u8]) -> String {
+  if bytes.starts_with(&[0xfe, 0xff]) {
+    let units: Vec<u16> = bytes[2..]
+      .chunks(2)
+      .filter(|c| c.len() == 2)
+      .map(|c| u16::from_be_bytes([c[0], c[1]]))
+      .collect();
+    String::from_utf16_lossy(&units)
+  } else if bytes.starts_with(&[0xef, 0xbb, 0xbf]) {
+    String::from_utf8_lossy(&bytes[3..]).into_owned()
+  } else {
+    bytes.iter().map(|&b| char::from(b)).collect()
+  }
+}
+
+fn non_empty<S: AsRef<str>>(val: S) -> Option<String> {
+  let val = val.as_ref().trim();
+  if val.is_empty() {
+    None
+  } else {
+    Some(val.to_owned())
+  }
+}
+
+fn title(val: String) -> Option<String> {
+  let lower = val.to_lowercase();
+  let junk = lower == "untitled"
+    || [".pdf", ".dvi", ".ps", ".doc", ".docx", ".tex"]
+      .iter()
+      .any(|ext| lower.ends_with(ext));
+  if junk {
+    None
+  } else {
+    Some(val)
+  }
+}
+
+fn split_list<'a>(val: &'a str, separators: &[&str]) -> Vec<&'a str> {
+  match separators.iter().find(|sep| val.contains(*sep)) {
+    Some(sep) => val.split(sep).collect(),
+    None => vec![val],
+  }
+}
+
+fn non_empty_list<'a, I: Iterator<Item = &'a str>>(items: I) -> Option<Vec<String>> {
+  Some(items.filter_map(non_empty).collect::<Vec<_>>()).filter(|v| !v.is_empty())
+}
+
+fn authors(val: &str) -> Option<Vec<String>> {
+  non_empty_list(
+    split_list(val, &[";", ","])
+      .into_iter()
+      .flat_map(|a| a.split(" and ")),
+  )
+}
+
+fn keywords(val: &str) -> Option<Vec<String>> {
+  non_empty_list(split_list(val, &[";", ","]).into_iter())
+}
+
+fn year(val: &str) -> Option<u16> {
+  let val = val.trim();
+  let val = if val.starts_with("D:") {
+    val.get(2..)?
+  } else {
+    val
+  };
+  let val = val.get(..4)?;
+  if !val.bytes().all(|b| b.is_ascii_digit()) {
+    return None;
+  }
+  val.parse().ok().filter(|&y| y > 0)
+}
+
+fn info_string(doc: &Document, info: &Dictionary, key: &[u8]) -> Option<String> {
+  let bytes = info.get_deref(key, doc).and_then(Object::as_str).ok()?;
+  non_empty(decode_text_string(bytes))
+}
+
+fn from_info(doc: &Document) -> Metadata {
+  let info = match doc
+    .trailer
+    .get_deref(b"Info", doc)
+    .and_then(Object::as_dict)
+  {
+    Ok(info) => info,
+    Err(_) => return Default::default(),
+  };
+  Metadata {
+    title: info_string(doc, info, b"Title").and_then(title),
+    authors: info_string(doc, info, b"Author").and_then(|a| authors(&a)),
+    year: info_string(doc, info, b"CreationDate").and_then(|d| year(&d)),
+    subject: info_string(doc, info, b"Subject"),
+    keywords: info_string(doc, info, b"Keywords").and_then(|k| keywords(&k)),
+  }
+}
+
+fn xmp_values(xmp: &roxmltree::Document, ns: &str, name: &str) -> Vec<String> {
+  for node in xmp.descendants().filter(|n| n.is_element()) {
+    if node.tag_name().namespace() == Some(ns) && node.tag_name().name() == name {
+      let items: Vec<String> = node
+        .descendants()
+        .filter(|n| {
+          n.is_element() && n.tag_name().namespace() == Some(RDF_NS) && n.tag_name().name() == "li"
+        })
+        .filter_map(|n| n.text().and_then(non_empty))
+        .collect();
+      if !items.is_empty() {
+        return items;
+      }
+      if let Some(text) = node.text().and_then(non_empty) {
+        return vec![text];
+      }
+    }
+    if let Some(val) = node.attribute((ns, name)).and_then(non_empty) {
+      return vec![val];
+    }
+  }
+  Vec::new()
+}
+
+fn from_xmp(doc: &Document) -> Metadata {
+  let stream = match doc
+    .catalog()
+    .and_then(|c| c.get_deref(b"Metadata", doc))
+    .and_then(Object::as_stream)
+  {
+    Ok(stream) => stream,
+    Err(_) => return Default::default(),
+  };
+  let bytes = stream
+    .decompressed_content()
+    .unwrap_or_else(|_| stream.content.clone());
+  let raw = String::from_utf8_lossy(&bytes);
+  let xml = match raw.find('<') {
+    Some(i) => &raw[i..],
+    None => return Default::default(),
+  };
+  let xmp = match roxmltree::Document::parse(xml) {
+    Ok(xmp) => xmp,
+    Err(e) => {
+      warn!("Failed to parse the XMP metadata: {}", e);
+      return Default::default();
+    }
+  };
+  let first = |ns, name| xmp_values(&xmp, ns, name).into_iter().next();
+  let list = |ns, name| Some(xmp_values(&xmp, ns, name)).filter(|v| !v.is_empty());
+  Metadata {
+    title: first(DC_NS, "title").and_then(title),
+    authors: list(DC_NS, "creator"),
+    year: first(XMP_NS, "CreateDate").and_then(|d| year(&d)),
+    subject: first(DC_NS, "description"),
+    keywords: first(PDF_NS, "Keywords")
+      .and_then(|k| keywords(&k))
+      .or_else(|| list(DC_NS, "subject")),
+  }
+}
+
+pub fn extract_metadata<P: AsRef<Path>>(path: P) -> Result<Metadata, Error> {
+  let path = path.as_ref().to_owned();
+  panic::catch_unwind(move || -> Result<Metadata, Error> {
+    let doc = Document::load(path)?;
+    Ok(from_xmp(&doc).merge(from_info(&doc)))
+  })
+  .map_err(|_| format_err!("The PDF parser panicked."))?
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_decode_text_string() {
+    assert_eq!(decode_text_string(b"Caf\xe9"), "Café");
+    assert_eq!(
+      decode_text_string(&[0xfe, 0xff, 0x30, 0x42, 0x00, 0x41]),
+      "あA"
+    );
+  }
+
+  #[test]
+  fn test_authors() {
+    assert_eq!(
+      authors("Alice Smith; Bob Jones"),
+      Some(vec!["Alice Smith".to_owned(), "Bob Jones".to_owned()])
+    );
+    assert_eq!(
+      authors("Alice Smith, Bob Jones and Carol White"),
+      Some(vec![
+        "Alice Smith".to_owned(),
+        "Bob Jones".to_owned(),
+        "Carol White".to_owned()
+      ])
+    );
+    assert_eq!(authors("Knuth"), Some(vec!["Knuth".to_owned()]));
+    assert_eq!(authors("  "), None);
+  }
+
+  #[test]
+  fn test_year() {
+    assert_eq!(year("D:20190514093000+09'00'"), Some(2019));
+    assert_eq!(year("2010-03-01T12:00:00Z"), Some(2010));
+    assert_eq!(year("D:"), None);
+    assert_eq!(year("D:200é1"), None);
+    assert_eq!(year("19€"), None);
+  }
+
+  #[test]
+  fn test_xmp() {
+    let xml = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+      <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+        <rdf:Description rdf:about="" xmlns:pdf="http://ns.adobe.com/pdf/1.3/" pdf:Keywords="typesetting; programming">
+          <dc:title xmlns:dc="http://purl.org/dc/elements/1.1/">
+            <rdf:Alt><rdf:li xml:lang="x-default">Literate Programming</rdf:li></rdf:Alt>
+          </dc:title>
+          <dc:creator xmlns:dc="http://purl.org/dc/elements/1.1/">
+            <rdf:Seq><rdf:li>Donald E. Knuth</rdf:li></rdf:Seq>
+          </dc:creator>
+        </rdf:Description>
+      </rdf:RDF>
+    </x:xmpmeta>"#;
+    let xmp = roxmltree::Document::parse(xml).unwrap();
+    assert_eq!(
+      xmp_values(&xmp, DC_NS, "title"),
+      vec!["Literate Programming"]
+    );
+    assert_eq!(xmp_values(&xmp, DC_NS, "creator"), vec!["Donald E. Knuth"]);
+    assert_eq!(
+      xmp_values(&xmp, PDF_NS, "Keywords"),
+      vec!["typesetting; programming"]
+    );
+    assert_eq!(xmp_values(&xmp, XMP_NS, "CreateDate"), Vec::<String>::new());
+  }
+}
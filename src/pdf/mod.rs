@@ -0,0 +1,7 @@
+mod infer;
+mod meta;
+mod text;
+
+pub use self::infer::*;
+pub use self::meta::*;
+pub use self::text::*;
@@ -0,0 +1,46 @@
+use std::panic;
+use std::path::Path;
+
+use failure::Error;
+
+fn normalize(raw: &str) -> String {
+  let mut text = String::with_capacity(raw.len());
+  let mut chars = raw.chars().peekable();
+  while let Some(c) = chars.next() {
+    if c == '-' && chars.peek() == Some(&'\n') {
+      chars.next();
+      continue;
+    }
+    if c.is_whitespace() {
+      while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+      }
+      if !text.is_empty() && chars.peek().is_some() {
+        text.push(' ');
+      }
+      continue;
+    }
+    text.push(c);
+  }
+  text
+}
+
+pub fn extract_text<P: AsRef<Path>>(path: P) -> Result<String, Error> {
+  let path = path.as_ref().to_owned();
+  let raw = panic::catch_unwind(move || pdf_extract::extract_text(path))
+    .map_err(|_| format_err!("The PDF parser panicked."))??;
+  Ok(normalize(&raw))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_normalize() {
+    assert_eq!(
+      normalize("\n  Literate  Pro-\ngramming\n\nby\tKnuth \n"),
+      "Literate Programming by Knuth"
+    );
+  }
+}
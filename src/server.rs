@@ -0,0 +1,885 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use actix::Addr;
+use actix_files as fs;
+use actix_multipart::{Field, Multipart, MultipartError};
+use actix_web::{error, http, middleware, web, App, HttpResponse, HttpServer, Responder};
+use failure::Error;
+use futures::{future, Future, Stream};
+use tempfile::NamedTempFile;
+
+use weid::config::Config;
+use weid::db::{self, Db};
+use weid::{bibtex, import, pdf};
+
+struct AppData {
+  db: Addr<Db>,
+}
+
+fn index() -> impl Responder {
+  fs::NamedFile::open(Path::new("index.html"))
+}
+
+fn favicon() -> impl Responder {
+  fs::NamedFile::open(Path::new("favicon.ico"))
+}
+
+struct AddParam {
+  fields: pdf::Fields,
+  file: Option<(NamedTempFile, Option<String>)>,
+}
+
+fn read_json_field<T>(field: Field) -> impl Future<Item = T, Error = error::Error>
+where
+  for<'a> T: serde::Deserialize<'a>,
+{
+  field
+    .map_err(error::ErrorInternalServerError)
+    .fold(web::BytesMut::with_capacity(8192), |mut body, chunk| {
+      if (body.len() + chunk.len()) > 32_768 {
+        Err(format_err!("Overflow field"))
+      } else {
+        body.extend_from_slice(&chunk);
+        Ok(body)
+      }
+    })
+    .and_then(|body| serde_json::from_slice(&body).map_err(error::ErrorInternalServerError))
+}
+
+fn set_field(
+  fields: &mut pdf::Fields,
+  name: &str,
+  val: serde_json::Value,
+) -> Result<(), error::Error> {
+  fn parse<T>(val: serde_json::Value) -> Result<Option<T>, error::Error>
+  where
+    for<'a> T: serde::Deserialize<'a>,
+  {
+    serde_json::from_value(val)
+      .map(Some)
+      .map_err(error::ErrorInternalServerError)
+  }
+  match name {
+    "title" => fields.title = parse(val)?,
+    "authors" => fields.authors = parse(val)?,
+    "year" => fields.year = parse(val)?,
+    "venue" => fields.venue = parse(val)?,
+    "volume" => fields.volume = parse(val)?,
+    "pages" => fields.pages = parse(val)?,
+    "doi" => fields.doi = parse(val)?,
+    "arxiv" => fields.arxiv = parse(val)?,
+    "abstract" => fields.abstract_ = parse(val)?,
+    "keywords" => fields.keywords = parse(val)?,
+    "note" => fields.note = parse(val)?,
+    _ => return Err(error::ErrorInternalServerError(format_err!("Unknown name"))),
+  }
+  Ok(())
+}
+
+fn read_file_field(field: Field) -> impl Future<Item = NamedTempFile, Error = error::Error> {
+  let file = match NamedTempFile::new() {
+    Ok(file) => file,
+    Err(e) => return future::Either::A(future::err(error::ErrorInternalServerError(e))),
+  };
+  future::Either::B(
+    field
+      .fold(file, |mut file, chunk| {
+        web::block(move || {
+          file
+            .write_all(chunk.as_ref())
+            .map_err(|e| MultipartError::Payload(error::PayloadError::Io(e)))?;
+          Ok(file)
+        })
+        .map_err(|e: error::BlockingError<MultipartError>| match e {
+          error::BlockingError::Error(e) => e,
+          error::BlockingError::Canceled => MultipartError::Incomplete,
+        })
+      })
+      .map_err(error::ErrorInternalServerError),
+  )
+}
+
+fn read_upload(multipart: Multipart) -> impl Future<Item = db::Upload, Error = error::Error> {
+  multipart
+    .map_err(error::ErrorInternalServerError)
+    .fold(None, |upload, field| -> Box<dyn Future<Item = _, Error = _>> {
+      let cd = field.content_disposition();
+      let name = cd.as_ref().and_then(|cd| cd.get_name().map(str::to_owned));
+      let filename = cd.and_then(|cd| cd.get_filename().map(str::to_owned));
+      match (name.as_deref(), upload) {
+        (Some("file"), None) => Box::new(read_file_field(field).map(|file| Some((file, filename)))),
+        (Some("file"), Some(_)) => Box::new(future::err(error::ErrorBadRequest(format_err!(
+          "Only one 'file' can be uploaded"
+        )))),
+        _ => Box::new(future::err(error::ErrorInternalServerError(format_err!(
+          "Unknown name"
+        )))),
+      }
+    })
+    .and_then(|upload| {
+      upload.ok_or_else(|| error::ErrorBadRequest(format_err!("'file' is not provided")))
+    })
+    .and_then(|(file, filename)| {
+      web::block(move || db::Upload::new(file, filename))
+        .map_err(error::ErrorInternalServerError)
+    })
+}
+
+#[derive(Serialize)]
+struct Conflict {
+  message: String,
+  id: u64,
+}
+
+fn db_error(e: Error) -> error::Error {
+  let e = match e.downcast::<db::ArticleError>() {
+    Ok(e) => return error::ErrorBadRequest(e),
+    Err(e) => e,
+  };
+  let e = match e.downcast::<db::AttachmentError>() {
+    Ok(db::AttachmentError::Duplicate(id)) => {
+      let e = db::AttachmentError::Duplicate(id);
+      let response = HttpResponse::Conflict().json(Conflict {
+        message: e.to_string(),
+        id,
+      });
+      return error::InternalError::from_response(e, response).into();
+    }
+    Ok(e) => return error::ErrorNotFound(e),
+    Err(e) => e,
+  };
+  let e = match e.downcast::<db::MergeError>() {
+    Ok(e @ db::MergeError::NotFound(_)) => return error::ErrorNotFound(e),
+    Ok(e) => return error::ErrorBadRequest(e),
+    Err(e) => e,
+  };
+  match e.downcast::<db::CollectionError>() {
+    Ok(e @ db::CollectionError::NotFound(_)) => error::ErrorNotFound(e),
+    Ok(e) => error::ErrorBadRequest(e),
+    Err(e) => error::ErrorInternalServerError(e),
+  }
+}
+
+#[derive(Serialize)]
+struct Added {
+  #[serde(flatten)]
+  article: db::Article,
+  inferred: Vec<&'static str>,
+  duplicate: bool,
+}
+
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum OnDuplicate {
+  Conflict,
+  Existing,
+}
+
+#[derive(Deserialize, Debug)]
+struct AddQuery {
+  #[serde(default = "default_on_duplicate")]
+  duplicate: OnDuplicate,
+}
+
+fn default_on_duplicate() -> OnDuplicate {
+  OnDuplicate::Conflict
+}
+
+fn extract(db: Addr<Db>, id: u64, path: PathBuf) {
+  actix::spawn(
+    web::block(move || pdf::extract_text(path))
+      .map_err(move |e| error!("Failed to extract the text of id={}: {}", id, e))
+      .and_then(move |text| {
+        db.send(db::SetText::new(id, text)).then(move |result| {
+          match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("Failed to index the text of id={}: {}", id, e),
+            Err(e) => error!("Failed to index the text of id={}: {}", id, e),
+          }
+          Ok(())
+        })
+      }),
+  );
+}
+
+fn index_text(db: Addr<Db>, article: &db::Article) {
+  let primary = article.content().primary();
+  match primary.map(|a| (a.media_type, article.attachment_path(a))) {
+    Some((db::MediaType::Pdf, path)) => extract(db, article.id(), path),
+    _ => {
+      let id = article.id();
+      actix::spawn(
+        db.send(db::SetText::new(id, String::new()))
+          .then(move |result| {
+            match result {
+              Ok(Ok(())) => {}
+              Ok(Err(e)) => error!("Failed to clear the text of id={}: {}", id, e),
+              Err(e) => error!("Failed to clear the text of id={}: {}", id, e),
+            }
+            Ok(())
+          }),
+      );
+    }
+  }
+}
+
+fn serve(
+  path: &Path,
+  media_type: db::MediaType,
+  filename: String,
+) -> Result<fs::NamedFile, error::Error> {
+  let disposition = if media_type.inline() {
+    http::header::DispositionType::Inline
+  } else {
+    http::header::DispositionType::Attachment
+  };
+  let mime = media_type
+    .mime()
+    .parse::<mime::Mime>()
+    .map_err(error::ErrorInternalServerError)?;
+  Ok(
+    fs::NamedFile::open(path)?
+      .set_content_type(mime)
+      .set_content_disposition(http::header::ContentDisposition {
+        disposition,
+        parameters: vec![http::header::DispositionParam::Filename(filename)],
+      }),
+  )
+}
+
+fn add(
+  data: web::Data<Arc<AppData>>,
+  query: web::Query<AddQuery>,
+  multipart: Multipart,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  let on_duplicate = query.duplicate;
+  multipart
+    .map_err(error::ErrorInternalServerError)
+    .fold(
+      AddParam {
+        fields: Default::default(),
+        file: None,
+      },
+      |mut param, field| -> Box<dyn Future<Item = _, Error = _>> {
+        let cd = match field.content_disposition() {
+          Some(cd) => cd,
+          None => {
+            return Box::new(future::err(error::ErrorInternalServerError(format_err!(
+              "The content disposition header is required."
+            ))))
+          }
+        };
+        let name = match cd.get_name() {
+          Some(name) => name,
+          None => {
+            return Box::new(future::err(error::ErrorInternalServerError(format_err!(
+              "The name is required."
+            ))))
+          }
+        };
+        match name {
+          "file" => {
+            let filename = cd.get_filename().map(str::to_owned);
+            Box::new(read_file_field(field).and_then(|file| {
+              param.file = Some((file, filename));
+              future::ok(param)
+            }))
+          }
+          name => {
+            let name = name.to_owned();
+            Box::new(read_json_field(field).and_then(move |val| {
+              set_field(&mut param.fields, &name, val)?;
+              Ok(param)
+            }))
+          }
+        }
+      },
+    )
+    .and_then(|param| {
+      let AddParam { fields: param, file } = param;
+      let (file, filename) = match file {
+        Some(file) => file,
+        None => return future::Either::A(future::ok((param, None, Default::default()))),
+      };
+      future::Either::B(
+        web::block(move || -> Result<_, Error> {
+          let upload = db::Upload::new(file, filename)?;
+          let meta = if upload.media_type() == db::MediaType::Pdf && param.inferable() {
+            pdf::extract_metadata(upload.file().path()).unwrap_or_else(|e| {
+              warn!("Failed to read the metadata of the uploaded PDF: {}", e);
+              Default::default()
+            })
+          } else {
+            Default::default()
+          };
+          Ok((param, Some(upload), meta))
+        })
+        .map_err(error::ErrorInternalServerError),
+      )
+    })
+    .and_then(|(param, upload, meta)| {
+      let (content, inferred) = pdf::infer(param, meta).map_err(error::ErrorBadRequest)?;
+      Ok((content, upload, inferred))
+    })
+    .and_then(move |(content, upload, inferred)| {
+      let db = data.db.clone();
+      data
+        .db
+        .send(db::Add::new(content, upload))
+        .from_err()
+        .and_then(move |result| match result {
+          Ok(article) => {
+            if let Some(attachment) = article.content().primary() {
+              if attachment.media_type == db::MediaType::Pdf {
+                extract(db, article.id(), article.attachment_path(attachment));
+              }
+            }
+            future::Either::A(future::ok(web::Json(Added {
+              article,
+              inferred,
+              duplicate: false,
+            })))
+          }
+          Err(e) => match (on_duplicate, e.downcast::<db::AttachmentError>()) {
+            (OnDuplicate::Existing, Ok(db::AttachmentError::Duplicate(id))) => {
+              future::Either::B(db.send(db::Get::new(id)).then(|result| {
+                Ok(web::Json(Added {
+                  article: result??,
+                  inferred: Vec::new(),
+                  duplicate: true,
+                }))
+              }))
+            }
+            (_, Ok(e)) => future::Either::A(future::err(db_error(e.into()))),
+            (_, Err(e)) => future::Either::A(future::err(db_error(e))),
+          },
+        })
+    })
+}
+
+fn import(
+  data: web::Data<Arc<AppData>>,
+  multipart: Multipart,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  multipart
+    .map_err(error::ErrorInternalServerError)
+    .fold(None, |file, field| -> Box<dyn Future<Item = _, Error = _>> {
+      let cd = field.content_disposition();
+      let name = cd.as_ref().and_then(|cd| cd.get_name().map(str::to_owned));
+      let filename = cd.and_then(|cd| cd.get_filename().map(str::to_owned));
+      match (name.as_deref(), file) {
+        (Some("file"), None) => Box::new(read_file_field(field).map(|file| Some((filename, file)))),
+        (Some("file"), Some(_)) => Box::new(future::err(error::ErrorBadRequest(format_err!(
+          "Only one 'file' can be imported"
+        )))),
+        _ => Box::new(future::err(error::ErrorInternalServerError(format_err!(
+          "Unknown name"
+        )))),
+      }
+    })
+    .and_then(|file| {
+      file.ok_or_else(|| error::ErrorBadRequest(format_err!("'file' is not provided")))
+    })
+    .and_then(|(filename, file)| {
+      web::block(move || import::read(filename.as_deref(), file.path())).map_err(|e| match e {
+        error::BlockingError::Error(e) => match e.downcast::<import::ImportError>() {
+          Ok(e) => error::ErrorBadRequest(e),
+          Err(e) => error::ErrorInternalServerError(e),
+        },
+        error::BlockingError::Canceled => error::ErrorInternalServerError("Canceled"),
+      })
+    })
+    .and_then(move |entries| {
+      let contents = import::contents(&entries);
+      data
+        .db
+        .send(db::Import::new(contents))
+        .then(move |result| Ok(web::Json(import::report(entries, result??)?)))
+    })
+}
+
+fn delete(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<u64>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  data
+    .db
+    .send(db::Remove::new(*path))
+    .then(|result| Ok(web::Json(result??)))
+}
+
+fn update(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<u64>,
+  patch: web::Json<db::ArticlePatch>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  data
+    .db
+    .send(db::Update::new(*path, patch.into_inner()))
+    .then(|result| Ok(web::Json(result?.map_err(db_error)?)))
+}
+
+#[derive(Serialize)]
+struct Replaced {
+  #[serde(flatten)]
+  article: db::Article,
+  revision: Option<u32>,
+}
+
+fn replace_file(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<u64>,
+  multipart: Multipart,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  let id = *path;
+  let db = data.db.clone();
+  read_upload(multipart)
+    .and_then(move |upload| {
+      data
+        .db
+        .send(db::Get::new(id))
+        .then(|result| Ok((result??, upload)))
+    })
+    .and_then(|(article, upload)| {
+      web::block(move || db::Replacement::new(&article, upload))
+        .map_err(error::ErrorInternalServerError)
+    })
+    .and_then(move |replacement| {
+      db.send(db::ReplaceFile::new(id, replacement))
+        .then(move |result| {
+          let (article, revision) = result??;
+          index_text(db, &article);
+          Ok(web::Json(Replaced { article, revision }))
+        })
+    })
+}
+
+fn attach(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<u64>,
+  multipart: Multipart,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  let id = *path;
+  read_upload(multipart).and_then(move |upload| {
+    let db = data.db.clone();
+    data.db.send(db::Attach::new(id, upload)).then(move |result| {
+      let article = result?.map_err(db_error)?;
+      if article.content().attachments.len() == 1 {
+        index_text(db, &article);
+      }
+      Ok(web::Json(article))
+    })
+  })
+}
+
+fn detach(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<(u64, u32)>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  let (id, attachment) = *path;
+  let db = data.db.clone();
+  data
+    .db
+    .send(db::Detach::new(id, attachment))
+    .then(move |result| {
+      let article = result?.map_err(db_error)?;
+      if article.content().primary().is_none_or(|a| attachment < a.id) {
+        index_text(db, &article);
+      }
+      Ok(web::Json(article))
+    })
+}
+
+fn view_attachment(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<(u64, u32)>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  let (id, attachment) = *path;
+  data.db.send(db::Get::new(id)).then(move |result| {
+    let article = result??;
+    let attachment = article
+      .content()
+      .attachment(attachment)
+      .ok_or_else(|| error::ErrorNotFound(db::AttachmentError::NotFound(id, attachment)))?;
+    serve(
+      &article.attachment_path(attachment),
+      attachment.media_type,
+      attachment
+        .name
+        .clone()
+        .unwrap_or_else(|| attachment.default_name(id)),
+    )
+  })
+}
+
+fn revisions(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<u64>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  data
+    .db
+    .send(db::Revisions::new(*path))
+    .then(|result| Ok(web::Json(result??)))
+}
+
+fn view_revision(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<(u64, u32)>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  let (id, revision) = *path;
+  data
+    .db
+    .send(db::Revision::new(id, revision))
+    .then(move |result| match result?? {
+      Some((path, media_type)) => serve(
+        &path,
+        media_type,
+        format!("{}.r{}.{}", id, revision, media_type.extension()),
+      ),
+      None => Err(error::ErrorNotFound(format_err!(
+        "The article id={} has no revision {}.",
+        id, revision
+      ))),
+    })
+}
+
+fn view(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<u64>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  data.db.send(db::Get::new(*path)).then(|result| {
+    let article = result??;
+    let attachment = match article.content().primary() {
+      Some(attachment) => attachment,
+      None => {
+        return Err(error::ErrorNotFound(format_err!(
+          "The article id={} has no file.",
+          article.id()
+        )))
+      }
+    };
+    serve(
+      &article.attachment_path(attachment),
+      attachment.media_type,
+      attachment.default_name(article.id()),
+    )
+  })
+}
+
+#[derive(Deserialize, Debug)]
+struct SearchQuery {
+  q: String,
+}
+
+fn search(
+  data: web::Data<Arc<AppData>>,
+  query: web::Query<SearchQuery>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  let query = match db::Query::parse(&query.q) {
+    Ok(query) => query,
+    Err(e) => return future::Either::A(future::err(error::ErrorBadRequest(e))),
+  };
+  future::Either::B(
+    data
+      .db
+      .send(db::Search::new(query))
+      .then(|result| Ok(web::Json(result??))),
+  )
+}
+
+const BIBTEX_CONTENT_TYPE: &str = "application/x-bibtex; charset=utf-8";
+
+fn bibtex(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<u64>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  data.db.send(db::Get::new(*path)).then(|result| {
+    let article = result??;
+    Ok(
+      HttpResponse::Ok()
+        .content_type(BIBTEX_CONTENT_TYPE)
+        .body(bibtex::render(&[article])),
+    )
+  })
+}
+
+fn export(
+  data: web::Data<Arc<AppData>>,
+  query: web::Query<SearchQuery>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  let render = |articles: Vec<db::Article>| {
+    HttpResponse::Ok()
+      .content_type(BIBTEX_CONTENT_TYPE)
+      .body(bibtex::render(&articles))
+  };
+  if query.q.trim().is_empty() {
+    let all = db::List::new(0, usize::MAX, db::Sort::Id, db::Order::Asc);
+    return future::Either::A(
+      data
+        .db
+        .send(all)
+        .then(move |result| Ok(render(result??.into_articles()))),
+    );
+  }
+  let query = match db::Query::parse(&query.q) {
+    Ok(query) => query,
+    Err(e) => return future::Either::B(future::Either::A(future::err(error::ErrorBadRequest(e)))),
+  };
+  future::Either::B(future::Either::B(
+    data
+      .db
+      .send(db::Search::new(query))
+      .then(move |result| Ok(render(result??))),
+  ))
+}
+
+#[derive(Deserialize, Debug)]
+struct DuplicatesQuery {
+  #[serde(default = "default_threshold")]
+  threshold: f64,
+}
+
+fn default_threshold() -> f64 {
+  0.8
+}
+
+fn duplicates(
+  data: web::Data<Arc<AppData>>,
+  query: web::Query<DuplicatesQuery>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  if !(query.threshold > 0.0 && query.threshold <= 1.0) {
+    return future::Either::A(future::err(error::ErrorBadRequest(format_err!(
+      "'threshold' must be in (0, 1]"
+    ))));
+  }
+  future::Either::B(
+    data
+      .db
+      .send(db::Duplicates::new(query.threshold))
+      .then(|result| Ok(web::Json(result??))),
+  )
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct MergeParam {
+  into: u64,
+  from: Vec<u64>,
+}
+
+fn merge(
+  data: web::Data<Arc<AppData>>,
+  param: web::Json<MergeParam>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  let param = param.into_inner();
+  data
+    .db
+    .send(db::Merge::new(param.into, param.from))
+    .then(|result| {
+      let (article, _) = result?.map_err(db_error)?;
+      Ok(web::Json(article))
+    })
+}
+
+#[derive(Deserialize, Debug)]
+struct ListQuery {
+  #[serde(default)]
+  offset: usize,
+  #[serde(default = "default_limit")]
+  limit: usize,
+  #[serde(default)]
+  sort: db::Sort,
+  #[serde(default)]
+  order: db::Order,
+}
+
+fn default_limit() -> usize {
+  50
+}
+
+const MAX_LIMIT: usize = 1000;
+
+fn list(
+  data: web::Data<Arc<AppData>>,
+  query: web::Query<ListQuery>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  let list = db::List::new(
+    query.offset,
+    query.limit.min(MAX_LIMIT),
+    query.sort,
+    query.order,
+  );
+  data.db.send(list).then(|result| Ok(web::Json(result??)))
+}
+
+#[derive(Deserialize, Debug)]
+struct CollectionsQuery {
+  kind: Option<db::Kind>,
+}
+
+fn collections(
+  data: web::Data<Arc<AppData>>,
+  query: web::Query<CollectionsQuery>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  data
+    .db
+    .send(db::ListCollections::new(query.kind))
+    .then(|result| Ok(web::Json(result??)))
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct NewCollection {
+  name: String,
+  #[serde(default)]
+  kind: db::Kind,
+  parent: Option<u64>,
+}
+
+fn create_collection(
+  data: web::Data<Arc<AppData>>,
+  param: web::Json<NewCollection>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  let param = param.into_inner();
+  data
+    .db
+    .send(db::CreateCollection::new(
+      param.kind,
+      param.name,
+      param.parent,
+    ))
+    .then(|result| Ok(web::Json(result?.map_err(db_error)?)))
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RenamedCollection {
+  name: String,
+}
+
+fn rename_collection(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<u64>,
+  param: web::Json<RenamedCollection>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  data
+    .db
+    .send(db::RenameCollection::new(*path, param.into_inner().name))
+    .then(|result| Ok(web::Json(result?.map_err(db_error)?)))
+}
+
+fn delete_collection(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<u64>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  data
+    .db
+    .send(db::DeleteCollection::new(*path))
+    .then(|result| Ok(web::Json(result?.map_err(db_error)?)))
+}
+
+fn add_member(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<(u64, u64)>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  let (collection, article) = *path;
+  data
+    .db
+    .send(db::Assign::new(collection, article, true))
+    .then(|result| Ok(web::Json(result?.map_err(db_error)?)))
+}
+
+fn remove_member(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<(u64, u64)>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  let (collection, article) = *path;
+  data
+    .db
+    .send(db::Assign::new(collection, article, false))
+    .then(|result| Ok(web::Json(result?.map_err(db_error)?)))
+}
+
+fn memberships(
+  data: web::Data<Arc<AppData>>,
+  path: web::Path<u64>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  data
+    .db
+    .send(db::Memberships::new(*path))
+    .then(|result| Ok(web::Json(result??)))
+}
+
+fn reindex_all(
+  data: web::Data<Arc<AppData>>,
+) -> impl Future<Item = impl Responder, Error = error::Error> {
+  data
+    .db
+    .send(db::Reindex)
+    .then(|result| Ok(web::Json(result??)))
+}
+
+/// Serves the store at `config.db_path` over HTTP until the process is stopped.
+pub fn start(config: Config) -> Result<(), Error> {
+  let system = actix::System::new("weid");
+
+  let data = Arc::new(AppData {
+    db: Db::open(&config)?,
+  });
+
+  HttpServer::new(move || {
+    App::new()
+      .data(data.clone())
+      .wrap(middleware::Logger::default())
+      .service(fs::Files::new("/assets", "assets"))
+      .route("/", web::get().to(index))
+      .route("/favicon.ico", web::get().to(favicon))
+      .route("/search", web::get().to_async(search))
+      .route("/export.bib", web::get().to_async(export))
+      .route("/add", web::post().to_async(add))
+      .route("/import", web::post().to_async(import))
+      .route("/duplicates", web::get().to_async(duplicates))
+      .route("/merge", web::post().to_async(merge))
+      .route("/admin/reindex", web::post().to_async(reindex_all))
+      .route("/delete/{id}", web::post().to_async(delete))
+      .route("/view/{id}", web::get().to_async(view))
+      .route("/articles", web::get().to_async(list))
+      .route("/articles/{id}", web::patch().to_async(update))
+      .route("/articles/{id}/file", web::put().to_async(replace_file))
+      .route("/articles/{id}/attachments", web::post().to_async(attach))
+      .route(
+        "/articles/{id}/attachments/{attachment}",
+        web::get().to_async(view_attachment),
+      )
+      .route(
+        "/articles/{id}/attachments/{attachment}",
+        web::delete().to_async(detach),
+      )
+      .route("/articles/{id}/revisions", web::get().to_async(revisions))
+      .route(
+        "/articles/{id}/revisions/{revision}",
+        web::get().to_async(view_revision),
+      )
+      .route("/articles/{id}/bibtex", web::get().to_async(bibtex))
+      .route("/articles/{id}/collections", web::get().to_async(memberships))
+      .route("/collections", web::get().to_async(collections))
+      .route("/collections", web::post().to_async(create_collection))
+      .route("/collections/{id}", web::patch().to_async(rename_collection))
+      .route("/collections/{id}", web::delete().to_async(delete_collection))
+      .route(
+        "/collections/{id}/articles/{article}",
+        web::put().to_async(add_member),
+      )
+      .route(
+        "/collections/{id}/articles/{article}",
+        web::delete().to_async(remove_member),
+      )
+  })
+  .workers(config.workers)
+  .bind((config.address.as_str(), config.port))?
+  .start();
+
+  Ok(system.run()?)
+}
@@ -0,0 +1,125 @@
+use std::io::Write;
+
+use failure::Error;
+use tempfile::{tempdir, NamedTempFile, TempDir};
+
+use weid::collection::RBTree;
+use weid::config::Config;
+use weid::db::{self, ArticleContent, Db, Store};
+
+fn config(dir: &TempDir) -> Config {
+  Config {
+    db_path: dir.path().to_owned(),
+    map_size: 1 << 24,
+    ..Default::default()
+  }
+}
+
+#[test]
+fn test_store() -> Result<(), Error> {
+  let dir = tempdir()?;
+  let mut store = Store::open(&config(&dir))?;
+
+  let mut file = NamedTempFile::new()?;
+  file.write_all(b"%PDF-1.4 literate")?;
+  let upload = db::Upload::new(file, Some("knuth.pdf".to_owned()))?;
+  let content = ArticleContent::new("Literate Programming", ["Donald E. Knuth"])?;
+  let article = store.add(content, Some(upload))?;
+  let attachment = article.content().primary().unwrap();
+  assert_eq!(attachment.media_type, db::MediaType::Pdf);
+  assert!(article.attachment_path(attachment).exists());
+
+  let content = ArticleContent::new("Structured Programming", ["Edsger W. Dijkstra"])?;
+  let other = store.add(content, None)?;
+
+  let found = store.search(&db::Query::parse("author:knuth")?)?;
+  assert_eq!(
+    found.iter().map(db::Article::id).collect::<Vec<_>>(),
+    vec![article.id()]
+  );
+
+  store.remove(article.id())?;
+  assert!(store.get(article.id()).is_err());
+  assert_eq!(
+    store.get(other.id())?.content().title,
+    "Structured Programming"
+  );
+  Ok(())
+}
+
+#[test]
+fn test_actor() -> Result<(), Error> {
+  let dir = tempdir()?;
+  let mut system = actix::System::new("test");
+  let db = Db::open(&Config {
+    workers: 4,
+    ..config(&dir)
+  })?;
+  let content = ArticleContent::new("Literate Programming", ["Donald E. Knuth"])?;
+  let article = system.block_on(db.send(db::Add::new(content, None)))??;
+  let got = system.block_on(db.send(db::Get::new(article.id())))??;
+  assert_eq!(got.content(), article.content());
+
+  let query = db::Query::parse("author:knuth")?;
+  let searches = futures::future::lazy(|| {
+    futures::future::join_all((0..32).map(|_| db.send(db::Search::new(query.clone()))))
+  });
+  for found in system.block_on(searches)? {
+    assert_eq!(found?.len(), 1);
+  }
+
+  system.block_on(db.send(db::Remove::new(article.id())))??;
+  assert!(system
+    .block_on(db.send(db::Get::new(article.id())))?
+    .is_err());
+  Ok(())
+}
+
+#[test]
+fn test_reindex_actor() -> Result<(), Error> {
+  let dir = tempdir()?;
+  let mut system = actix::System::new("test");
+  let db = Db::open(&config(&dir))?;
+  for title in ["Literate Programming", "Concrete Mathematics"].iter() {
+    let content = ArticleContent::new(*title, ["Donald E. Knuth"])?;
+    system.block_on(db.send(db::Add::new(content, None)))??;
+  }
+  assert!(Store::reindex(&config(&dir), |_| {}).is_err());
+  let reindexed = system.block_on(db.send(db::Reindex))??;
+  assert_eq!((reindexed.articles, reindexed.attachments), (2, 0));
+  let found = system.block_on(db.send(db::Search::new(db::Query::parse("author:knuth")?)))??;
+  assert_eq!(found.len(), 2);
+  Ok(())
+}
+
+#[test]
+fn test_import() -> Result<(), Error> {
+  let dir = tempdir()?;
+  let mut store = Store::open(&config(&dir))?;
+
+  let path = dir.path().join("refs.bib");
+  std::fs::write(
+    &path,
+    "@book{knuth1984, title = {Literate Programming}, author = {Knuth, Donald}, year = 1984}\n\
+     @book{broken, author = {Nobody}}\n",
+  )?;
+  let entries = weid::import::read(Some("refs.bib"), &path)?;
+  let contents = weid::import::contents(&entries);
+  let imported = store.import(contents)?;
+  let report = weid::import::report(entries, imported)?;
+  assert_eq!((report.added, report.duplicates, report.invalid), (1, 0, 1));
+  Ok(())
+}
+
+#[test]
+fn test_rbtree() -> Result<(), Error> {
+  let file = tempfile::tempfile()?;
+  let mut tree = RBTree::<u64>::create(file)?;
+  for val in [5, 1, 4, 2, 3].iter() {
+    tree.add(*val)?;
+  }
+  tree.del(4)?;
+  assert_eq!(tree.iter().collect::<Vec<_>>(), vec![1, 2, 3, 5]);
+  assert!(tree.violations().is_empty());
+  Ok(())
+}